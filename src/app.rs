@@ -1,30 +1,76 @@
-use crate::config::{Config, edit_config_file};
+use crate::config::{
+    AppsSort, Config, Hotkey, HotkeyAction, MINIMIZE_OTHERS_HOTKEY_ID,
+    MOVE_TO_NEXT_MONITOR_HOTKEY_ID, MouseTrigger, PAUSE_HOTKEY_ID, PIN_HOTKEY_ID, SEARCH_HOTKEY_ID,
+    SWITCH_ALL_WINDOWS_HOTKEY_ID, SWITCH_APPS_HOTKEY_ID, SWITCH_WINDOWS_HOTKEY_ID,
+    TOPMOST_HOTKEY_ID, TRANSPARENCY_HOTKEY_ID, edit_config_file,
+};
+use crate::crash::install_crash_handlers;
 use crate::foreground::ForegroundWatcher;
-use crate::keyboard::KeyboardListener;
-use crate::painter::{GdiAAPainter, find_clicked_app_index};
+use crate::keyboard::{KeyboardListener, is_key_held, is_modifier_held};
+use crate::matcher::rank;
+use crate::mouse::MouseListener;
+use crate::mouse_trigger::MouseTriggerListener;
+use crate::painter::{Painter, SearchPainter, TitlesPainter};
 use crate::startup::Startup;
-use crate::trayicon::TrayIcon;
+use crate::statistics::Statistics;
+use crate::storage::Storage;
+use crate::tooltip::Tooltip;
+use crate::trayicon::{BalloonAction, TrayIcon};
 use crate::utils::{
-    RELOAD_CONFIG_EVENT_NAME, check_error, get_app_icon, get_foreground_window,
-    get_window_user_data, is_iconic_window, is_running_as_admin, list_windows,
-    set_foreground_window, set_window_user_data,
+    ListWindowsOptions, Win32Api, WinApi, check_error, flash_window, get_icon_accent_color,
+    get_system_dpi, get_window_title, get_window_user_data, is_key_down, is_remote_session,
+    is_running_as_admin, is_window, launch_app, launch_app_elevated, launch_command,
+    reload_config_event_name, reset_virtual_desktop_manager, resolve_virtual_desktops,
+    second_instance_event_name, set_foreground_window, set_window_user_data, to_wstring,
+    watch_virtual_desktops,
+};
+use crate::window_menu::{WindowAction, show_window_menu};
+use crate::window_ops::{
+    Snap, close_window, cycle_opacity, has_several_monitors, minimize_others, minimize_window,
+    move_to_next_monitor, restore_windows, snap_window, toggle_topmost,
 };
 
 use anyhow::{Result, anyhow};
 use indexmap::IndexSet;
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
 use windows::Win32::{
     Foundation::{GetLastError, HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
-    System::LibraryLoader::GetModuleHandleW,
-    UI::WindowsAndMessaging::{
-        CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, CreateWindowExW, DefWindowProcW, DispatchMessageW,
-        GWL_STYLE, GetMessageW, GetWindowLongPtrW, HICON, HTCLIENT, IDC_ARROW, LoadCursorW, MSG,
-        PostMessageW, PostQuitMessage, RegisterClassW, RegisterWindowMessageW, SetWindowLongPtrW,
-        TranslateMessage, WINDOW_STYLE, WM_COMMAND, WM_ERASEBKGND, WM_LBUTTONUP, WM_NCHITTEST,
-        WM_RBUTTONUP, WNDCLASSW, WS_CAPTION, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    System::{
+        Com::{COINIT_APARTMENTTHREADED, CoInitializeEx},
+        LibraryLoader::GetModuleHandleW,
+        RemoteDesktop::{
+            NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification,
+            WTSUnRegisterSessionNotification,
+        },
+    },
+    UI::{
+        Controls::{HOVER_DEFAULT, WM_MOUSEHOVER, WM_MOUSELEAVE},
+        HiDpi::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, SetProcessDpiAwarenessContext},
+        Input::KeyboardAndMouse::{
+            ReleaseCapture, SetCapture, TME_HOVER, TME_LEAVE, TRACKMOUSEEVENT, TrackMouseEvent,
+            VK_CONTROL, VK_DOWN, VK_SHIFT, VK_UP,
+        },
+        WindowsAndMessaging::{
+            CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, CreateWindowExW, DefWindowProcW,
+            DispatchMessageW, GWL_STYLE, GetMessageW, GetWindowLongPtrW, HICON, HTCLIENT,
+            IDC_ARROW, KillTimer, LoadCursorW, MB_ICONINFORMATION, MB_OK, MSG, MessageBoxW,
+            PBT_APMRESUMEAUTOMATIC, PostMessageW, PostQuitMessage, RegisterClassW,
+            RegisterWindowMessageW, SPI_SETHIGHCONTRAST, SetTimer, SetWindowLongPtrW,
+            TranslateMessage, USER_DEFAULT_SCREEN_DPI, WHEEL_DELTA, WINDOW_STYLE,
+            WM_CAPTURECHANGED, WM_COMMAND, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_ENDSESSION,
+            WM_ERASEBKGND, WM_HOTKEY, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONUP, WM_MOUSEMOVE,
+            WM_MOUSEWHEEL, WM_NCHITTEST, WM_POWERBROADCAST, WM_QUERYENDSESSION, WM_RBUTTONUP,
+            WM_SETTINGCHANGE, WM_SYSCOLORCHANGE, WM_THEMECHANGED, WM_TIMER, WM_WTSSESSION_CHANGE,
+            WNDCLASSW, WS_CAPTION, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+            WTS_CONSOLE_CONNECT, WTS_REMOTE_CONNECT, WTS_SESSION_UNLOCK,
+        },
     },
 };
-use windows::core::{PCWSTR, w};
+use windows::core::{GUID, PCWSTR, w};
 
 pub const NAME: PCWSTR = w!("Window Switcher");
 pub const WM_USER_TRAYICON: u32 = 6000;
@@ -32,12 +78,85 @@ pub const WM_USER_REGISTER_TRAYICON: u32 = 6001;
 pub const WM_USER_SWITCH_APPS: u32 = 6010;
 pub const WM_USER_SWITCH_APPS_DONE: u32 = 6011;
 pub const WM_USER_SWITCH_APPS_CANCEL: u32 = 6012;
+/// The switch apps key is released while the modifier is still held.
+pub const WM_USER_SWITCH_APPS_KEY_UP: u32 = 6013;
+/// Moves the selection with the navigation keys while the overlay is shown.
+pub const WM_USER_SWITCH_APPS_SELECT: u32 = 6014;
+/// Switches to the app at the index in lparam, sent by the number keys.
+pub const WM_USER_SWITCH_APPS_PICK: u32 = 6015;
+/// Acts on the window of the selected app while the overlay is shown.
+pub const WM_USER_SWITCH_APPS_ACTION: u32 = 6016;
+/// Switches to the app used before the foreground one without the overlay, sent by
+/// double-tapping the modifier.
+pub const WM_USER_SWITCH_APPS_LAST: u32 = 6017;
+/// lparam flags of `WM_USER_SWITCH_APPS`.
+pub const SWITCH_APPS_REVERSE: isize = 1;
+pub const SWITCH_APPS_REPEAT: isize = 2;
+/// Sent by a mouse trigger instead of the hotkey.
+pub const SWITCH_APPS_MOUSE: isize = 4;
+/// lparam of `WM_USER_SWITCH_APPS_DONE` to start a new instance of the selected app.
+pub const SWITCH_APPS_DONE_NEW_INSTANCE: isize = 1;
+/// lparam of `WM_USER_SWITCH_APPS_DONE` to start the selected app as administrator.
+pub const SWITCH_APPS_DONE_ELEVATED: isize = 2;
+/// lparams of `WM_USER_SWITCH_APPS_DONE` to snap the selected app after switching to it.
+pub const SWITCH_APPS_DONE_SNAP_LEFT: isize = 3;
+pub const SWITCH_APPS_DONE_SNAP_RIGHT: isize = 4;
+pub const SWITCH_APPS_DONE_MAXIMIZE: isize = 5;
+/// lparams of `WM_USER_SWITCH_APPS_SELECT`.
+pub const SWITCH_APPS_SELECT_NEXT: isize = 0;
+pub const SWITCH_APPS_SELECT_PREVIOUS: isize = 1;
+pub const SWITCH_APPS_SELECT_FIRST: isize = 2;
+pub const SWITCH_APPS_SELECT_LAST: isize = 3;
+/// lparams of `WM_USER_SWITCH_APPS_ACTION`.
+pub const SWITCH_APPS_ACTION_CLOSE: isize = 0;
+pub const SWITCH_APPS_ACTION_MINIMIZE: isize = 1;
 pub const WM_USER_SWITCH_WINDOWS: u32 = 6020;
+/// lparam flags of `WM_USER_SWITCH_WINDOWS`.
+pub const SWITCH_WINDOWS_REVERSE: isize = 1;
+/// Cycles through the windows of all apps instead of those of the foreground app.
+pub const SWITCH_WINDOWS_ALL: isize = 2;
 pub const WM_USER_SWITCH_WINDOWS_DONE: u32 = 6021;
+pub const WM_USER_SWITCH_WINDOWS_CANCEL: u32 = 6022;
 pub const WM_USER_RELOAD_CONFIG: u32 = 6030;
+pub const WM_USER_SECOND_INSTANCE: u32 = 6031;
+/// Posted once the virtual desktops changed, the excluded desktops are resolved again.
+pub const WM_USER_DESKTOPS_CHANGED: u32 = 6032;
+/// Sent by the keyboard hook for action hotkeys, wparam is the hotkey id.
+pub const WM_USER_HOTKEY: u32 = 6040;
+/// Sent by the search palette for a typed character, wparam is the character.
+pub const WM_USER_SEARCH_CHAR: u32 = 6050;
+/// Sent by the search palette for a pressed key, wparam is the virtual key.
+pub const WM_USER_SEARCH_KEY: u32 = 6051;
+/// Sent by the search palette once it lost the focus.
+pub const WM_USER_SEARCH_CLOSE: u32 = 6052;
+/// Advances the selection while the switch apps key is held.
+pub const TIMER_SLIDESHOW: usize = 1;
+/// Steps the fade in or out of the overlay.
+pub const TIMER_FADE: usize = 2;
+/// Slides the selection highlight of the overlay.
+pub const TIMER_SLIDE: usize = 3;
+/// Lists the apps again once a window closed from the overlay is gone.
+pub const TIMER_REFRESH: usize = 4;
+
+const REFRESH_DELAY: u32 = 200;
+/// Switches to the selected app once the wheel stopped tilting.
+pub const TIMER_TILT: usize = 5;
+const TILT_DELAY: u32 = 600;
+/// Shows the overlay once the switch apps hotkey has been held for the overlay delay.
+pub const TIMER_OVERLAY: usize = 6;
+/// Polls the modifiers of a registered switch hotkey, which reports no key releases.
+pub const TIMER_HOTKEY_RELEASE: usize = 7;
+const HOTKEY_RELEASE_DELAY: u32 = 30;
+/// Saves the state, so a crash or a forced shutdown loses little of the statistics.
+pub const TIMER_SAVE: usize = 8;
+const SAVE_INTERVAL: u32 = 5 * 60 * 1000;
+/// The balloon telling that Windows refused a switch is shown at most this often.
+const ACTIVATE_BALLOON_INTERVAL: Duration = Duration::from_secs(10 * 60);
 pub const IDM_EXIT: u32 = 1;
 pub const IDM_STARTUP: u32 = 2;
 pub const IDM_CONFIGURE: u32 = 3;
+pub const IDM_PAUSE: u32 = 4;
+pub const IDM_STATISTICS: u32 = 5;
 
 pub fn start(config: &Config) -> Result<()> {
     info!("start config={config:?}");
@@ -56,21 +175,85 @@ pub struct App {
     config: Config,
     switch_windows_state: SwitchWindowsState,
     switch_apps_state: Option<SwitchAppsState>,
-    cached_icons: HashMap<String, HICON>,
-    painter: GdiAAPainter,
+    /// Icons by module path and the size they were extracted for.
+    cached_icons: HashMap<(String, i32), CachedIcon>,
+    painter: Painter,
+    /// Lists the windows while switching windows, when enabled.
+    titles_painter: Option<TitlesPainter>,
+    /// Windows found by the search palette while it is open.
+    search_state: Option<SearchState>,
+    search_painter: Option<SearchPainter>,
     keyboard_listener: KeyboardListener,
+    /// Only installed while the switch apps overlay is shown.
+    mouse_listener: Option<MouseListener>,
+    mouse_trigger: Option<MouseTriggerListener>,
+    /// Names the app under the mouse, shown once the mouse rests on an icon.
+    tooltip: Option<Tooltip>,
+    foreground_watcher: ForegroundWatcher,
+    storage: Storage,
+    win_api: Box<dyn WinApi>,
+    /// Windows minimized by the minimize-others hotkey, restored on the next press.
+    minimized_others: Vec<HWND>,
+    /// Windows made layered by the transparency hotkey.
+    layered_windows: HashSet<isize>,
+    slideshow: Option<Slideshow>,
+    /// The overlay waits for the overlay delay before it is painted.
+    overlay_pending: bool,
+    /// Registered switch hotkey whose modifiers are held, the switch ends once released.
+    held_hotkey: Option<Hotkey>,
+    /// Windows pinned by the pin hotkey.
+    pinned_windows: Rc<HashSet<isize>>,
+    /// `pin_apps` of the config, built once per load for the list windows options.
+    pinned_apps: Rc<HashSet<String>>,
+    /// `exclude_desktops` of the config resolved into IDs, until the desktops change.
+    excluded_desktops: OnceCell<Rc<HashSet<GUID>>>,
+    /// Names of the hotkeys by id for the statistics, built once per load.
+    hotkey_names: HashMap<u32, String>,
+    /// When the balloon about a refused switch was shown last.
+    activate_balloon_shown: Option<Instant>,
 }
 
 impl App {
     pub fn start(config: &Config) -> Result<()> {
+        install_crash_handlers();
+
+        // Lay out the overlay in physical pixels of each monitor instead of letting Windows
+        // stretch it on monitors with another scale
+        if let Err(err) =
+            unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) }
+        {
+            warn!("Failed to enable per-monitor DPI awareness, {err}");
+        }
+
+        // COM is used for the virtual desktops
+        let _ = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) };
+
         let hwnd = Self::create_window()?;
-        let painter = GdiAAPainter::new(hwnd)?;
+        let painter = Painter::new(hwnd, config)?;
 
-        let _foreground_watcher = ForegroundWatcher::init(&config.switch_windows_blacklist)?;
-        let _keyboard_listener = KeyboardListener::init(hwnd, &config.to_hotkeys())?;
+        let foreground_watcher = ForegroundWatcher::init(
+            &config.hotkey_blacklists,
+            config.on_foreground_change.as_deref(),
+            config.pause_in_full_screen,
+        )?;
+        let mut keyboard_listener =
+            KeyboardListener::init(hwnd, &config.to_hotkeys(), config.hotkey_mode)?;
+        keyboard_listener.set_quick_select(config.overlay_quick_select);
+        keyboard_listener.set_follow_layout(config.hotkeys_follow_layout);
+        keyboard_listener.set_cancel_keys(&config.cancel_keys);
+        keyboard_listener.set_sticky(config.switch_apps_sticky_mode);
+        keyboard_listener.set_inspect(config.switch_apps_inspect);
+        keyboard_listener.set_double_tap_interval(config.switch_apps_double_tap_interval);
+        keyboard_listener.set_action_keys(
+            config.switch_apps_close_key,
+            config.switch_apps_minimize_key,
+        );
+        if config.remote_session_release_hook && is_remote_session() {
+            keyboard_listener.uninstall();
+        }
 
         let trayicon = match config.trayicon {
-            true => Some(TrayIcon::create()),
+            true => Some(TrayIcon::create(config.respect_focus_assist)),
             false => None,
         };
 
@@ -79,23 +262,54 @@ impl App {
 
         let startup = Startup::init(is_admin)?;
 
+        let storage = Storage::load(config.instance_name.as_deref());
+        let switch_windows_state = SwitchWindowsState {
+            last_windows: storage
+                .last_windows
+                .iter()
+                .map(|(module_path, hwnd)| (module_path.clone(), HWND(*hwnd as _)))
+                .collect(),
+            ..Default::default()
+        };
         let mut app = App {
             hwnd,
             is_admin,
             trayicon,
             startup,
             config: config.clone(),
-            switch_windows_state: SwitchWindowsState {
-                cache: None,
-                modifier_released: true,
-            },
+            switch_windows_state,
             switch_apps_state: None,
             cached_icons: Default::default(),
             painter,
-            keyboard_listener: _keyboard_listener,
+            titles_painter: None,
+            search_state: None,
+            search_painter: None,
+            keyboard_listener,
+            mouse_listener: None,
+            mouse_trigger: None,
+            tooltip: Tooltip::create(hwnd).map_err(|err| error!("{err}")).ok(),
+            foreground_watcher,
+            storage,
+            win_api: Box::new(Win32Api),
+            minimized_others: vec![],
+            layered_windows: Default::default(),
+            slideshow: None,
+            overlay_pending: false,
+            held_hotkey: None,
+            pinned_windows: Default::default(),
+            pinned_apps: Rc::new(config.pin_apps.clone()),
+            excluded_desktops: OnceCell::new(),
+            hotkey_names: hotkey_names(config),
+            activate_balloon_shown: None,
         };
 
         app.set_trayicon();
+        app.start_mouse_trigger();
+
+        // Get notified on unlock and remote desktop reconnects to revalidate the hook
+        if let Err(err) = unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } {
+            error!("Failed to register session notification, {err}");
+        }
 
         // SAFETY: We store the App in user data to be retrieved by window_proc callbacks.
         // The pointer remains valid for the lifetime of the window and is properly
@@ -104,21 +318,37 @@ impl App {
 
         check_error(|| set_window_user_data(hwnd, app_ptr))
             .map_err(|err| anyhow!("Failed to set window ptr, {err}"))?;
+        unsafe { SetTimer(Some(hwnd), TIMER_SAVE, SAVE_INTERVAL, None) };
 
-        // Start the reload config event listener
-        Self::start_reload_config_listener(hwnd)?;
+        // Start the listeners for events signaled by other launches of the program
+        let instance_id = config.instance_id();
+        Self::start_event_listener(
+            hwnd,
+            &reload_config_event_name(instance_id.as_deref()),
+            WM_USER_RELOAD_CONFIG,
+        )?;
+        Self::start_event_listener(
+            hwnd,
+            &second_instance_event_name(instance_id.as_deref()),
+            WM_USER_SECOND_INSTANCE,
+        )?;
+        // The key is missing until desktops were used, they are then resolved once per reload
+        if let Err(err) = watch_virtual_desktops(hwnd, WM_USER_DESKTOPS_CHANGED) {
+            debug!("{err}");
+        }
 
         Self::eventloop()
     }
 
-    fn start_reload_config_listener(hwnd: HWND) -> Result<()> {
+    /// Posts `msg` to the window whenever the named event is signaled.
+    fn start_event_listener(hwnd: HWND, event_name: &str, msg: u32) -> Result<()> {
         use crate::utils::to_wstring;
         use windows::Win32::Foundation::{HANDLE, WAIT_OBJECT_0};
         use windows::Win32::System::Threading::{CreateEventW, INFINITE, WaitForSingleObject};
 
-        let event_name = to_wstring(RELOAD_CONFIG_EVENT_NAME);
+        let event_name = to_wstring(event_name);
         let event = unsafe { CreateEventW(None, false, false, PCWSTR(event_name.as_ptr())) }
-            .map_err(|err| anyhow!("Failed to create reload config event, {err}"))?;
+            .map_err(|err| anyhow!("Failed to create event, {err}"))?;
 
         let hwnd_ptr = hwnd.0 as isize;
         let event_ptr = event.0 as isize;
@@ -128,12 +358,7 @@ impl App {
                 let result = unsafe { WaitForSingleObject(event, INFINITE) };
                 if result == WAIT_OBJECT_0 {
                     let _ = unsafe {
-                        PostMessageW(
-                            Some(HWND(hwnd_ptr as _)),
-                            WM_USER_RELOAD_CONFIG,
-                            WPARAM(0),
-                            LPARAM(0),
-                        )
+                        PostMessageW(Some(HWND(hwnd_ptr as _)), msg, WPARAM(0), LPARAM(0))
                     };
                 }
             }
@@ -215,9 +440,10 @@ impl App {
             match trayicon.register(self.hwnd) {
                 Ok(()) => {
                     info!("trayicon registered");
-                    let _ = trayicon.show_balloon(
+                    let _ = trayicon.show_balloon_with_action(
                         "Window Switcher",
                         "Ready to switch! Your windows are now under control 🪟",
+                        Some(BalloonAction::Pause),
                     );
                 }
                 Err(err) => {
@@ -265,27 +491,146 @@ impl App {
         match msg {
             WM_USER_TRAYICON => {
                 let app = get_app(hwnd)?;
+                let mut balloon_action = None;
                 if let Some(trayicon) = app.trayicon.as_mut() {
                     let keycode = lparam.0 as u32;
                     if keycode == WM_LBUTTONUP || keycode == WM_RBUTTONUP {
-                        trayicon.show(app.startup.is_enable)?;
+                        trayicon.show(app.startup.is_enable, app.keyboard_listener.is_paused())?;
+                    } else {
+                        balloon_action = trayicon.on_balloon_event(keycode);
+                    }
+                }
+                match balloon_action {
+                    Some(BalloonAction::OpenConfig) => {
+                        if let Err(err) = edit_config_file() {
+                            alert!("{err}");
+                        }
                     }
+                    Some(BalloonAction::Pause) if !app.keyboard_listener.is_paused() => {
+                        app.toggle_pause();
+                    }
+                    _ => {}
                 }
                 return Ok(LRESULT(0));
             }
             WM_USER_SWITCH_APPS => {
                 debug!("message WM_USER_SWITCH_APPS");
                 let app = get_app(hwnd)?;
-                let reverse = lparam.0 == 1;
-                app.switch_apps(reverse)?;
-                if let Some(state) = &app.switch_apps_state {
-                    app.painter.paint(state);
+                let reverse = lparam.0 & SWITCH_APPS_REVERSE != 0;
+                let repeat = lparam.0 & SWITCH_APPS_REPEAT != 0;
+                let is_mouse = lparam.0 & SWITCH_APPS_MOUSE != 0;
+                // The slideshow takes over from the auto-repeat of the key
+                if !(repeat && app.config.switch_apps_slideshow) {
+                    let started = Instant::now();
+                    let is_new = app.switch_apps_state.is_none();
+                    app.switch_apps(reverse)?;
+                    let delay = app.config.switch_apps_overlay_delay;
+                    if app.switch_apps_state.is_some() {
+                        if is_new && delay > 0 && app.config.switch_apps_show_ui {
+                            // Switching before the delay is up flips apps without any UI
+                            app.overlay_pending = true;
+                            unsafe { SetTimer(Some(hwnd), TIMER_OVERLAY, delay, None) };
+                        } else if !app.overlay_pending {
+                            app.paint_switch_apps();
+                        }
+                        if is_new {
+                            if !app.overlay_pending {
+                                app.start_mouse_listener();
+                            }
+                            let statistics = &mut app.storage.statistics;
+                            statistics.record_hotkey(&app.config.switch_apps_hotkey.name);
+                            statistics.record_latency(started.elapsed());
+                        }
+                    }
+                    if is_mouse {
+                        if app.config.switch_apps_mouse_trigger == MouseTrigger::Tilt {
+                            unsafe { SetTimer(Some(hwnd), TIMER_TILT, TILT_DELAY, None) };
+                        }
+                    } else if !repeat {
+                        app.start_slideshow(reverse);
+                    }
+                }
+                // Tells the keyboard hook whether there is an overlay to keep open in sticky mode,
+                // switching blind shows none
+                let is_open = app.switch_apps_state.is_some() && app.config.switch_apps_show_ui;
+                return Ok(LRESULT(is_open as isize));
+            }
+            WM_USER_SWITCH_APPS_KEY_UP => {
+                debug!("message WM_USER_SWITCH_APPS_KEY_UP");
+                let app = get_app(hwnd)?;
+                app.stop_slideshow();
+            }
+            WM_USER_SWITCH_APPS_SELECT => {
+                debug!("message WM_USER_SWITCH_APPS_SELECT");
+                let app = get_app(hwnd)?;
+                app.select_app(lparam.0);
+            }
+            WM_USER_SWITCH_APPS_PICK => {
+                debug!("message WM_USER_SWITCH_APPS_PICK");
+                let app = get_app(hwnd)?;
+                app.pick_app(lparam.0 as usize);
+            }
+            WM_USER_SWITCH_APPS_LAST => {
+                debug!("message WM_USER_SWITCH_APPS_LAST");
+                let app = get_app(hwnd)?;
+                app.switch_to_last_app()?;
+            }
+            WM_USER_SWITCH_APPS_ACTION => {
+                debug!("message WM_USER_SWITCH_APPS_ACTION");
+                let app = get_app(hwnd)?;
+                match lparam.0 {
+                    SWITCH_APPS_ACTION_CLOSE => app.close_selected_window(),
+                    SWITCH_APPS_ACTION_MINIMIZE => app.minimize_selected_window(),
+                    _ => {}
+                }
+            }
+            WM_TIMER if wparam.0 == TIMER_SLIDESHOW => {
+                let app = get_app(hwnd)?;
+                app.slideshow_tick()?;
+            }
+            WM_TIMER if wparam.0 == TIMER_FADE => {
+                let app = get_app(hwnd)?;
+                app.painter.on_fade_timer();
+            }
+            WM_TIMER if wparam.0 == TIMER_TILT => {
+                let _ = unsafe { KillTimer(Some(hwnd), TIMER_TILT) };
+                let app = get_app(hwnd)?;
+                app.do_switch_app();
+            }
+            WM_TIMER if wparam.0 == TIMER_OVERLAY => {
+                let _ = unsafe { KillTimer(Some(hwnd), TIMER_OVERLAY) };
+                let app = get_app(hwnd)?;
+                app.show_pending_overlay();
+            }
+            WM_TIMER if wparam.0 == TIMER_REFRESH => {
+                let _ = unsafe { KillTimer(Some(hwnd), TIMER_REFRESH) };
+                let app = get_app(hwnd)?;
+                app.refresh_switch_apps()?;
+            }
+            WM_TIMER if wparam.0 == TIMER_SAVE => {
+                let app = get_app(hwnd)?;
+                app.save_storage(true);
+            }
+            WM_TIMER if wparam.0 == TIMER_SLIDE => {
+                let app = get_app(hwnd)?;
+                match &app.switch_apps_state {
+                    Some(state) => app.painter.on_slide_timer(state),
+                    None => {
+                        let _ = unsafe { KillTimer(Some(hwnd), TIMER_SLIDE) };
+                    }
                 }
             }
             WM_USER_SWITCH_APPS_DONE => {
                 debug!("message WM_USER_SWITCH_APPS_DONE");
                 let app = get_app(hwnd)?;
-                app.do_switch_app();
+                match lparam.0 {
+                    SWITCH_APPS_DONE_NEW_INSTANCE => app.launch_app(false),
+                    SWITCH_APPS_DONE_ELEVATED => app.launch_app(true),
+                    SWITCH_APPS_DONE_SNAP_LEFT => app.snap_app(Snap::Left),
+                    SWITCH_APPS_DONE_SNAP_RIGHT => app.snap_app(Snap::Right),
+                    SWITCH_APPS_DONE_MAXIMIZE => app.snap_app(Snap::Maximize),
+                    _ => app.do_switch_app(),
+                }
             }
             WM_USER_SWITCH_APPS_CANCEL => {
                 debug!("message WM_USER_SWITCH_APPS_CANCEL");
@@ -295,30 +640,216 @@ impl App {
             WM_USER_SWITCH_WINDOWS => {
                 debug!("message WM_USER_SWITCH_WINDOWS");
                 let app = get_app(hwnd)?;
-                let reverse = lparam.0 == 1;
-                let hwnd = app
-                    .switch_apps_state
-                    .as_ref()
-                    .and_then(|state| state.apps.get(state.index).map(|(_, id)| *id))
-                    .unwrap_or_else(get_foreground_window);
-                app.switch_windows(hwnd, reverse)?;
+                let reverse = lparam.0 & SWITCH_WINDOWS_REVERSE != 0;
+                let all = lparam.0 & SWITCH_WINDOWS_ALL != 0;
+                let started = Instant::now();
+                let is_new = app.switch_windows_state.modifier_released;
+                let switched = if all {
+                    app.switch_all_windows(reverse)?
+                } else {
+                    let hwnd = app
+                        .switch_apps_state
+                        .as_ref()
+                        .and_then(|state| state.apps.get(state.index).map(|v| v.hwnd))
+                        .unwrap_or_else(|| app.win_api.get_foreground_window());
+                    app.switch_windows(hwnd, reverse)?
+                };
+                let hotkey = if all {
+                    app.config.switch_all_windows_hotkey.as_ref()
+                } else {
+                    Some(&app.config.switch_windows_hotkey)
+                };
+                if switched
+                    && is_new
+                    && let Some(hotkey) = hotkey
+                {
+                    let statistics = &mut app.storage.statistics;
+                    statistics.record_hotkey(&hotkey.name);
+                    statistics.record_latency(started.elapsed());
+                }
             }
             WM_USER_SWITCH_WINDOWS_DONE => {
                 debug!("message WM_USER_SWITCH_WINDOWS_DONE");
                 let app = get_app(hwnd)?;
                 app.switch_windows_state.modifier_released = true;
+                app.unpaint_titles();
+            }
+            WM_USER_SWITCH_WINDOWS_CANCEL => {
+                debug!("message WM_USER_SWITCH_WINDOWS_CANCEL");
+                let app = get_app(hwnd)?;
+                app.cancel_switch_windows();
+            }
+            WM_USER_SEARCH_CHAR => {
+                let app = get_app(hwnd)?;
+                if let Some(c) = char::from_u32(wparam.0 as u32) {
+                    app.on_search_char(c);
+                }
+            }
+            WM_USER_SEARCH_KEY => {
+                let app = get_app(hwnd)?;
+                app.on_search_key(wparam.0 as u16);
+            }
+            WM_USER_SEARCH_CLOSE => {
+                debug!("message WM_USER_SEARCH_CLOSE");
+                let app = get_app(hwnd)?;
+                app.close_search();
             }
             WM_USER_RELOAD_CONFIG => {
                 debug!("message WM_USER_RELOAD_CONFIG");
                 let app = get_app(hwnd)?;
                 app.reload_config();
             }
+            WM_USER_DESKTOPS_CHANGED => {
+                debug!("message WM_USER_DESKTOPS_CHANGED");
+                let app = get_app(hwnd)?;
+                app.excluded_desktops.take();
+            }
+            WM_USER_SECOND_INSTANCE => {
+                debug!("message WM_USER_SECOND_INSTANCE");
+                let app = get_app(hwnd)?;
+                app.on_second_instance();
+            }
+            WM_USER_HOTKEY => {
+                let id = wparam.0 as u32;
+                debug!("message WM_USER_HOTKEY {id}");
+                let app = get_app(hwnd)?;
+                app.on_hotkey(id)?;
+            }
+            WM_HOTKEY => {
+                debug!("message WM_HOTKEY {}", wparam.0);
+                let app = get_app(hwnd)?;
+                if let Some(hotkey) = app.keyboard_listener.registered_hotkey(wparam.0) {
+                    let hotkey = hotkey.clone();
+                    app.on_registered_hotkey(hotkey)?;
+                }
+            }
+            WM_TIMER if wparam.0 == TIMER_HOTKEY_RELEASE => {
+                let app = get_app(hwnd)?;
+                app.poll_hotkey_release();
+            }
+            WM_WTSSESSION_CHANGE => {
+                let event = wparam.0 as u32;
+                debug!("message WM_WTSSESSION_CHANGE {event}");
+                if matches!(
+                    event,
+                    WTS_SESSION_UNLOCK | WTS_REMOTE_CONNECT | WTS_CONSOLE_CONNECT
+                ) {
+                    let app = get_app(hwnd)?;
+                    app.revalidate();
+                }
+            }
+            WM_POWERBROADCAST => {
+                let event = wparam.0 as u32;
+                debug!("message WM_POWERBROADCAST {event}");
+                if event == PBT_APMRESUMEAUTOMATIC {
+                    let app = get_app(hwnd)?;
+                    app.revalidate();
+                }
+            }
+            WM_QUERYENDSESSION => {
+                return Ok(LRESULT(1));
+            }
+            WM_ENDSESSION => {
+                debug!("message WM_ENDSESSION {}", wparam.0);
+                // The process is terminated once this message returns if the session ends
+                if wparam.0 != 0 {
+                    Self::shutdown(hwnd);
+                }
+                return Ok(LRESULT(0));
+            }
+            WM_SETTINGCHANGE => {
+                // lparam names the changed setting, the theme is "ImmersiveColorSet",
+                // toggling a contrast theme passes SPI_SETHIGHCONTRAST in wparam
+                let setting = PCWSTR(lparam.0 as _);
+                if wparam.0 as u32 == SPI_SETHIGHCONTRAST.0
+                    || !setting.is_null()
+                        && unsafe { setting.to_string() }.is_ok_and(|v| v == "ImmersiveColorSet")
+                {
+                    debug!("message WM_SETTINGCHANGE theme");
+                    let app = get_app(hwnd)?;
+                    app.painter.update_theme();
+                    if let Some(painter) = app.titles_painter.as_mut() {
+                        painter.update_theme();
+                    }
+                    if let Some(painter) = app.search_painter.as_mut() {
+                        painter.update_theme();
+                    }
+                    app.paint_switch_apps();
+                }
+            }
+            WM_THEMECHANGED | WM_SYSCOLORCHANGE => {
+                // Contrast themes are turned on and off with these
+                debug!("message WM_THEMECHANGED");
+                let app = get_app(hwnd)?;
+                app.painter.update_theme();
+                if let Some(painter) = app.titles_painter.as_mut() {
+                    painter.update_theme();
+                }
+                if let Some(painter) = app.search_painter.as_mut() {
+                    painter.update_theme();
+                }
+                app.paint_switch_apps();
+            }
+            WM_DISPLAYCHANGE => {
+                // Monitors were added, removed or resized, center the overlay again
+                debug!("message WM_DISPLAYCHANGE");
+                let app = get_app(hwnd)?;
+                app.paint_switch_apps();
+            }
+            WM_DPICHANGED => {
+                // The overlay is laid out for the DPI of its monitor on every paint
+                debug!("message WM_DPICHANGED {}", wparam.0 & 0xffff);
+                let app = get_app(hwnd)?;
+                app.paint_switch_apps();
+                return Ok(LRESULT(0));
+            }
             WM_NCHITTEST => {
                 return Ok(LRESULT(HTCLIENT as _));
             }
+            WM_LBUTTONDOWN => {
+                let app = get_app(hwnd)?;
+                app.start_drag();
+            }
+            WM_MOUSEMOVE => {
+                let app = get_app(hwnd)?;
+                app.drag();
+                app.hover(true);
+            }
+            WM_MOUSEWHEEL => {
+                let delta = (wparam.0 >> 16) as u16 as i16;
+                let app = get_app(hwnd)?;
+                app.scroll(delta as i32)?;
+                return Ok(LRESULT(0));
+            }
+            WM_MOUSELEAVE => {
+                let app = get_app(hwnd)?;
+                app.hover(false);
+            }
+            WM_MOUSEHOVER => {
+                let app = get_app(hwnd)?;
+                app.show_tooltip();
+            }
             WM_LBUTTONUP => {
                 let app = get_app(hwnd)?;
-                app.click();
+                if !app.end_drag() {
+                    app.click();
+                }
+            }
+            WM_CAPTURECHANGED => {
+                // Another window took the mouse in the middle of a drag
+                let app = get_app(hwnd)?;
+                app.end_drag();
+            }
+            WM_RBUTTONUP => {
+                let app = get_app(hwnd)?;
+                app.show_window_menu();
+                return Ok(LRESULT(0));
+            }
+            WM_MBUTTONUP => {
+                let app = get_app(hwnd)?;
+                if let Some(hwnd) = app.clicked_window() {
+                    app.close_app_window(hwnd);
+                }
             }
             WM_COMMAND => {
                 let value = wparam.0 as u32;
@@ -327,12 +858,7 @@ impl App {
                 if kind == 0 {
                     match id {
                         IDM_EXIT => {
-                            if let Ok(app) = get_app(hwnd) {
-                                // SAFETY: app was created via Box::into_raw in start(), and this
-                                // is the only place where Box::from_raw is called to reclaim ownership.
-                                // After this drop, the window is destroyed via PostQuitMessage.
-                                unsafe { drop(Box::from_raw(app)) }
-                            }
+                            Self::shutdown(hwnd);
                             // SAFETY: PostQuitMessage terminates the message loop cleanly
                             unsafe { PostQuitMessage(0) }
                         }
@@ -340,6 +866,14 @@ impl App {
                             let app = get_app(hwnd)?;
                             app.startup.toggle()?;
                         }
+                        IDM_PAUSE => {
+                            let app = get_app(hwnd)?;
+                            app.toggle_pause();
+                        }
+                        IDM_STATISTICS => {
+                            let app = get_app(hwnd)?;
+                            app.show_statistics();
+                        }
                         IDM_CONFIGURE => {
                             if let Err(err) = edit_config_file() {
                                 alert!("{err}");
@@ -352,102 +886,174 @@ impl App {
             WM_ERASEBKGND => {
                 return Ok(LRESULT(0));
             }
-            _ if msg == WM_USER_REGISTER_TRAYICON
-                || msg == WM_TASKBARCREATED.load(std::sync::atomic::Ordering::SeqCst) =>
-            {
+            WM_USER_REGISTER_TRAYICON => {
                 let app = get_app(hwnd)?;
                 app.set_trayicon();
             }
+            _ if msg == WM_TASKBARCREATED.load(std::sync::atomic::Ordering::SeqCst) => {
+                let app = get_app(hwnd)?;
+                app.on_taskbar_created();
+            }
             _ => {}
         }
         // SAFETY: DefWindowProcW is called with valid window parameters
         Ok(unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) })
     }
 
+    /// Releases the hooks, the tray icon and the App itself, and persists the state.
+    fn shutdown(hwnd: HWND) {
+        info!("shutdown");
+        let _ = unsafe { WTSUnRegisterSessionNotification(hwnd) };
+        if let Ok(app) = get_app(hwnd) {
+            set_window_user_data(hwnd, 0);
+            app.save_storage(false);
+            // SAFETY: app was created via Box::into_raw in start(), and this
+            // is the only place where Box::from_raw is called to reclaim ownership.
+            // The user data is cleared above, so the App can't be reached again.
+            unsafe { drop(Box::from_raw(app)) }
+        }
+        log::logger().flush();
+    }
+
+    fn list_windows_options(
+        &self,
+        ignore_minimal: bool,
+        only_current_desktop: bool,
+    ) -> ListWindowsOptions {
+        ListWindowsOptions {
+            ignore_minimal,
+            only_current_desktop,
+            is_admin: self.is_admin,
+            pinned_windows: self.pinned_windows.clone(),
+            pinned_apps: self.pinned_apps.clone(),
+            excluded_desktops: self
+                .excluded_desktops
+                .get_or_init(|| {
+                    if self.config.exclude_desktops.is_empty() {
+                        Default::default()
+                    } else {
+                        Rc::new(resolve_virtual_desktops(&self.config.exclude_desktops))
+                    }
+                })
+                .clone(),
+            include_elevated: false,
+        }
+    }
+
     fn switch_windows(&mut self, hwnd: HWND, reverse: bool) -> Result<bool> {
-        let windows = list_windows(
-            self.config.switch_windows_ignore_minimal,
-            self.config.switch_windows_only_current_desktop(),
-            self.is_admin,
-        )?;
         debug!(
             "switch windows: hwnd:{hwnd:?} reverse:{reverse} state:{:?}",
             self.switch_windows_state
         );
-        let module_path = match windows
-            .iter()
-            .find(|(_, v)| v.iter().any(|(id, _)| *id == hwnd))
-            .map(|(k, _)| k.clone())
-        {
-            Some(v) => v,
-            None => return Ok(false),
-        };
-        match windows.get(&module_path) {
+        let options = self.list_windows_options(
+            self.config.switch_windows_ignore_minimal,
+            self.config.switch_windows_only_current_desktop(),
+        );
+        match next_app_window(
+            self.win_api.as_ref(),
+            &options,
+            &self.switch_windows_state,
+            hwnd,
+            reverse,
+        )? {
+            Some((hwnd, state)) => {
+                self.switch_windows_state = state;
+                self.activate(hwnd);
+                self.paint_titles();
+                Ok(true)
+            }
             None => Ok(false),
-            Some(windows) => {
-                let windows_len = windows.len();
-                if windows_len == 1 {
+        }
+    }
+
+    /// Cycles through the windows of all apps from the most recently used, like alt+esc in
+    /// the order of alt+tab.
+    fn switch_all_windows(&mut self, reverse: bool) -> Result<bool> {
+        debug!(
+            "switch all windows: reverse:{reverse} state:{:?}",
+            self.switch_windows_state
+        );
+        let state = &mut self.switch_windows_state;
+        let (index, windows) = match state.all.take() {
+            Some((index, windows)) if !state.modifier_released => {
+                (cycle_index(index, windows.len(), reverse), windows)
+            }
+            _ => {
+                let options = self.list_windows_options(
+                    self.config.switch_windows_ignore_minimal,
+                    self.config.switch_windows_only_current_desktop(),
+                );
+                let foreground = self.win_api.get_foreground_window();
+                let Some(all) =
+                    new_switch_all_windows(self.win_api.as_ref(), &options, foreground, reverse)?
+                else {
                     return Ok(false);
-                }
-                let current_id = windows[0].0;
-                let mut index = 1;
-                let mut state_id = current_id;
-                let mut state_windows = vec![];
-                if windows_len > 2 {
-                    if let Some((cache_module_path, cache_id, cache_index, cache_windows)) =
-                        self.switch_windows_state.cache.as_ref()
-                    {
-                        if cache_module_path == &module_path {
-                            if self.switch_windows_state.modifier_released {
-                                if *cache_id != current_id {
-                                    if let Some((i, _)) =
-                                        windows.iter().enumerate().find(|(_, (v, _))| v == cache_id)
-                                    {
-                                        index = i;
-                                    }
-                                }
-                            } else {
-                                state_id = *cache_id;
-                                let mut windows_set: IndexSet<isize> =
-                                    windows.iter().map(|(v, _)| v.0 as _).collect();
-                                for id in cache_windows {
-                                    if windows_set.contains(id) {
-                                        state_windows.push(*id);
-                                        windows_set.swap_remove(id);
-                                    }
-                                }
-                                state_windows.extend(windows_set);
-                                index = if reverse {
-                                    if *cache_index == 0 {
-                                        windows_len - 1
-                                    } else {
-                                        cache_index - 1
-                                    }
-                                } else if *cache_index >= windows_len - 1 {
-                                    0
-                                } else {
-                                    cache_index + 1
-                                };
-                            }
-                        }
-                    }
-                }
-                if state_windows.is_empty() {
-                    state_windows = windows.iter().map(|(v, _)| v.0 as _).collect();
-                }
-                // Use get() for bounds-safe access to prevent potential panic
-                let hwnd = state_windows
-                    .get(index)
-                    .map(|v| HWND(*v as _))
-                    .unwrap_or_else(|| HWND(state_windows[0] as _));
-                self.switch_windows_state = SwitchWindowsState {
-                    cache: Some((module_path.clone(), state_id, index, state_windows)),
-                    modifier_released: false,
                 };
-                set_foreground_window(hwnd);
-
-                Ok(true)
+                if self.switch_windows_state.modifier_released {
+                    self.switch_windows_state.original = Some(foreground);
+                }
+                all
             }
+        };
+        let hwnd = HWND(windows[index] as _);
+        let state = &mut self.switch_windows_state;
+        state.all = Some((index, windows));
+        state.cache = None;
+        state.modifier_released = false;
+        self.activate(hwnd);
+        self.paint_titles();
+        Ok(true)
+    }
+
+    /// Ends the switch and goes back to the window it started from.
+    fn cancel_switch_windows(&mut self) {
+        let original = self.switch_windows_state.original;
+        self.switch_windows_state.reset();
+        self.unpaint_titles();
+        if let Some(hwnd) = original {
+            self.activate(hwnd);
+        }
+    }
+
+    /// Lists the windows being switched, creating the list window on first use.
+    fn paint_titles(&mut self) {
+        if !self.config.switch_windows_show_list {
+            return;
+        }
+        let state = &self.switch_windows_state;
+        let Some((index, windows)) = state
+            .all
+            .as_ref()
+            .map(|(index, windows)| (index, windows))
+            .or_else(|| {
+                state
+                    .cache
+                    .as_ref()
+                    .map(|(_, _, index, windows)| (index, windows))
+            })
+        else {
+            return;
+        };
+        let state = SwitchWindowsUiState {
+            titles: windows
+                .iter()
+                .map(|v| get_window_title(HWND(*v as _)))
+                .collect(),
+            index: *index,
+        };
+        if self.titles_painter.is_none() {
+            self.titles_painter = TitlesPainter::new(self.hwnd, &self.config)
+                .map_err(|err| error!("{err}"))
+                .ok();
+        }
+        if let Some(painter) = self.titles_painter.as_mut() {
+            painter.paint(&state);
+        }
+    }
+
+    fn unpaint_titles(&mut self) {
+        if let Some(painter) = self.titles_painter.as_mut() {
+            painter.unpaint();
         }
     }
 
@@ -457,105 +1063,929 @@ impl App {
             self.switch_apps_state
         );
         if let Some(state) = self.switch_apps_state.as_mut() {
-            if reverse {
-                if state.index == 0 {
-                    state.index = state.apps.len() - 1;
-                } else {
-                    state.index -= 1;
-                }
-            } else if state.index == state.apps.len() - 1 {
-                state.index = 0;
-            } else {
-                state.index += 1;
-            };
+            state.index = cycle_index(state.index, state.apps.len(), reverse);
             debug!("switch apps: new index:{}", state.index);
             return Ok(());
         }
-        let windows = list_windows(
+        let mut options = self.list_windows_options(
             self.config.switch_apps_ignore_minimal,
             self.config.switch_apps_only_current_desktop(),
-            self.is_admin,
+        );
+        options.include_elevated = self.config.switch_apps_show_elevated;
+        self.switch_apps_state = new_switch_apps_state(
+            self.win_api.as_ref(),
+            &options,
+            &self.config,
+            &self.storage.apps_order,
+            &self.storage.statistics,
+            &mut self.cached_icons,
+            reverse,
         )?;
-        let mut apps = vec![];
-        for (module_path, hwnds) in windows.iter() {
-            // hwnds is guaranteed to be non-empty by list_windows implementation
-            let module_hwnd = if hwnds.is_empty() {
-                continue;
-            } else if is_iconic_window(hwnds[0].0) {
-                hwnds.last().map(|(hwnd, _)| *hwnd).unwrap_or(hwnds[0].0)
-            } else {
-                hwnds[0].0
-            };
-            let module_hicon = self
-                .cached_icons
-                .entry(module_path.clone())
-                .or_insert_with(|| {
-                    get_app_icon(
-                        &self.config.switch_apps_override_icons,
-                        module_path,
-                        module_hwnd,
-                    )
-                });
-            apps.push((*module_hicon, module_hwnd));
+        debug!("switch apps, new state:{:?}", self.switch_apps_state);
+        Ok(())
+    }
+
+    /// Starts auto-advancing the selection once the key has been held for a while.
+    fn start_slideshow(&mut self, reverse: bool) {
+        if !self.config.switch_apps_slideshow || self.switch_apps_state.is_none() {
+            return;
+        }
+        self.slideshow = Some(Slideshow {
+            reverse,
+            started: None,
+            interval: 0,
+        });
+        unsafe {
+            SetTimer(
+                Some(self.hwnd),
+                TIMER_SLIDESHOW,
+                self.config.switch_apps_slideshow_delay,
+                None,
+            )
+        };
+    }
+
+    fn slideshow_tick(&mut self) -> Result<()> {
+        let Some(slideshow) = self.slideshow.as_mut() else {
+            let _ = unsafe { KillTimer(Some(self.hwnd), TIMER_SLIDESHOW) };
+            return Ok(());
+        };
+        let started = *slideshow.started.get_or_insert_with(Instant::now);
+        let mut interval = self.config.switch_apps_slideshow_interval;
+        if self.config.switch_apps_slideshow_accelerate {
+            interval = accelerated_interval(interval, started.elapsed());
+        }
+        if slideshow.interval != interval {
+            slideshow.interval = interval;
+            unsafe { SetTimer(Some(self.hwnd), TIMER_SLIDESHOW, interval, None) };
         }
-        let num_apps = apps.len() as i32;
-        if num_apps == 0 {
+        let reverse = slideshow.reverse;
+        if self.switch_apps_state.is_none() {
+            self.stop_slideshow();
             return Ok(());
         }
+        self.switch_apps(reverse)?;
+        if let Some(state) = self.switch_apps_state.as_mut()
+            && !self.overlay_pending
+        {
+            // Scanning the windows needs more than the icons, the preview stays until closed
+            state.preview = true;
+            self.paint_switch_apps();
+        }
+        Ok(())
+    }
 
-        let index = if apps.len() == 1 {
-            0
-        } else if reverse {
-            apps.len() - 1
-        } else {
-            1
-        };
+    /// Paints the overlay held back by the overlay delay, the hotkey is still held.
+    fn show_pending_overlay(&mut self) {
+        if !std::mem::take(&mut self.overlay_pending) {
+            return;
+        }
+        if self.switch_apps_state.is_some() {
+            self.paint_switch_apps();
+            self.start_mouse_listener();
+        }
+    }
 
-        let state = SwitchAppsState { apps, index };
-        self.switch_apps_state = Some(state);
-        debug!("switch apps, new state:{:?}", self.switch_apps_state);
-        Ok(())
+    /// Paints the switch apps overlay, nothing is shown while switching blind.
+    fn paint_switch_apps(&mut self) {
+        if !self.config.switch_apps_show_ui {
+            return;
+        }
+        if let Some(state) = &self.switch_apps_state {
+            self.painter.paint(state);
+        }
+    }
+
+    fn stop_slideshow(&mut self) {
+        if self.slideshow.take().is_some() {
+            let _ = unsafe { KillTimer(Some(self.hwnd), TIMER_SLIDESHOW) };
+        }
     }
 
     fn click(&mut self) {
-        if let Some(state) = self.switch_apps_state.as_mut() {
-            if let Some(i) = find_clicked_app_index(state) {
-                state.index = i;
+        if let Some(state) = self.switch_apps_state.as_mut()
+            && let Some(i) = self.painter.find_clicked_app_index(state)
+        {
+            state.index = i;
+            if is_key_down(VK_CONTROL) {
+                self.launch_app(is_key_down(VK_SHIFT));
+            } else {
                 self.do_switch_app();
             }
         }
     }
 
-    fn do_switch_app(&mut self) {
-        if let Some(state) = self.switch_apps_state.take() {
-            if let Some((_, id)) = state.apps.get(state.index) {
-                set_foreground_window(*id);
+    /// Acts on the window of the right-clicked app as picked from its menu, the switch goes on.
+    fn show_window_menu(&mut self) {
+        let Some(hwnd) = self.clicked_window() else {
+            return;
+        };
+        // Picking an item outside of the overlay is no click outside of it
+        self.mouse_listener = None;
+        let action = show_window_menu(self.hwnd, has_several_monitors());
+        if self.switch_apps_state.is_some() {
+            self.start_mouse_listener();
+        }
+        let action = match action {
+            Ok(Some(v)) => v,
+            Ok(None) => return,
+            Err(err) => {
+                error!("{err}");
+                return;
             }
-            self.painter.unpaint(state);
+        };
+        let result = match action {
+            WindowAction::Minimize => {
+                minimize_window(hwnd);
+                Ok(())
+            }
+            WindowAction::Maximize => snap_window(hwnd, Snap::Maximize),
+            WindowAction::MoveToNextMonitor => move_to_next_monitor(hwnd),
+            WindowAction::Close => {
+                self.close_app_window(hwnd);
+                return;
+            }
+        };
+        if let Err(err) = result {
+            error!("{err}");
+        }
+        if let Err(err) = self.refresh_switch_apps() {
+            error!("{err}");
         }
     }
 
-    fn cancel_switch_app(&mut self) {
-        if let Some(state) = self.switch_apps_state.take() {
-            self.painter.unpaint(state);
+    /// Window of the app under the cursor in the overlay.
+    fn clicked_window(&self) -> Option<HWND> {
+        let state = self.switch_apps_state.as_ref()?;
+        let index = self.painter.find_clicked_app_index(state)?;
+        state.apps.get(index).map(|v| v.hwnd)
+    }
+
+    /// Asks the window to close, the switch goes on with the apps left.
+    fn close_app_window(&mut self, hwnd: HWND) -> bool {
+        if let Err(err) = close_window(hwnd) {
+            error!("{err}");
+            return false;
         }
+        // The window takes a moment to close, if it closes at all
+        unsafe { SetTimer(Some(self.hwnd), TIMER_REFRESH, REFRESH_DELAY, None) };
+        true
     }
 
-    fn reload_config(&mut self) {
-        use crate::load_config;
-        info!("reloading configuration");
-        match load_config() {
-            Ok(new_config) => {
-                if let Err(err) = self
-                    .keyboard_listener
-                    .update_hotkeys(&self.config.to_hotkeys(), &new_config.to_hotkeys())
+    /// Closes the window of the selected app and drops the app from the overlay at once,
+    /// the refresh brings it back if the window is still open.
+    fn close_selected_window(&mut self) {
+        let Some(state) = self.switch_apps_state.as_ref() else {
+            return;
+        };
+        let Some(hwnd) = state.apps.get(state.index).map(|v| v.hwnd) else {
+            return;
+        };
+        if !self.close_app_window(hwnd) {
+            return;
+        }
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        state.apps.remove(state.index);
+        if state.apps.is_empty() {
+            self.cancel_switch_app();
+            return;
+        }
+        state.index = state.index.min(state.apps.len() - 1);
+        self.paint_switch_apps();
+    }
+
+    /// Minimizes the window of the selected app and moves on to the next app.
+    fn minimize_selected_window(&mut self) {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        let Some(item) = state.apps.get_mut(state.index) else {
+            return;
+        };
+        minimize_window(item.hwnd);
+        item.minimized = true;
+        state.index = cycle_index(state.index, state.apps.len(), false);
+        self.paint_switch_apps();
+    }
+
+    /// Lists the apps again while the overlay is shown, keeping the selected app selected.
+    fn refresh_switch_apps(&mut self) -> Result<()> {
+        let Some(state) = self.switch_apps_state.as_ref() else {
+            return Ok(());
+        };
+        let selected = state.apps.get(state.index).map(|v| v.module_path.clone());
+        let index = state.index;
+        let preview = state.preview;
+        let mut options = self.list_windows_options(
+            self.config.switch_apps_ignore_minimal,
+            self.config.switch_apps_only_current_desktop(),
+        );
+        options.include_elevated = self.config.switch_apps_show_elevated;
+        let new_state = new_switch_apps_state(
+            self.win_api.as_ref(),
+            &options,
+            &self.config,
+            &self.storage.apps_order,
+            &self.storage.statistics,
+            &mut self.cached_icons,
+            false,
+        )?;
+        let Some(mut new_state) = new_state else {
+            self.cancel_switch_app();
+            return Ok(());
+        };
+        new_state.index = selected
+            .and_then(|v| new_state.apps.iter().position(|item| item.module_path == v))
+            .unwrap_or(index.min(new_state.apps.len() - 1));
+        new_state.preview = preview;
+        self.switch_apps_state = Some(new_state);
+        self.paint_switch_apps();
+        Ok(())
+    }
+
+    /// Starts dragging the clicked icon, only custom sorted apps can be rearranged.
+    fn start_drag(&mut self) {
+        if self.config.switch_apps_sort != AppsSort::Custom {
+            return;
+        }
+        if let Some(state) = self.switch_apps_state.as_mut() {
+            state.drag = self
+                .painter
+                .find_clicked_app_index(state)
+                .map(|index| AppDrag {
+                    index,
+                    moved: false,
+                });
+            // The button may be released outside of the overlay, that ends the drag too
+            if state.drag.is_some() {
+                unsafe { SetCapture(self.hwnd) };
+            }
+        }
+    }
+
+    fn drag(&mut self) {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        let Some(from) = state.drag.as_ref().map(|v| v.index) else {
+            return;
+        };
+        let Some(to) = self.painter.find_clicked_app_index(state) else {
+            return;
+        };
+        if from == to {
+            return;
+        }
+        let item = state.apps.remove(from);
+        state.apps.insert(to, item);
+        state.index = to;
+        state.drag = Some(AppDrag {
+            index: to,
+            moved: true,
+        });
+        self.paint_switch_apps();
+    }
+
+    /// Moves the selection a step per wheel notch, up selects the previous app.
+    fn scroll(&mut self, delta: i32) -> Result<()> {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return Ok(());
+        };
+        // Precision touchpads scroll in fractions of a notch
+        state.wheel_delta += delta;
+        let steps = state.wheel_delta / WHEEL_DELTA as i32;
+        if steps == 0 {
+            return Ok(());
+        }
+        state.wheel_delta -= steps * WHEEL_DELTA as i32;
+        for _ in 0..steps.abs() {
+            self.switch_apps(steps > 0)?;
+        }
+        self.paint_switch_apps();
+        Ok(())
+    }
+
+    /// Moves the selection to the next, previous, first or last app.
+    fn select_app(&mut self, select: isize) {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        let len = state.apps.len();
+        // Left selects the next app when the icons run right to left
+        let select = match select {
+            SWITCH_APPS_SELECT_NEXT if self.config.overlay_rtl() => SWITCH_APPS_SELECT_PREVIOUS,
+            SWITCH_APPS_SELECT_PREVIOUS if self.config.overlay_rtl() => SWITCH_APPS_SELECT_NEXT,
+            v => v,
+        };
+        state.index = match select {
+            SWITCH_APPS_SELECT_PREVIOUS => cycle_index(state.index, len, true),
+            SWITCH_APPS_SELECT_FIRST => 0,
+            SWITCH_APPS_SELECT_LAST => len.saturating_sub(1),
+            _ => cycle_index(state.index, len, false),
+        };
+        debug!("select app: new index:{}", state.index);
+        self.paint_switch_apps();
+    }
+
+    /// Switches to the app at the index, indexes past the last app are ignored.
+    fn pick_app(&mut self, index: usize) {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        if index >= state.apps.len() {
+            return;
+        }
+        state.index = index;
+        self.do_switch_app();
+    }
+
+    /// Highlights the app under the mouse cursor, so it is clear what a click activates.
+    fn hover(&mut self, inside: bool) {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return;
+        };
+        let hover = if inside {
+            let mut track = TRACKMOUSEEVENT {
+                cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                dwFlags: TME_LEAVE | TME_HOVER,
+                hwndTrack: self.hwnd,
+                dwHoverTime: HOVER_DEFAULT,
+            };
+            let _ = unsafe { TrackMouseEvent(&mut track) };
+            self.painter.find_clicked_app_index(state)
+        } else {
+            None
+        };
+        if hover != state.hover {
+            state.hover = hover;
+            self.paint_switch_apps();
+            if let Some(tooltip) = self.tooltip.as_mut() {
+                tooltip.hide();
+            }
+        }
+    }
+
+    /// Shows the exe name and full title of the app the mouse rests on.
+    fn show_tooltip(&mut self) {
+        let Some(tooltip) = self.tooltip.as_mut() else {
+            return;
+        };
+        let Some(item) = self
+            .switch_apps_state
+            .as_ref()
+            .and_then(|state| state.apps.get(state.hover?))
+        else {
+            return;
+        };
+        let exe_name = Path::new(&item.module_path)
+            .file_name()
+            .map(|v| v.to_string_lossy())
+            .unwrap_or_default();
+        tooltip.show(&format!("{exe_name}\n{}", item.title));
+    }
+
+    /// Finishes dragging and persists the new order.
+    ///
+    /// Returns false if no icon was moved, so the mouse up should be treated as a click.
+    fn end_drag(&mut self) -> bool {
+        let Some(state) = self.switch_apps_state.as_mut() else {
+            return false;
+        };
+        let Some(drag) = state.drag.take() else {
+            return false;
+        };
+        // Sends WM_CAPTURECHANGED, which finds the drag already over
+        let _ = unsafe { ReleaseCapture() };
+        if !drag.moved {
+            return false;
+        }
+        let Some(state) = self.switch_apps_state.as_ref() else {
+            return false;
+        };
+        let mut apps_order: Vec<String> =
+            state.apps.iter().map(|v| v.module_path.clone()).collect();
+        for module_path in &self.storage.apps_order {
+            if !apps_order.contains(module_path) {
+                apps_order.push(module_path.clone());
+            }
+        }
+        self.storage.apps_order = apps_order;
+        self.save_storage(false);
+        true
+    }
+
+    /// Writes the state file, with the windows switch windows returns to. Skipped when nothing
+    /// was recorded since the last time if `only_changed` is set.
+    fn save_storage(&mut self, only_changed: bool) {
+        let last_windows: HashMap<String, isize> = self
+            .switch_windows_state
+            .last_windows
+            .iter()
+            .map(|(module_path, hwnd)| (module_path.clone(), hwnd.0 as isize))
+            .collect();
+        let changed =
+            self.storage.statistics.take_changed() || last_windows != self.storage.last_windows;
+        self.storage.last_windows = last_windows;
+        if (changed || !only_changed)
+            && let Err(err) = self.storage.save()
+        {
+            error!("{err}");
+        }
+    }
+
+    /// Switches to the window, when the foreground lock rules of Windows block that,
+    /// flashes its taskbar button instead and tells why nothing happened.
+    fn activate(&mut self, hwnd: HWND) {
+        if set_foreground_window(hwnd) {
+            return;
+        }
+        warn!("failed to set foreground window {hwnd:?}");
+        flash_window(hwnd);
+        // Cycling through windows may be refused at every step, the flashing still shows it
+        if self
+            .activate_balloon_shown
+            .is_some_and(|v| v.elapsed() < ACTIVATE_BALLOON_INTERVAL)
+        {
+            return;
+        }
+        self.activate_balloon_shown = Some(Instant::now());
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && let Err(err) = trayicon.show_balloon(
+                "Window Switcher",
+                "Windows did not allow switching to the window, it is flashing in the taskbar",
+            )
+        {
+            error!("Failed to show balloon notification: {err}");
+        }
+    }
+
+    fn do_switch_app(&mut self) {
+        self.stop_slideshow();
+        if let Some(state) = self.switch_apps_state.take() {
+            if let Some(item) = state.apps.get(state.index) {
+                self.activate(item.hwnd);
+                self.storage.statistics.record_app_switch(&item.module_path);
+            }
+            self.hide_overlay(state);
+        }
+    }
+
+    /// Switches to the app a quick press of the hotkey would, unless the overlay is shown.
+    fn switch_to_last_app(&mut self) -> Result<()> {
+        if self.switch_apps_state.is_some() {
+            return Ok(());
+        }
+        self.switch_apps(false)?;
+        self.do_switch_app();
+        Ok(())
+    }
+
+    /// Handles a hotkey registered with Windows, used when the keyboard hook is unavailable.
+    /// The switch hotkeys go through the same messages as from the hook.
+    fn on_registered_hotkey(&mut self, hotkey: Hotkey) -> Result<()> {
+        let message = match hotkey.id {
+            SWITCH_APPS_HOTKEY_ID => WM_USER_SWITCH_APPS,
+            SWITCH_WINDOWS_HOTKEY_ID | SWITCH_ALL_WINDOWS_HOTKEY_ID => WM_USER_SWITCH_WINDOWS,
+            id => return self.on_hotkey(id),
+        };
+        let mut flags = is_key_down(VK_SHIFT) as isize;
+        if hotkey.id == SWITCH_ALL_WINDOWS_HOTKEY_ID {
+            flags |= SWITCH_WINDOWS_ALL;
+        }
+        unsafe { PostMessageW(Some(self.hwnd), message, WPARAM(0), LPARAM(flags)) }
+            .map_err(|err| anyhow!("Failed to post hotkey message, {err}"))?;
+        if self.held_hotkey.is_none() {
+            unsafe {
+                SetTimer(
+                    Some(self.hwnd),
+                    TIMER_HOTKEY_RELEASE,
+                    HOTKEY_RELEASE_DELAY,
+                    None,
+                )
+            };
+        }
+        self.held_hotkey = Some(hotkey);
+        Ok(())
+    }
+
+    /// Ends the switch of a registered hotkey once its modifiers are released.
+    fn poll_hotkey_release(&mut self) {
+        if self.held_hotkey.as_ref().is_some_and(|v| !is_key_held(v)) {
+            self.stop_slideshow();
+        }
+        if self.held_hotkey.as_ref().is_some_and(is_modifier_held) {
+            return;
+        }
+        let _ = unsafe { KillTimer(Some(self.hwnd), TIMER_HOTKEY_RELEASE) };
+        let message = match self.held_hotkey.take().map(|v| v.id) {
+            Some(SWITCH_APPS_HOTKEY_ID) => WM_USER_SWITCH_APPS_DONE,
+            Some(_) => WM_USER_SWITCH_WINDOWS_DONE,
+            None => return,
+        };
+        let _ = unsafe { PostMessageW(Some(self.hwnd), message, WPARAM(0), LPARAM(0)) };
+    }
+
+    fn on_hotkey(&mut self, id: u32) -> Result<()> {
+        if let Some(name) = self.hotkey_names.get(&id) {
+            self.storage.statistics.record_hotkey(name);
+        }
+        match id {
+            MINIMIZE_OTHERS_HOTKEY_ID => self.minimize_others()?,
+            TRANSPARENCY_HOTKEY_ID => self.cycle_opacity()?,
+            PIN_HOTKEY_ID => self.toggle_pin(),
+            PAUSE_HOTKEY_ID => self.toggle_pause(),
+            TOPMOST_HOTKEY_ID => self.toggle_topmost()?,
+            SEARCH_HOTKEY_ID => self.open_search()?,
+            MOVE_TO_NEXT_MONITOR_HOTKEY_ID => {
+                move_to_next_monitor(self.win_api.get_foreground_window())?
+            }
+            _ => match self.config.hotkey_action(id) {
+                Some(HotkeyAction::Run(command)) => launch_command(command)?,
+                Some(HotkeyAction::Focus(exe)) => {
+                    let exe = exe.clone();
+                    self.focus_or_launch(&exe)?
+                }
+                None => {}
+            },
+        }
+        Ok(())
+    }
+
+    /// Opens the search palette listing the windows of all apps from the most recently used.
+    fn open_search(&mut self) -> Result<()> {
+        if self.search_state.is_some() {
+            return Ok(());
+        }
+        // Minimized windows and those on other desktops are found too, that is what it is for
+        let options = self.list_windows_options(false, false);
+        self.search_state = Some(SearchState::new(mru_windows(
+            self.win_api.as_ref(),
+            &options,
+        )?));
+        if self.search_painter.is_none() {
+            self.search_painter = Some(SearchPainter::new(self.hwnd, &self.config)?);
+        }
+        self.paint_search();
+        Ok(())
+    }
+
+    fn paint_search(&mut self) {
+        if let (Some(state), Some(painter)) = (&self.search_state, self.search_painter.as_mut()) {
+            painter.paint(&state.ui_state());
+        }
+    }
+
+    /// Enter switches to the selected match and Esc closes the palette, other characters edit
+    /// the query.
+    fn on_search_char(&mut self, c: char) {
+        let Some(state) = self.search_state.as_mut() else {
+            return;
+        };
+        match c {
+            '\r' => {
+                let hwnd = state.selected();
+                self.close_search();
+                if let Some(hwnd) = hwnd {
+                    self.activate(hwnd);
+                }
+            }
+            '\x1b' => self.close_search(),
+            c => {
+                if state.type_char(c) {
+                    self.paint_search();
+                }
+            }
+        }
+    }
+
+    /// Up and down move the selection through the matches.
+    fn on_search_key(&mut self, vk: u16) {
+        let Some(state) = self.search_state.as_mut() else {
+            return;
+        };
+        if state.matches.is_empty() || ![VK_UP.0, VK_DOWN.0].contains(&vk) {
+            return;
+        }
+        state.index = cycle_index(state.index, state.matches.len(), vk == VK_UP.0);
+        self.paint_search();
+    }
+
+    fn close_search(&mut self) {
+        self.search_state = None;
+        if let Some(painter) = self.search_painter.as_mut() {
+            painter.unpaint();
+        }
+    }
+
+    /// Minimizes all windows but the foreground one, or restores them if they were minimized
+    /// by the previous press.
+    fn minimize_others(&mut self) -> Result<()> {
+        if self.minimized_others.is_empty() {
+            self.minimized_others =
+                minimize_others(self.win_api.get_foreground_window(), self.is_admin)?;
+        } else {
+            restore_windows(&std::mem::take(&mut self.minimized_others));
+        }
+        Ok(())
+    }
+
+    /// Switches to the selected app and snaps its window.
+    fn snap_app(&mut self, snap: Snap) {
+        let hwnd = self
+            .switch_apps_state
+            .as_ref()
+            .and_then(|state| state.apps.get(state.index).map(|v| v.hwnd));
+        self.do_switch_app();
+        if let Some(hwnd) = hwnd
+            && let Err(err) = snap_window(hwnd, snap)
+        {
+            error!("{err}");
+        }
+    }
+
+    /// Pins the foreground window so it's always listed, or unpins it.
+    fn toggle_pin(&mut self) {
+        let pinned_windows = Rc::make_mut(&mut self.pinned_windows);
+        pinned_windows.retain(|v| is_window(HWND(*v as _)));
+        let hwnd = self.win_api.get_foreground_window().0 as isize;
+        let message = if pinned_windows.remove(&hwnd) {
+            "Window unpinned"
+        } else {
+            pinned_windows.insert(hwnd);
+            "Window pinned, it's always listed in the switchers"
+        };
+        info!("{message} {hwnd}");
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && let Err(err) = trayicon.show_balloon("Window Switcher", message)
+        {
+            error!("Failed to show balloon notification: {err}");
+        }
+    }
+
+    /// While paused the hotkeys are left to Windows, so e.g. alt+tab shows the native switcher.
+    fn toggle_pause(&mut self) {
+        let paused = !self.keyboard_listener.is_paused();
+        if paused {
+            self.cancel_switch_app();
+            self.switch_windows_state.reset();
+            self.unpaint_titles();
+        }
+        self.keyboard_listener.set_paused(paused);
+        if paused {
+            self.mouse_trigger = None;
+        } else {
+            self.start_mouse_trigger();
+        }
+        let message = if paused {
+            "Paused, the hotkeys are handled by Windows"
+        } else {
+            "Resumed"
+        };
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && let Err(err) = trayicon.show_balloon("Window Switcher", message)
+        {
+            error!("Failed to show balloon notification: {err}");
+        }
+    }
+
+    fn show_statistics(&self) {
+        let text = to_wstring(&self.storage.statistics.summary());
+        unsafe {
+            MessageBoxW(
+                Some(self.hwnd),
+                PCWSTR(text.as_ptr()),
+                w!("Window Switcher Statistics"),
+                MB_OK | MB_ICONINFORMATION,
+            )
+        };
+    }
+
+    fn cycle_opacity(&mut self) -> Result<()> {
+        let hwnd = self.win_api.get_foreground_window();
+        let owns_layered = self.layered_windows.contains(&(hwnd.0 as isize));
+        if cycle_opacity(hwnd, owns_layered)? {
+            self.layered_windows.insert(hwnd.0 as isize);
+        } else {
+            self.layered_windows.remove(&(hwnd.0 as isize));
+        }
+        Ok(())
+    }
+
+    /// Switches to the most recently used window of the exe, starts the exe if it has none.
+    fn focus_or_launch(&mut self, exe: &str) -> Result<()> {
+        let options = self.list_windows_options(false, false);
+        match find_app_window(self.win_api.as_ref(), &options, exe)? {
+            Some(hwnd) => self.activate(hwnd),
+            None => launch_app(exe)?,
+        }
+        Ok(())
+    }
+
+    /// Flips always on top of the foreground window and tells its new state.
+    fn toggle_topmost(&mut self) -> Result<()> {
+        let topmost = toggle_topmost(self.win_api.get_foreground_window())?;
+        let message = if topmost {
+            "The window stays on top"
+        } else {
+            "The window no longer stays on top"
+        };
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && let Err(err) = trayicon.show_balloon("Window Switcher", message)
+        {
+            error!("Failed to show balloon notification: {err}");
+        }
+        Ok(())
+    }
+
+    /// Starts a new instance of the selected app instead of switching to it.
+    fn launch_app(&mut self, elevated: bool) {
+        self.stop_slideshow();
+        if let Some(state) = self.switch_apps_state.take() {
+            if let Some(item) = state.apps.get(state.index) {
+                let ret = if elevated {
+                    launch_app_elevated(&item.module_path)
+                } else {
+                    launch_app(&item.module_path)
+                };
+                if let Err(err) = ret {
+                    error!("{err}");
+                }
+            }
+            self.hide_overlay(state);
+        }
+    }
+
+    fn cancel_switch_app(&mut self) {
+        self.stop_slideshow();
+        if let Some(state) = self.switch_apps_state.take() {
+            self.hide_overlay(state);
+        }
+    }
+
+    fn hide_overlay(&mut self, state: SwitchAppsState) {
+        if std::mem::take(&mut self.overlay_pending) {
+            let _ = unsafe { KillTimer(Some(self.hwnd), TIMER_OVERLAY) };
+        }
+        self.mouse_listener = None;
+        if state.drag.is_some() {
+            let _ = unsafe { ReleaseCapture() };
+        }
+        self.keyboard_listener.end_sticky();
+        if let Some(tooltip) = self.tooltip.as_mut() {
+            tooltip.hide();
+        }
+        if self.config.switch_apps_show_ui {
+            self.painter.unpaint(state);
+        }
+    }
+
+    /// Installs the hook of the mouse trigger again, or removes it when there is none.
+    fn start_mouse_trigger(&mut self) {
+        self.mouse_trigger = None;
+        let trigger = self.config.switch_apps_mouse_trigger;
+        if !self.config.switch_apps_enable || trigger == MouseTrigger::None {
+            return;
+        }
+        match MouseTriggerListener::init(self.hwnd, trigger) {
+            Ok(listener) => self.mouse_trigger = Some(listener),
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    fn start_mouse_listener(&mut self) {
+        if !self.config.overlay_close_on_outside_click || !self.config.switch_apps_show_ui {
+            return;
+        }
+        match MouseListener::init(self.hwnd) {
+            Ok(listener) => self.mouse_listener = Some(listener),
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    /// Shows that the app is alive and opens the config file, which is reloaded once
+    /// the editor is closed.
+    fn on_second_instance(&mut self) {
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && let Err(err) = trayicon.show_balloon("Window Switcher", "Already running")
+        {
+            error!("Failed to show balloon notification: {err}");
+        }
+        let hwnd = self.hwnd.0 as isize;
+        std::thread::spawn(move || match edit_config_file() {
+            Ok(true) => {
+                let _ = unsafe {
+                    PostMessageW(
+                        Some(HWND(hwnd as _)),
+                        WM_USER_RELOAD_CONFIG,
+                        WPARAM(0),
+                        LPARAM(0),
+                    )
+                };
+            }
+            Ok(false) => {}
+            Err(err) => alert!("{err}"),
+        });
+    }
+
+    /// Re-installs the hooks and the tray icon, which may be silently dropped by Windows
+    /// while the session is locked or the machine is asleep, and forgets cached windows.
+    fn revalidate(&mut self) {
+        info!("revalidating hooks and trayicon");
+        self.cancel_switch_app();
+        self.switch_windows_state.reset();
+        self.unpaint_titles();
+        if self.config.remote_session_release_hook && is_remote_session() {
+            info!("remote session, leave hotkeys to the local machine");
+            self.keyboard_listener.uninstall();
+        } else if let Err(err) = self.keyboard_listener.reinstall() {
+            error!("{err}");
+        }
+        if let Err(err) = self.foreground_watcher.reinstall() {
+            error!("{err}");
+        }
+        if let Some(trayicon) = self.trayicon.as_mut()
+            && !trayicon.exist()
+            && let Err(err) = trayicon.register(self.hwnd)
+        {
+            error!("{err}");
+        }
+    }
+
+    /// Explorer has been restarted, drop everything derived from the old shell.
+    fn on_taskbar_created(&mut self) {
+        info!("taskbar recreated");
+        self.cancel_switch_app();
+        self.switch_windows_state.reset();
+        self.unpaint_titles();
+        // Icons of packaged apps are resolved through the shell
+        self.cached_icons.clear();
+        reset_virtual_desktop_manager();
+        self.set_trayicon();
+    }
+
+    fn reload_config(&mut self) {
+        use crate::load_config;
+        info!("reloading configuration");
+        match load_config() {
+            Ok(new_config) => {
+                if let Err(err) = self
+                    .keyboard_listener
+                    .update_hotkeys(&self.config.to_hotkeys(), &new_config.to_hotkeys())
                 {
                     error!("Failed to update hotkeys: {err}");
                     alert!("Failed to update hotkeys: {err}");
                     return;
                 }
+                let release_hook_changed = self.config.remote_session_release_hook
+                    != new_config.remote_session_release_hook;
                 self.config = new_config;
+                self.pinned_apps = Rc::new(self.config.pin_apps.clone());
+                self.hotkey_names = hotkey_names(&self.config);
+                self.excluded_desktops.take();
+                self.painter.update_config(&self.config);
+                if !self.config.switch_windows_show_list {
+                    self.titles_painter = None;
+                } else if let Some(painter) = self.titles_painter.as_mut() {
+                    painter.update_config(&self.config);
+                }
+                if let Some(painter) = self.search_painter.as_mut() {
+                    painter.update_config(&self.config);
+                }
+                self.keyboard_listener
+                    .set_quick_select(self.config.overlay_quick_select);
+                self.keyboard_listener
+                    .set_follow_layout(self.config.hotkeys_follow_layout);
+                self.keyboard_listener
+                    .set_cancel_keys(&self.config.cancel_keys);
+                self.keyboard_listener
+                    .set_sticky(self.config.switch_apps_sticky_mode);
+                self.keyboard_listener
+                    .set_inspect(self.config.switch_apps_inspect);
+                self.keyboard_listener
+                    .set_double_tap_interval(self.config.switch_apps_double_tap_interval);
+                self.keyboard_listener.set_action_keys(
+                    self.config.switch_apps_close_key,
+                    self.config.switch_apps_minimize_key,
+                );
+                if !self.keyboard_listener.is_paused() {
+                    self.start_mouse_trigger();
+                }
+                if let Err(err) = self.foreground_watcher.update(
+                    &self.config.hotkey_blacklists,
+                    self.config.on_foreground_change.as_deref(),
+                    self.config.pause_in_full_screen,
+                ) {
+                    error!("{err}");
+                }
+                if let Some(trayicon) = self.trayicon.as_mut() {
+                    trayicon.set_respect_focus_assist(self.config.respect_focus_assist);
+                }
                 info!("configuration reloaded successfully");
+                if release_hook_changed && is_remote_session() {
+                    self.revalidate();
+                }
                 if let Some(trayicon) = self.trayicon.as_mut() {
                     if let Err(err) =
                         trayicon.show_balloon("Window Switcher", "Configuration reloaded")
@@ -569,7 +1999,24 @@ impl App {
             }
             Err(err) => {
                 error!("Failed to reload configuration: {err}");
-                alert!("Failed to reload configuration: {err}");
+                let message = format!("Failed to reload configuration: {err}");
+                let shown = match self.trayicon.as_mut() {
+                    Some(trayicon) => trayicon
+                        .show_balloon_with_action(
+                            "Window Switcher",
+                            &message,
+                            Some(BalloonAction::OpenConfig),
+                        )
+                        .unwrap_or_else(|balloon_err| {
+                            error!("Failed to show balloon notification: {balloon_err}");
+                            false
+                        }),
+                    None => false,
+                };
+                // A broken config must not go unnoticed, e.g. behind Focus Assist
+                if !shown {
+                    alert!("{message}");
+                }
             }
         }
     }
@@ -595,14 +2042,730 @@ fn get_app(hwnd: HWND) -> Result<&'static mut App> {
     Ok(app)
 }
 
+/// Picks the window of the app owning `hwnd` to switch to, `None` if the app has one window.
+fn next_app_window(
+    api: &dyn WinApi,
+    options: &ListWindowsOptions,
+    state: &SwitchWindowsState,
+    hwnd: HWND,
+    reverse: bool,
+) -> Result<Option<(HWND, SwitchWindowsState)>> {
+    let windows = api.list_windows(options)?;
+    let Some((module_path, windows)) = windows
+        .iter()
+        .find(|(_, v)| v.iter().any(|(id, _)| *id == hwnd))
+    else {
+        return Ok(None);
+    };
+    let windows_len = windows.len();
+    if windows_len == 1 {
+        return Ok(None);
+    }
+    let current_id = windows[0].0;
+    let mut index = 1;
+    let mut state_id = current_id;
+    let mut state_windows = vec![];
+    if windows_len > 2 {
+        if state.modifier_released {
+            if let Some(last_id) = state.last_windows.get(module_path)
+                && *last_id != current_id
+                && let Some((i, _)) = windows.iter().enumerate().find(|(_, (v, _))| v == last_id)
+            {
+                index = i;
+            }
+        } else if let Some((cache_module_path, cache_id, cache_index, cache_windows)) =
+            state.cache.as_ref()
+            && cache_module_path == module_path
+        {
+            state_id = *cache_id;
+            let mut windows_set: IndexSet<isize> = windows.iter().map(|(v, _)| v.0 as _).collect();
+            for id in cache_windows {
+                if windows_set.contains(id) {
+                    state_windows.push(*id);
+                    windows_set.swap_remove(id);
+                }
+            }
+            state_windows.extend(windows_set);
+            index = cycle_index((*cache_index).min(windows_len - 1), windows_len, reverse);
+        }
+    }
+    if state_windows.is_empty() {
+        state_windows = windows.iter().map(|(v, _)| v.0 as _).collect();
+    }
+    // Use get() for bounds-safe access to prevent potential panic
+    let hwnd = state_windows
+        .get(index)
+        .map(|v| HWND(*v as _))
+        .unwrap_or_else(|| HWND(state_windows[0] as _));
+    let mut last_windows = state.last_windows.clone();
+    last_windows.insert(module_path.clone(), state_id);
+    let original = if state.modifier_released {
+        Some(current_id)
+    } else {
+        state.original
+    };
+    let state = SwitchWindowsState {
+        cache: Some((module_path.clone(), state_id, index, state_windows)),
+        modifier_released: false,
+        last_windows,
+        original,
+        all: None,
+    };
+    Ok(Some((hwnd, state)))
+}
+
+/// Windows of all apps with their title and module path, in the order they were last used in.
+/// `list_windows` groups them by app, the z-order puts them back in order.
+fn mru_windows(
+    api: &dyn WinApi,
+    options: &ListWindowsOptions,
+) -> Result<Vec<(HWND, String, String)>> {
+    let z_order: HashMap<isize, usize> = api
+        .get_z_order()?
+        .into_iter()
+        .enumerate()
+        .map(|(i, hwnd)| (hwnd.0 as isize, i))
+        .collect();
+    let mut windows: Vec<(HWND, String, String)> = api
+        .list_windows(options)?
+        .into_iter()
+        .flat_map(|(module_path, windows)| {
+            windows
+                .into_iter()
+                .map(move |(hwnd, title)| (hwnd, title, module_path.clone()))
+        })
+        .collect();
+    windows.sort_by_key(|(hwnd, _, _)| {
+        z_order
+            .get(&(hwnd.0 as isize))
+            .copied()
+            .unwrap_or(usize::MAX)
+    });
+    Ok(windows)
+}
+
+/// Picks the window of all apps after the foreground window, `None` if there is nothing to
+/// switch to.
+fn new_switch_all_windows(
+    api: &dyn WinApi,
+    options: &ListWindowsOptions,
+    foreground: HWND,
+    reverse: bool,
+) -> Result<Option<(usize, Vec<isize>)>> {
+    let windows: Vec<isize> = mru_windows(api, options)?
+        .into_iter()
+        .map(|(hwnd, _, _)| hwnd.0 as isize)
+        .collect();
+    let index = match windows.iter().position(|v| *v == foreground.0 as isize) {
+        Some(_) if windows.len() == 1 => return Ok(None),
+        Some(i) => cycle_index(i, windows.len(), reverse),
+        None if windows.is_empty() => return Ok(None),
+        // Nothing listed is in the foreground, e.g. the desktop, the top window is the next one
+        None if reverse => windows.len() - 1,
+        None => 0,
+    };
+    Ok(Some((index, windows)))
+}
+
+/// Most recently used window of the exe, given by its full path or its file name.
+fn find_app_window(
+    api: &dyn WinApi,
+    options: &ListWindowsOptions,
+    exe: &str,
+) -> Result<Option<HWND>> {
+    let by_name = !exe.contains(['\\', '/']);
+    let windows = api.list_windows(options)?;
+    let hwnd = windows
+        .iter()
+        .find(|(module_path, _)| {
+            let path = Path::new(module_path.as_str());
+            if by_name {
+                path.file_name()
+                    .is_some_and(|v| v.to_string_lossy().eq_ignore_ascii_case(exe))
+            } else {
+                module_path.eq_ignore_ascii_case(exe)
+            }
+        })
+        .and_then(|(_, windows)| windows.first())
+        .map(|(hwnd, _)| *hwnd);
+    Ok(hwnd)
+}
+
+/// Window title, full path, PID and elevation of the app, to tell copies of a program apart
+/// and to help writing blacklists and rules.
+fn details_text(api: &dyn WinApi, hwnd: HWND, title: &str, module_path: &str) -> String {
+    let (pid, elevated) = api.get_window_process(hwnd);
+    let elevation = match elevated {
+        Some(true) => "elevated",
+        Some(false) => "not elevated",
+        None => "unknown elevation",
+    };
+    format!("{title}  |  {module_path}  |  PID {pid}  |  {elevation}")
+}
+
+fn hotkey_names(config: &Config) -> HashMap<u32, String> {
+    config
+        .to_hotkeys()
+        .into_iter()
+        .map(|v| (v.id, v.name.clone()))
+        .collect()
+}
+
+/// Lists the apps for the switch apps overlay with the one after the foreground app selected,
+/// `None` if there are no apps.
+fn new_switch_apps_state(
+    api: &dyn WinApi,
+    options: &ListWindowsOptions,
+    config: &Config,
+    apps_order: &[String],
+    statistics: &Statistics,
+    cached_icons: &mut HashMap<(String, i32), CachedIcon>,
+    reverse: bool,
+) -> Result<Option<SwitchAppsState>> {
+    let windows = api.list_windows(options)?;
+    // Switching blind only needs the order of the apps, nothing is painted
+    let show_ui = config.switch_apps_show_ui;
+    // Only collected for the badge, going through the audio sessions is slow
+    let audio_module_paths = if show_ui && config.overlay_audio_badge {
+        api.get_audio_module_paths()
+    } else {
+        Default::default()
+    };
+    let show_desktops = show_ui && config.overlay_desktop_badge && !options.only_current_desktop;
+    // Extracted at the size drawn, icons upscaled from 32px look blurry in large overlays
+    let icon_size =
+        config.switch_apps_icon_size * get_system_dpi() as i32 / USER_DEFAULT_SCREEN_DPI as i32;
+    // Read from the registry once, not for every app on another desktop
+    let desktops = if show_desktops {
+        api.list_virtual_desktop_ids()
+    } else {
+        vec![]
+    };
+    let mut apps = vec![];
+    for (module_path, hwnds) in windows.iter() {
+        // hwnds is guaranteed to be non-empty by list_windows implementation
+        let (module_hwnd, title) = if hwnds.is_empty() {
+            continue;
+        } else if api.is_iconic_window(hwnds[0].0) {
+            hwnds.last().unwrap_or(&hwnds[0])
+        } else {
+            &hwnds[0]
+        };
+        let module_hwnd = *module_hwnd;
+        let (hicon, accent) = if show_ui {
+            let key = (module_path.clone(), icon_size);
+            let icon = cached_icons.entry(key).or_insert_with(|| CachedIcon {
+                hicon: api.get_app_icon(
+                    &config.switch_apps_override_icons,
+                    module_path,
+                    module_hwnd,
+                    icon_size,
+                ),
+                accent: None,
+            });
+            // Going through the pixels is slow, only done once the accent is used
+            let accent = if config.theme.accent_from_icon {
+                *icon
+                    .accent
+                    .get_or_insert_with(|| get_icon_accent_color(icon.hicon))
+            } else {
+                None
+            };
+            (icon.hicon, accent)
+        } else {
+            (HICON::default(), None)
+        };
+        apps.push(SwitchAppsItem {
+            module_path: module_path.clone(),
+            hicon,
+            accent,
+            hwnd: module_hwnd,
+            title: title.clone(),
+            windows: hwnds.len(),
+            minimized: api.is_iconic_window(module_hwnd),
+            // Elevated windows are left out otherwise, no need to open their processes
+            elevated: show_ui
+                && options.include_elevated
+                && !options.is_admin
+                && api.is_elevated_window(module_hwnd),
+            desktop: if show_desktops {
+                api.get_window_desktop_number(module_hwnd, &desktops)
+            } else {
+                None
+            },
+            playing_audio: audio_module_paths.contains(module_path),
+            // Opening the process is too slow for every paint
+            details: (show_ui && config.overlay_show_details)
+                .then(|| details_text(api, module_hwnd, title, module_path)),
+        });
+    }
+    let num_apps = apps.len();
+    if num_apps == 0 {
+        return Ok(None);
+    }
+
+    // The first app is the foreground one, remember it before sorting
+    let current_module_path = apps[0].module_path.clone();
+    match config.switch_apps_sort {
+        AppsSort::Mru => {}
+        AppsSort::Custom => {
+            apps.sort_by_key(|item| {
+                apps_order
+                    .iter()
+                    .position(|v| v == &item.module_path)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        AppsSort::Frecency => {
+            // Stable, apps never switched to keep their most recently used order
+            let now = SystemTime::now();
+            apps.sort_by(|a, b| {
+                statistics
+                    .frecency(&b.module_path, now)
+                    .total_cmp(&statistics.frecency(&a.module_path, now))
+            });
+        }
+    }
+    let current_index = apps
+        .iter()
+        .position(|v| v.module_path == current_module_path)
+        .unwrap_or(0);
+
+    Ok(Some(SwitchAppsState {
+        apps,
+        index: cycle_index(current_index, num_apps, reverse),
+        hover: None,
+        drag: None,
+        wheel_delta: 0,
+        preview: false,
+    }))
+}
+
+/// Interval of the slideshow after running for a while, halved every second down to an eighth.
+fn accelerated_interval(interval: u32, elapsed: Duration) -> u32 {
+    let halvings = elapsed.as_secs().min(3) as u32;
+    (interval >> halvings).max(1)
+}
+
+/// Moves to the next or previous index, wrapping around at both ends.
+fn cycle_index(index: usize, len: usize, reverse: bool) -> usize {
+    if len == 0 {
+        0
+    } else if reverse {
+        (index + len - 1) % len
+    } else {
+        (index + 1) % len
+    }
+}
+
 #[derive(Debug)]
 struct SwitchWindowsState {
     cache: Option<(String, HWND, usize, Vec<isize>)>,
     modifier_released: bool,
+    /// Window each app was switched away from last, the next press returns to it even if
+    /// other apps were used in between.
+    last_windows: HashMap<String, HWND>,
+    /// Window in the foreground when the switch started, Esc returns to it.
+    original: Option<HWND>,
+    /// Index and windows of all apps while switching all windows.
+    all: Option<(usize, Vec<isize>)>,
+}
+
+impl Default for SwitchWindowsState {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            modifier_released: true,
+            last_windows: Default::default(),
+            original: None,
+            all: None,
+        }
+    }
+}
+
+impl SwitchWindowsState {
+    /// Ends the current switch, the windows remembered per app are kept.
+    fn reset(&mut self) {
+        self.cache = None;
+        self.modifier_released = true;
+        self.original = None;
+        self.all = None;
+    }
+}
+
+/// Windows listed by the search palette, narrowed down by the query.
+#[derive(Debug)]
+struct SearchState {
+    query: String,
+    /// Windows of all apps from the most recently used, with their title and exe name.
+    windows: Vec<(HWND, String, String)>,
+    /// Indices of the windows matching the query, best first.
+    matches: Vec<usize>,
+    /// Match Enter switches to.
+    index: usize,
+}
+
+impl SearchState {
+    fn new(windows: Vec<(HWND, String, String)>) -> Self {
+        let windows = windows
+            .into_iter()
+            .map(|(hwnd, title, module_path)| {
+                let name = Path::new(&module_path)
+                    .file_name()
+                    .map(|v| v.to_string_lossy().into_owned())
+                    .unwrap_or(module_path);
+                (hwnd, title, name)
+            })
+            .collect();
+        let mut state = Self {
+            query: String::new(),
+            windows,
+            matches: vec![],
+            index: 0,
+        };
+        state.update_matches();
+        state
+    }
+
+    /// Edits the query, backspace removes the last character. Whether the query changed.
+    fn type_char(&mut self, c: char) -> bool {
+        match c {
+            '\x08' => {
+                if self.query.pop().is_none() {
+                    return false;
+                }
+            }
+            c if c.is_control() => return false,
+            c => self.query.push(c),
+        }
+        self.update_matches();
+        true
+    }
+
+    /// Matches the windows against the query again, the best match gets selected.
+    fn update_matches(&mut self) {
+        self.matches = rank(
+            &self.query,
+            self.windows
+                .iter()
+                .map(|(_, title, name)| [title.as_str(), name.as_str()]),
+        );
+        self.index = 0;
+    }
+
+    fn selected(&self) -> Option<HWND> {
+        self.matches
+            .get(self.index)
+            .and_then(|i| self.windows.get(*i))
+            .map(|(hwnd, _, _)| *hwnd)
+    }
+
+    fn ui_state(&self) -> SearchUiState {
+        SearchUiState {
+            query: self.query.clone(),
+            rows: self
+                .matches
+                .iter()
+                .map(|i| {
+                    let (_, title, name) = &self.windows[*i];
+                    format!("{title} - {name}")
+                })
+                .collect(),
+            index: self.index,
+        }
+    }
+}
+
+/// What the search palette shows.
+#[derive(Debug)]
+pub struct SearchUiState {
+    pub query: String,
+    /// Matching windows as their title and exe name, best first.
+    pub rows: Vec<String>,
+    /// Match being selected.
+    pub index: usize,
+}
+
+/// Windows listed while switching windows of the same app.
+#[derive(Debug)]
+pub struct SwitchWindowsUiState {
+    pub titles: Vec<String>,
+    /// Window being switched to.
+    pub index: usize,
 }
 
 #[derive(Debug)]
 pub struct SwitchAppsState {
-    pub apps: Vec<(HICON, HWND)>,
+    pub apps: Vec<SwitchAppsItem>,
     pub index: usize,
+    /// App under the mouse cursor.
+    pub hover: Option<usize>,
+    drag: Option<AppDrag>,
+    /// Wheel rotation not yet turned into a step.
+    wheel_delta: i32,
+    /// Show the preview of the selected window even if it is off, as the slideshow does.
+    pub preview: bool,
+}
+
+/// Icon of an app with its accent color, both worked out once per app.
+#[derive(Debug, Clone, Copy)]
+struct CachedIcon {
+    hicon: HICON,
+    /// Worked out the first time it is needed, `Some(None)` for gray icons.
+    accent: Option<Option<u32>>,
+}
+
+#[derive(Debug)]
+pub struct SwitchAppsItem {
+    pub module_path: String,
+    pub hicon: HICON,
+    /// Dominant color of the icon as `0xRRGGBB`, `None` for gray icons.
+    pub accent: Option<u32>,
+    pub hwnd: HWND,
+    /// Title of the window switched to, tells instances of the same app apart.
+    pub title: String,
+    /// Number of windows of the app, cycled through by switch windows.
+    pub windows: usize,
+    /// Whether the window is minimized, switching to it restores it.
+    pub minimized: bool,
+    /// Whether the app runs as administrator while the switcher does not, switching to it
+    /// may fail.
+    pub elevated: bool,
+    /// Number of the virtual desktop of the window when it is not the current one, switching
+    /// to it moves to that desktop.
+    pub desktop: Option<usize>,
+    /// Whether a process of the app is playing audio.
+    pub playing_audio: bool,
+    /// Title, path, PID and elevation for the details line, `None` unless it is shown.
+    pub details: Option<String>,
+}
+
+/// Auto-advancing the selection while the switch apps key is held.
+#[derive(Debug)]
+struct Slideshow {
+    reverse: bool,
+    /// When the initial delay passed and the interval took over.
+    started: Option<Instant>,
+    /// Milliseconds the timer currently runs at.
+    interval: u32,
+}
+
+#[derive(Debug)]
+struct AppDrag {
+    index: usize,
+    moved: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MockWinApi;
+
+    fn released_state() -> SwitchWindowsState {
+        SwitchWindowsState::default()
+    }
+
+    fn switch_apps(
+        api: &MockWinApi,
+        config: &Config,
+        apps_order: &[String],
+        reverse: bool,
+    ) -> Vec<(String, isize, bool)> {
+        let state = new_switch_apps_state(
+            api,
+            &Default::default(),
+            config,
+            apps_order,
+            &Statistics::default(),
+            &mut HashMap::new(),
+            reverse,
+        )
+        .unwrap()
+        .unwrap();
+        state
+            .apps
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.module_path.clone(), v.hwnd.0 as isize, i == state.index))
+            .collect()
+    }
+
+    #[test]
+    fn test_cycle_index() {
+        assert_eq!(cycle_index(0, 3, false), 1);
+        assert_eq!(cycle_index(2, 3, false), 0);
+        assert_eq!(cycle_index(0, 3, true), 2);
+        assert_eq!(cycle_index(0, 1, true), 0);
+    }
+
+    #[test]
+    fn test_switch_windows() {
+        let api = MockWinApi::default()
+            .with_window("a.exe", 1)
+            .with_window("a.exe", 2)
+            .with_window("a.exe", 3)
+            .with_window("b.exe", 4);
+        let options = Default::default();
+        let (hwnd, state) = next_app_window(&api, &options, &released_state(), HWND(1 as _), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hwnd, HWND(2 as _));
+        // Pressing again while the modifier is held moves on through the same order
+        let (hwnd, state) = next_app_window(&api, &options, &state, HWND(2 as _), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hwnd, HWND(3 as _));
+        assert_eq!(state.original, Some(HWND(1 as _)));
+        let (hwnd, _) = next_app_window(&api, &options, &state, HWND(3 as _), true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hwnd, HWND(2 as _));
+        // A single window has nothing to switch to
+        assert!(
+            next_app_window(&api, &options, &released_state(), HWND(4 as _), false)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_switch_windows_returns_to_last_window() {
+        let options = Default::default();
+        let api = MockWinApi::default()
+            .with_window("a.exe", 1)
+            .with_window("a.exe", 2)
+            .with_window("a.exe", 3)
+            .with_window("b.exe", 4)
+            .with_window("b.exe", 5);
+        let (_, mut state) =
+            next_app_window(&api, &options, &released_state(), HWND(1 as _), false)
+                .unwrap()
+                .unwrap();
+        state.modifier_released = true;
+        // Switching windows of another app in between
+        let (_, mut state) = next_app_window(&api, &options, &state, HWND(4 as _), false)
+            .unwrap()
+            .unwrap();
+        state.modifier_released = true;
+        // Back in the first app, whose windows were raised in another order meanwhile
+        let api = MockWinApi::default()
+            .with_window("a.exe", 2)
+            .with_window("a.exe", 3)
+            .with_window("a.exe", 1);
+        let (hwnd, _) = next_app_window(&api, &options, &state, HWND(2 as _), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hwnd, HWND(1 as _));
+    }
+
+    #[test]
+    fn test_switch_all_windows() {
+        let api = MockWinApi::default()
+            .with_window("C:\\a.exe", 1)
+            .with_window("C:\\b.exe", 2)
+            .with_window("C:\\a.exe", 3);
+        let options = Default::default();
+        let switch = |foreground: isize, reverse| {
+            new_switch_all_windows(&api, &options, HWND(foreground as _), reverse).unwrap()
+        };
+        // list_windows groups the windows by app, all windows are in z-order
+        assert_eq!(switch(1, false), Some((1, vec![1, 2, 3])));
+        assert_eq!(switch(1, true), Some((2, vec![1, 2, 3])));
+        assert_eq!(switch(0, false), Some((0, vec![1, 2, 3])));
+        let single = MockWinApi::default().with_window("C:\\a.exe", 1);
+        assert_eq!(
+            new_switch_all_windows(&single, &options, HWND(1 as _), false).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_state() {
+        let api = MockWinApi::default()
+            .with_window("C:\\Windows\\notepad.exe", 1)
+            .with_window("C:\\Tools\\Code.exe", 2)
+            .with_window("C:\\Windows\\notepad.exe", 3);
+        let mut state = SearchState::new(mru_windows(&api, &Default::default()).unwrap());
+        assert_eq!(state.matches, vec![0, 1, 2]);
+        assert_eq!(state.selected(), Some(HWND(1 as _)));
+        assert!(state.type_char('c'));
+        assert!(state.type_char('o'));
+        assert_eq!(state.selected(), Some(HWND(2 as _)));
+        assert_eq!(state.ui_state().rows, vec!["window 2 - Code.exe"]);
+        assert!(state.type_char('\x08'));
+        assert!(state.type_char('\x08'));
+        assert!(!state.type_char('\x08'));
+        assert!(!state.type_char('\t'));
+        assert_eq!(state.matches.len(), 3);
+    }
+
+    #[test]
+    fn test_accelerated_interval() {
+        assert_eq!(accelerated_interval(800, Duration::from_millis(500)), 800);
+        assert_eq!(accelerated_interval(800, Duration::from_millis(1500)), 400);
+        assert_eq!(accelerated_interval(800, Duration::from_secs(2)), 200);
+        assert_eq!(accelerated_interval(800, Duration::from_secs(10)), 100);
+        assert_eq!(accelerated_interval(1, Duration::from_secs(10)), 1);
+    }
+
+    #[test]
+    fn test_find_app_window() {
+        let api = MockWinApi::default()
+            .with_window("C:\\Windows\\notepad.exe", 1)
+            .with_window("C:\\Windows\\notepad.exe", 2)
+            .with_window("C:\\Tools\\b.exe", 3);
+        let options = Default::default();
+        let find = |exe| find_app_window(&api, &options, exe).unwrap();
+        assert_eq!(find("notepad.exe"), Some(HWND(1 as _)));
+        assert_eq!(find("c:\\windows\\notepad.exe"), Some(HWND(1 as _)));
+        assert_eq!(find("C:\\Other\\b.exe"), None);
+        assert_eq!(find("c.exe"), None);
+    }
+
+    #[test]
+    fn test_switch_apps() {
+        let api = MockWinApi::default()
+            .with_window("a.exe", 1)
+            .with_iconic_window("b.exe", 2)
+            .with_window("b.exe", 3)
+            .with_window("c.exe", 4);
+        let config = Config::default();
+        assert_eq!(
+            switch_apps(&api, &config, &[], false),
+            vec![
+                ("a.exe".into(), 1, false),
+                // The last window of an app is used when its top window is minimized
+                ("b.exe".into(), 3, true),
+                ("c.exe".into(), 4, false),
+            ]
+        );
+        assert_eq!(
+            switch_apps(&api, &config, &[], true),
+            vec![
+                ("a.exe".into(), 1, false),
+                ("b.exe".into(), 3, false),
+                ("c.exe".into(), 4, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_switch_apps_custom_order() {
+        let api = MockWinApi::default()
+            .with_window("a.exe", 1)
+            .with_window("b.exe", 2)
+            .with_window("c.exe", 3);
+        let ini = ini::Ini::load_from_str("[switch-apps]\nsort = custom").unwrap();
+        let config = Config::load(&ini).unwrap();
+        let order = vec!["c.exe".to_string(), "a.exe".to_string()];
+        // The app after the foreground one is selected in the custom order
+        assert_eq!(
+            switch_apps(&api, &config, &order, false),
+            vec![
+                ("c.exe".into(), 3, false),
+                ("a.exe".into(), 1, false),
+                ("b.exe".into(), 2, true),
+            ]
+        );
+    }
 }
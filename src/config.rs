@@ -1,38 +1,167 @@
-use std::{collections::HashSet, fs, path::PathBuf, process::Command};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, iter,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
 
 use anyhow::{Result, anyhow};
 use indexmap::IndexMap;
 use ini::{Ini, ParseOption};
 use log::LevelFilter;
-use windows::core::w;
+use windows::Win32::Globalization::{GetLocaleInfoEx, LOCALE_IREADINGLAYOUT, LOCALE_RETURN_NUMBER};
+use windows::core::{PCWSTR, w};
 
 use crate::utils::{RegKey, get_exe_folder};
 
 pub const SWITCH_WINDOWS_HOTKEY_ID: u32 = 1;
 pub const SWITCH_APPS_HOTKEY_ID: u32 = 2;
+pub const MINIMIZE_OTHERS_HOTKEY_ID: u32 = 3;
+pub const TRANSPARENCY_HOTKEY_ID: u32 = 4;
+pub const PIN_HOTKEY_ID: u32 = 5;
+pub const PAUSE_HOTKEY_ID: u32 = 6;
+pub const MOVE_TO_NEXT_MONITOR_HOTKEY_ID: u32 = 7;
+pub const TOPMOST_HOTKEY_ID: u32 = 8;
+pub const SWITCH_ALL_WINDOWS_HOTKEY_ID: u32 = 9;
+pub const SEARCH_HOTKEY_ID: u32 = 10;
+/// Ids of the `[action.<name>]` hotkeys start here, in the order of the sections.
+pub const ACTION_HOTKEY_ID_BASE: u32 = 100;
 
 const DEFAULT_CONFIG: &str = include_str!("../window-switcher.ini");
 
+/// Config file path given on the command line, overrides the default lookup.
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Config {
     pub trayicon: bool,
+    pub instance_name: Option<String>,
+    pub remote_session_release_hook: bool,
+    /// How the hotkeys are caught, read at startup only.
+    pub hotkey_mode: HotkeyMode,
+    pub exclude_desktops: Vec<String>,
+    pub on_foreground_change: Option<String>,
+    pub respect_focus_assist: bool,
+    /// Match symbol keys of hotkeys by the character they type in the active keyboard layout.
+    pub hotkeys_follow_layout: bool,
+    /// Leave the hotkeys to the foreground app while it runs full screen, e.g. a game.
+    pub pause_in_full_screen: bool,
+    /// Scan codes of the keys cancelling a switch, empty to never cancel.
+    pub cancel_keys: Vec<u32>,
+    /// Exes each hotkey is left to while they are in the foreground, by hotkey id.
+    pub hotkey_blacklists: HashMap<u32, HashSet<String>>,
     pub log_level: LevelFilter,
     pub log_file: Option<PathBuf>,
     pub switch_windows_hotkey: Hotkey,
-    pub switch_windows_blacklist: HashSet<String>,
+    /// More hotkeys switching windows the same way, the rest of the hotkey list.
+    pub switch_windows_alt_hotkeys: Vec<Hotkey>,
     pub switch_windows_ignore_minimal: bool,
     switch_windows_only_current_desktop: Option<bool>,
+    /// List the titles of the app's windows while switching them.
+    pub switch_windows_show_list: bool,
+    /// Cycles through the windows of all apps from the most recently used.
+    pub switch_all_windows_hotkey: Option<Hotkey>,
     pub switch_apps_enable: bool,
     pub switch_apps_hotkey: Hotkey,
+    /// More hotkeys switching apps the same way, the rest of the hotkey list.
+    pub switch_apps_alt_hotkeys: Vec<Hotkey>,
     pub switch_apps_ignore_minimal: bool,
     pub switch_apps_override_icons: IndexMap<String, String>,
     switch_apps_only_current_desktop: Option<bool>,
+    pub switch_apps_sort: AppsSort,
+    pub switch_apps_slideshow: bool,
+    pub switch_apps_slideshow_delay: u32,
+    pub switch_apps_slideshow_interval: u32,
+    /// Step faster the longer the slideshow runs.
+    pub switch_apps_slideshow_accelerate: bool,
+    /// Milliseconds to hold the hotkey before the overlay is shown, 0 shows it at once.
+    pub switch_apps_overlay_delay: u32,
+    /// Size of the icons in the overlay in pixels at 100% scale.
+    pub switch_apps_icon_size: i32,
+    /// List apps running as administrator when not elevated, marked with a shield.
+    pub switch_apps_show_elevated: bool,
+    /// Show the overlay while switching apps, without it apps are switched blindly.
+    pub switch_apps_show_ui: bool,
+    /// Key closing the selected window while the overlay is shown.
+    pub switch_apps_close_key: Option<SingleKey>,
+    /// Keep the overlay open after releasing the modifier, until Enter or Esc.
+    pub switch_apps_sticky_mode: bool,
+    /// Keep the overlay open like in sticky mode when ctrl is held while releasing the modifier.
+    pub switch_apps_inspect: bool,
+    /// Milliseconds between two taps of the modifier switching to the last app, 0 disables.
+    pub switch_apps_double_tap_interval: u32,
+    pub switch_apps_mouse_trigger: MouseTrigger,
+    /// Key minimizing the selected window while the overlay is shown.
+    pub switch_apps_minimize_key: Option<SingleKey>,
+    pub overlay_show_details: bool,
+    pub overlay_show_titles: bool,
+    pub overlay_thumbnails: bool,
+    pub overlay_renderer: Renderer,
+    pub overlay_max_rows: usize,
+    /// Apps shown at once, the overlay scrolls through the others. 0 shows all apps.
+    pub overlay_max_items: usize,
+    pub overlay_wrap: OverlayWrap,
+    pub overlay_monitor: OverlayMonitor,
+    /// Cancel the switch on a click outside of the overlay, on unless turned off.
+    pub overlay_close_on_outside_click: bool,
+    pub overlay_animations: bool,
+    /// Number the first nine icons and switch to them with the number keys.
+    pub overlay_quick_select: bool,
+    /// Show how many windows an app has on its icon.
+    pub overlay_window_count: bool,
+    /// Mark the icons of apps whose window is minimized.
+    pub overlay_minimized_badge: bool,
+    /// Number the icons of apps on other virtual desktops with their desktop.
+    pub overlay_desktop_badge: bool,
+    /// Fade the icons of the apps that are not selected into the background.
+    pub overlay_dim_icons: bool,
+    /// Mark the icons of apps that are playing audio.
+    pub overlay_audio_badge: bool,
+    pub overlay_highlight_style: HighlightStyle,
+    /// Show copies of the overlay on the other monitors as well.
+    pub overlay_all_monitors: bool,
+    /// Keep the overlay out of screenshots, screen recordings and streams.
+    pub overlay_hide_from_capture: bool,
+    /// Blur what is behind the overlay, which then gets a translucent background.
+    pub overlay_blur: bool,
+    /// Corner radius in pixels at 100% scale, `None` rounds the corners on Windows 11 only.
+    pub overlay_corner_radius: Option<i32>,
+    /// Width of the border in pixels at 100% scale, `None` draws it only with a theme color.
+    pub overlay_border: Option<i32>,
+    /// Show a large live preview of the selected window below the icons.
+    pub overlay_preview: bool,
+    overlay_rtl: Option<bool>,
+    pub overlay_position: OverlayPosition,
+    /// Moves the overlay from its position, in pixels at 100% scale.
+    pub overlay_offset: (i32, i32),
+    pub theme: Theme,
+    pub minimize_others_hotkey: Option<Hotkey>,
+    pub transparency_hotkey: Option<Hotkey>,
+    pub pin_apps: HashSet<String>,
+    pub pin_hotkey: Option<Hotkey>,
+    pub pause_hotkey: Option<Hotkey>,
+    pub move_to_next_monitor_hotkey: Option<Hotkey>,
+    pub topmost_hotkey: Option<Hotkey>,
+    /// Opens the palette searching the windows by title and exe name.
+    pub search_hotkey: Option<Hotkey>,
+    pub action_hotkeys: Vec<(Hotkey, HotkeyAction)>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             trayicon: true,
+            instance_name: None,
+            remote_session_release_hook: false,
+            hotkey_mode: HotkeyMode::Auto,
+            exclude_desktops: Default::default(),
+            on_foreground_change: None,
+            respect_focus_assist: true,
+            hotkeys_follow_layout: false,
+            pause_in_full_screen: false,
+            cancel_keys: vec![0x01],
+            hotkey_blacklists: Default::default(),
             log_level: LevelFilter::Info,
             log_file: None,
             // These unwraps are safe because the hotkey strings are compile-time constants
@@ -43,15 +172,75 @@ impl Default for Config {
                 "alt + `",
             )
             .expect("default switch_windows_hotkey should be valid"),
-            switch_windows_blacklist: Default::default(),
+            switch_windows_alt_hotkeys: vec![],
             switch_windows_ignore_minimal: false,
             switch_windows_only_current_desktop: None,
+            switch_windows_show_list: false,
+            switch_all_windows_hotkey: None,
             switch_apps_enable: false,
             switch_apps_hotkey: Hotkey::create(SWITCH_APPS_HOTKEY_ID, "switch apps", "alt + tab")
                 .expect("default switch_apps_hotkey should be valid"),
+            switch_apps_alt_hotkeys: vec![],
             switch_apps_ignore_minimal: false,
             switch_apps_override_icons: Default::default(),
             switch_apps_only_current_desktop: None,
+            switch_apps_sort: AppsSort::default(),
+            switch_apps_slideshow: false,
+            switch_apps_slideshow_delay: 600,
+            switch_apps_slideshow_interval: 800,
+            switch_apps_slideshow_accelerate: false,
+            switch_apps_overlay_delay: 0,
+            switch_apps_icon_size: 64,
+            switch_apps_show_elevated: false,
+            switch_apps_show_ui: true,
+            switch_apps_close_key: Some(SingleKey {
+                code: 0x53,
+                letter: None,
+            }),
+            switch_apps_sticky_mode: false,
+            switch_apps_inspect: false,
+            switch_apps_double_tap_interval: 0,
+            switch_apps_mouse_trigger: MouseTrigger::None,
+            switch_apps_minimize_key: Some(SingleKey {
+                code: 0x32,
+                letter: Some('m'),
+            }),
+            overlay_show_details: false,
+            overlay_show_titles: false,
+            overlay_thumbnails: false,
+            overlay_renderer: Renderer::Gdi,
+            overlay_max_rows: 1,
+            overlay_max_items: 0,
+            overlay_wrap: OverlayWrap::Horizontal,
+            overlay_monitor: OverlayMonitor::Cursor,
+            overlay_close_on_outside_click: true,
+            overlay_animations: false,
+            overlay_quick_select: false,
+            overlay_window_count: false,
+            overlay_minimized_badge: false,
+            overlay_desktop_badge: false,
+            overlay_dim_icons: false,
+            overlay_audio_badge: false,
+            overlay_highlight_style: HighlightStyle::default(),
+            overlay_all_monitors: false,
+            overlay_hide_from_capture: false,
+            overlay_blur: false,
+            overlay_corner_radius: None,
+            overlay_border: None,
+            overlay_preview: false,
+            overlay_rtl: None,
+            overlay_position: OverlayPosition::default(),
+            overlay_offset: (0, 0),
+            theme: Theme::default(),
+            minimize_others_hotkey: None,
+            transparency_hotkey: None,
+            pin_apps: Default::default(),
+            pin_hotkey: None,
+            pause_hotkey: None,
+            move_to_next_monitor_hotkey: None,
+            topmost_hotkey: None,
+            search_hotkey: None,
+            action_hotkeys: Default::default(),
         }
     }
 }
@@ -63,39 +252,94 @@ impl Config {
             if let Some(v) = section.get("trayicon").and_then(Config::to_bool) {
                 conf.trayicon = v;
             }
+            if let Some(v) = section.get("instance_name")
+                && !v.trim().is_empty()
+            {
+                conf.instance_name = Some(v.trim().to_string());
+            }
+            if let Some(v) = section
+                .get("remote_session_release_hook")
+                .and_then(Config::to_bool)
+            {
+                conf.remote_session_release_hook = v;
+            }
+            if let Some(v) = section.get("hotkey_mode") {
+                conf.hotkey_mode =
+                    HotkeyMode::parse(v).ok_or_else(|| anyhow!("Invalid hotkey mode"))?;
+            }
+            if let Some(v) = section.get("exclude_desktops") {
+                conf.exclude_desktops = v
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+            }
+            if let Some(v) = section
+                .get("respect_focus_assist")
+                .and_then(Config::to_bool)
+            {
+                conf.respect_focus_assist = v;
+            }
+            if let Some(v) = section
+                .get("hotkeys_follow_layout")
+                .and_then(Config::to_bool)
+            {
+                conf.hotkeys_follow_layout = v;
+            }
+            if let Some(v) = section
+                .get("pause_in_full_screen")
+                .and_then(Config::to_bool)
+            {
+                conf.pause_in_full_screen = v;
+            }
+            if let Some(v) = section.get("cancel_key") {
+                conf.cancel_keys =
+                    Hotkey::parse_key_list(v).ok_or_else(|| anyhow!("Invalid cancel key"))?;
+            }
+            if let Some(v) = section.get("on_foreground_change")
+                && !v.trim().is_empty()
+            {
+                conf.on_foreground_change = Some(v.trim().to_string());
+            }
         }
 
         if let Some(section) = ini_conf.section(Some("log")) {
             if let Some(level) = section.get("level").and_then(|v| v.parse().ok()) {
                 conf.log_level = level;
             }
-            if let Some(path) = section.get("path").map(normalize_path_value) {
-                if !path.trim().is_empty() {
-                    let mut path = PathBuf::from(path);
-                    if !path.is_absolute() {
-                        let parent = get_exe_folder()?;
-                        path = parent.join(path);
-                    }
-                    conf.log_file = Some(path);
+            if let Some(path) = section.get("path").map(normalize_path_value)
+                && !path.trim().is_empty()
+            {
+                let mut path = PathBuf::from(path);
+                if !path.is_absolute() {
+                    let parent = get_exe_folder()?;
+                    path = parent.join(path);
                 }
+                conf.log_file = Some(path);
             }
         }
 
         if let Some(section) = ini_conf.section(Some("switch-windows")) {
-            if let Some(v) = section.get("hotkey") {
-                if !v.trim().is_empty() {
-                    conf.switch_windows_hotkey =
-                        Hotkey::create(SWITCH_WINDOWS_HOTKEY_ID, "switch windows", v)?;
+            if let Some(v) = section.get("hotkey")
+                && !v.trim().is_empty()
+            {
+                let mut hotkeys =
+                    Hotkey::create_list(SWITCH_WINDOWS_HOTKEY_ID, "switch windows", v)?.into_iter();
+                if let Some(hotkey) = hotkeys.next() {
+                    conf.switch_windows_hotkey = hotkey;
+                    conf.switch_windows_alt_hotkeys = hotkeys.collect();
                 }
             }
-
-            if let Some(v) = section
-                .get("blacklist")
-                .map(normalize_path_value)
-                .map(|v| v.split(',').map(|v| v.trim().to_string()).collect())
+            if let Some(v) = section.get("reverse_key")
+                && !v.trim().is_empty()
             {
-                conf.switch_windows_blacklist = v;
+                for hotkey in iter::once(&mut conf.switch_windows_hotkey)
+                    .chain(&mut conf.switch_windows_alt_hotkeys)
+                {
+                    hotkey.set_reverse_key(v)?;
+                }
             }
+
             if let Some(v) = section.get("ignore_minimal").and_then(Config::to_bool) {
                 conf.switch_windows_ignore_minimal = v;
             }
@@ -105,15 +349,43 @@ impl Config {
             {
                 conf.switch_windows_only_current_desktop = Some(v);
             }
+            if let Some(v) = section.get("show_list").and_then(Config::to_bool) {
+                conf.switch_windows_show_list = v;
+            }
+        }
+        if let Some(section) = ini_conf.section(Some("switch-all-windows"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            let mut hotkey = Hotkey::create(SWITCH_ALL_WINDOWS_HOTKEY_ID, "switch all windows", v)?;
+            if let Some(v) = section.get("reverse_key")
+                && !v.trim().is_empty()
+            {
+                hotkey.set_reverse_key(v)?;
+            }
+            conf.switch_all_windows_hotkey = Some(hotkey);
         }
         if let Some(section) = ini_conf.section(Some("switch-apps")) {
             if let Some(v) = section.get("enable").and_then(Config::to_bool) {
                 conf.switch_apps_enable = v;
             }
-            if let Some(v) = section.get("hotkey") {
-                if !v.trim().is_empty() {
-                    conf.switch_apps_hotkey =
-                        Hotkey::create(SWITCH_APPS_HOTKEY_ID, "switch apps", v)?;
+            if let Some(v) = section.get("hotkey")
+                && !v.trim().is_empty()
+            {
+                let mut hotkeys =
+                    Hotkey::create_list(SWITCH_APPS_HOTKEY_ID, "switch apps", v)?.into_iter();
+                if let Some(hotkey) = hotkeys.next() {
+                    conf.switch_apps_hotkey = hotkey;
+                    conf.switch_apps_alt_hotkeys = hotkeys.collect();
+                }
+            }
+            if let Some(v) = section.get("reverse_key")
+                && !v.trim().is_empty()
+            {
+                for hotkey in iter::once(&mut conf.switch_apps_hotkey)
+                    .chain(&mut conf.switch_apps_alt_hotkeys)
+                {
+                    hotkey.set_reverse_key(v)?;
                 }
             }
             if let Some(v) = section.get("ignore_minimal").and_then(Config::to_bool) {
@@ -136,18 +408,386 @@ impl Config {
             {
                 conf.switch_apps_only_current_desktop = Some(v);
             }
+            if let Some(v) = section.get("sort").and_then(AppsSort::parse) {
+                conf.switch_apps_sort = v;
+            }
+            if let Some(v) = section.get("slideshow").and_then(Config::to_bool) {
+                conf.switch_apps_slideshow = v;
+            }
+            if let Some(v) = section
+                .get("slideshow_delay")
+                .and_then(|v| v.trim().parse().ok())
+            {
+                conf.switch_apps_slideshow_delay = v;
+            }
+            if let Some(v) = section
+                .get("slideshow_interval")
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|v| *v > 0)
+            {
+                conf.switch_apps_slideshow_interval = v;
+            }
+            if let Some(v) = section
+                .get("slideshow_accelerate")
+                .and_then(Config::to_bool)
+            {
+                conf.switch_apps_slideshow_accelerate = v;
+            }
+            if let Some(v) = section
+                .get("overlay_delay")
+                .and_then(|v| v.trim().parse().ok())
+            {
+                conf.switch_apps_overlay_delay = v;
+            }
+            if let Some(v) = section
+                .get("icon_size")
+                .and_then(|v| v.trim().parse::<i32>().ok())
+            {
+                conf.switch_apps_icon_size = v.clamp(16, 256);
+            }
+            if let Some(v) = section.get("show_elevated").and_then(Config::to_bool) {
+                conf.switch_apps_show_elevated = v;
+            }
+            if let Some(v) = section.get("show_ui").and_then(Config::to_bool) {
+                conf.switch_apps_show_ui = v;
+            }
+            if let Some(v) = section.get("sticky_mode").and_then(Config::to_bool) {
+                conf.switch_apps_sticky_mode = v;
+            }
+            if let Some(v) = section.get("inspect").and_then(Config::to_bool) {
+                conf.switch_apps_inspect = v;
+            }
+            if let Some(v) = section
+                .get("double_tap_interval")
+                .and_then(|v| v.trim().parse().ok())
+            {
+                conf.switch_apps_double_tap_interval = v;
+            }
+            if let Some(v) = section.get("mouse_trigger").and_then(MouseTrigger::parse) {
+                conf.switch_apps_mouse_trigger = v;
+            }
+            if let Some(v) = section.get("close_key") {
+                conf.switch_apps_close_key = if v.trim().is_empty() {
+                    None
+                } else {
+                    Some(
+                        SingleKey::parse(v)
+                            .ok_or_else(|| anyhow!("Invalid switch apps close key"))?,
+                    )
+                };
+            }
+            if let Some(v) = section.get("minimize_key") {
+                conf.switch_apps_minimize_key = if v.trim().is_empty() {
+                    None
+                } else {
+                    Some(
+                        SingleKey::parse(v)
+                            .ok_or_else(|| anyhow!("Invalid switch apps minimize key"))?,
+                    )
+                };
+            }
+        }
+        if let Some(section) = ini_conf.section(Some("overlay")) {
+            if let Some(v) = section.get("show_details").and_then(Config::to_bool) {
+                conf.overlay_show_details = v;
+            }
+            if let Some(v) = section.get("show_titles").and_then(Config::to_bool) {
+                conf.overlay_show_titles = v;
+            }
+            if let Some(v) = section.get("thumbnails").and_then(Config::to_bool) {
+                conf.overlay_thumbnails = v;
+            }
+            if let Some(v) = section.get("renderer").and_then(Renderer::parse) {
+                conf.overlay_renderer = v;
+            }
+            if let Some(v) = section
+                .get("max_rows")
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                && v > 0
+            {
+                conf.overlay_max_rows = v;
+            }
+            if let Some(v) = section
+                .get("max_items")
+                .and_then(|v| v.trim().parse::<usize>().ok())
+            {
+                conf.overlay_max_items = v;
+            }
+            if let Some(v) = section.get("wrap").and_then(OverlayWrap::parse) {
+                conf.overlay_wrap = v;
+            }
+            if let Some(v) = section.get("monitor").and_then(OverlayMonitor::parse) {
+                conf.overlay_monitor = v;
+            }
+            if let Some(v) = section
+                .get("close_on_outside_click")
+                .and_then(Config::to_bool)
+            {
+                conf.overlay_close_on_outside_click = v;
+            }
+            if let Some(v) = section.get("animations").and_then(Config::to_bool) {
+                conf.overlay_animations = v;
+            }
+            if let Some(v) = section.get("quick_select").and_then(Config::to_bool) {
+                conf.overlay_quick_select = v;
+            }
+            if let Some(v) = section.get("window_count").and_then(Config::to_bool) {
+                conf.overlay_window_count = v;
+            }
+            if let Some(v) = section.get("minimized_badge").and_then(Config::to_bool) {
+                conf.overlay_minimized_badge = v;
+            }
+            if let Some(v) = section.get("desktop_badge").and_then(Config::to_bool) {
+                conf.overlay_desktop_badge = v;
+            }
+            if let Some(v) = section.get("dim_icons").and_then(Config::to_bool) {
+                conf.overlay_dim_icons = v;
+            }
+            if let Some(v) = section.get("audio_badge").and_then(Config::to_bool) {
+                conf.overlay_audio_badge = v;
+            }
+            if let Some(v) = section
+                .get("highlight_style")
+                .and_then(HighlightStyle::parse)
+            {
+                conf.overlay_highlight_style = v;
+            }
+            if let Some(v) = section.get("all_monitors").and_then(Config::to_bool) {
+                conf.overlay_all_monitors = v;
+            }
+            if let Some(v) = section.get("hide_from_capture").and_then(Config::to_bool) {
+                conf.overlay_hide_from_capture = v;
+            }
+            if let Some(v) = section.get("blur").and_then(Config::to_bool) {
+                conf.overlay_blur = v;
+            }
+            if let Some(v) = section.get("corner_radius") {
+                conf.overlay_corner_radius = match v.trim() {
+                    "auto" => None,
+                    v => v.parse::<i32>().ok().map(|v| v.clamp(0, 64)),
+                };
+            }
+            if let Some(v) = section
+                .get("border")
+                .and_then(|v| v.trim().parse::<i32>().ok())
+            {
+                conf.overlay_border = Some(v.clamp(0, 16));
+            }
+            if let Some(v) = section.get("preview").and_then(Config::to_bool) {
+                conf.overlay_preview = v;
+            }
+            if let Some(v) = section.get("rtl") {
+                conf.overlay_rtl = Config::to_bool(v.trim());
+            }
+            if let Some(v) = section.get("position").and_then(OverlayPosition::parse) {
+                conf.overlay_position = v;
+            }
+            if let Some(v) = section
+                .get("offset_x")
+                .and_then(|v| v.trim().parse::<i32>().ok())
+            {
+                conf.overlay_offset.0 = v;
+            }
+            if let Some(v) = section
+                .get("offset_y")
+                .and_then(|v| v.trim().parse::<i32>().ok())
+            {
+                conf.overlay_offset.1 = v;
+            }
+        }
+        if let Some(section) = ini_conf.section(Some("theme")) {
+            if let Some(v) = section.get("name").and_then(ThemePreset::parse) {
+                conf.theme.preset = v;
+            }
+            if let Some(v) = section.get("background").and_then(Theme::parse_color) {
+                conf.theme.background = Some(v);
+            }
+            if let Some(v) = section.get("highlight").and_then(Theme::parse_color) {
+                conf.theme.highlight = Some(v);
+            }
+            if let Some(v) = section.get("border").and_then(Theme::parse_color) {
+                conf.theme.border = Some(v);
+            }
+            if let Some(v) = section.get("opacity").and_then(Theme::parse_opacity) {
+                conf.theme.opacity = v;
+            }
+            if let Some(v) = section.get("accent_from_icon").and_then(Config::to_bool) {
+                conf.theme.accent_from_icon = v;
+            }
+        }
+        if let Some(section) = ini_conf.section(Some("minimize-others"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.minimize_others_hotkey = Some(Hotkey::create(
+                MINIMIZE_OTHERS_HOTKEY_ID,
+                "minimize others",
+                v,
+            )?);
+        }
+        if let Some(section) = ini_conf.section(Some("transparency"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.transparency_hotkey =
+                Some(Hotkey::create(TRANSPARENCY_HOTKEY_ID, "transparency", v)?);
+        }
+        if let Some(section) = ini_conf.section(Some("pin")) {
+            if let Some(v) = section.get("apps") {
+                conf.pin_apps = v
+                    .split(',')
+                    .map(|v| v.trim().to_lowercase())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+            }
+            if let Some(v) = section.get("hotkey")
+                && !v.trim().is_empty()
+            {
+                conf.pin_hotkey = Some(Hotkey::create(PIN_HOTKEY_ID, "pin", v)?);
+            }
+        }
+        if let Some(section) = ini_conf.section(Some("pause"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.pause_hotkey = Some(Hotkey::create(PAUSE_HOTKEY_ID, "pause", v)?);
+        }
+        if let Some(section) = ini_conf.section(Some("move-to-next-monitor"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.move_to_next_monitor_hotkey = Some(Hotkey::create(
+                MOVE_TO_NEXT_MONITOR_HOTKEY_ID,
+                "move to next monitor",
+                v,
+            )?);
+        }
+        if let Some(section) = ini_conf.section(Some("topmost"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.topmost_hotkey = Some(Hotkey::create(TOPMOST_HOTKEY_ID, "topmost", v)?);
+        }
+        if let Some(section) = ini_conf.section(Some("search"))
+            && let Some(v) = section.get("hotkey")
+            && !v.trim().is_empty()
+        {
+            conf.search_hotkey = Some(Hotkey::create(SEARCH_HOTKEY_ID, "search", v)?);
+        }
+        for (name, section) in ini_conf.iter() {
+            let Some(name) = name.and_then(|v| v.strip_prefix("action.")) else {
+                continue;
+            };
+            let name = format!("action {name}");
+            let (Some(hotkey), Some(action)) = (section.get("hotkey"), section.get("action"))
+            else {
+                continue;
+            };
+            if hotkey.trim().is_empty() {
+                continue;
+            }
+            let action = HotkeyAction::parse(action)
+                .ok_or_else(|| anyhow!("Invalid {name} action '{action}'"))?;
+            let id = ACTION_HOTKEY_ID_BASE + conf.action_hotkeys.len() as u32;
+            conf.action_hotkeys
+                .push((Hotkey::create(id, &name, hotkey)?, action));
+            if let Some(v) = section.get("blacklist") {
+                conf.set_blacklist(id, v);
+            }
+        }
+        for (name, id) in [
+            ("switch-windows", SWITCH_WINDOWS_HOTKEY_ID),
+            ("switch-all-windows", SWITCH_ALL_WINDOWS_HOTKEY_ID),
+            ("switch-apps", SWITCH_APPS_HOTKEY_ID),
+            ("minimize-others", MINIMIZE_OTHERS_HOTKEY_ID),
+            ("transparency", TRANSPARENCY_HOTKEY_ID),
+            ("pin", PIN_HOTKEY_ID),
+            ("move-to-next-monitor", MOVE_TO_NEXT_MONITOR_HOTKEY_ID),
+            ("topmost", TOPMOST_HOTKEY_ID),
+            ("search", SEARCH_HOTKEY_ID),
+        ] {
+            if let Some(v) = ini_conf
+                .section(Some(name))
+                .and_then(|v| v.get("blacklist"))
+            {
+                conf.set_blacklist(id, v);
+            }
         }
         Ok(conf)
     }
 
+    /// Sets the comma separated exes the hotkey is left to, e.g. `game1.exe,game2.exe`.
+    fn set_blacklist(&mut self, id: u32, value: &str) {
+        let blacklist: HashSet<String> = normalize_path_value(value)
+            .split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
+        if blacklist.is_empty() {
+            self.hotkey_blacklists.remove(&id);
+        } else {
+            self.hotkey_blacklists.insert(id, blacklist);
+        }
+    }
+
+    pub fn hotkey_action(&self, id: u32) -> Option<&HotkeyAction> {
+        self.action_hotkeys
+            .iter()
+            .find(|(hotkey, _)| hotkey.id == id)
+            .map(|(_, action)| action)
+    }
+
     pub fn to_hotkeys(&self) -> Vec<&Hotkey> {
         let mut hotkeys = vec![&self.switch_windows_hotkey];
+        hotkeys.extend(&self.switch_windows_alt_hotkeys);
+        if let Some(hotkey) = &self.switch_all_windows_hotkey {
+            hotkeys.push(hotkey);
+        }
         if self.switch_apps_enable {
             hotkeys.push(&self.switch_apps_hotkey);
+            hotkeys.extend(&self.switch_apps_alt_hotkeys);
         }
+        if let Some(hotkey) = &self.minimize_others_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.transparency_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.pin_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.pause_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.move_to_next_monitor_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.topmost_hotkey {
+            hotkeys.push(hotkey);
+        }
+        if let Some(hotkey) = &self.search_hotkey {
+            hotkeys.push(hotkey);
+        }
+        hotkeys.extend(self.action_hotkeys.iter().map(|(hotkey, _)| hotkey));
         hotkeys
     }
 
+    /// Identifies this instance, so instances using different config files can run side by side.
+    ///
+    /// Returns `None` for the default instance, which keeps the legacy object names. The id of
+    /// another config file is a hash of its path, which stays the same across builds.
+    pub fn instance_id(&self) -> Option<String> {
+        if let Some(name) = &self.instance_name {
+            return Some(name.replace('\\', "_"));
+        }
+        let path = canonical_path_key(CONFIG_PATH.get()?);
+        // Passing the default config file is the same as passing none
+        if default_config_path().is_ok_and(|v| canonical_path_key(&v) == path) {
+            return None;
+        }
+        Some(format!("{:016x}", fnv1a_hash(path.as_bytes())))
+    }
+
     pub fn to_bool(v: &str) -> Option<bool> {
         match v {
             "yes" | "true" | "on" | "1" => Some(true),
@@ -172,6 +812,26 @@ impl Config {
             .unwrap_or_else(Self::system_switcher_only_current_desktop)
     }
 
+    /// Whether the overlay is laid out right to left. If the configured value is not a
+    /// valid bool, the reading direction of the user's locale is used.
+    pub fn overlay_rtl(&self) -> bool {
+        self.overlay_rtl.unwrap_or_else(Self::system_rtl)
+    }
+
+    /// Whether the user's locale reads right to left, e.g. Arabic or Hebrew.
+    fn system_rtl() -> bool {
+        // The number is returned in the buffer instead of text, as two u16s
+        let mut value = [0u16; 2];
+        let len = unsafe {
+            GetLocaleInfoEx(
+                PCWSTR::null(),
+                LOCALE_IREADINGLAYOUT | LOCALE_RETURN_NUMBER,
+                Some(&mut value),
+            )
+        };
+        len > 0 && value[0] == 1
+    }
+
     fn system_switcher_only_current_desktop() -> bool {
         let alt_tab_filter = RegKey::new_hkcu(
             w!(r"Software\Microsoft\Windows\CurrentVersion\Explorer\Advanced"),
@@ -184,49 +844,479 @@ impl Config {
     }
 }
 
+/// How apps are ordered in the switch-apps overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AppsSort {
+    /// Most recently used first.
+    #[default]
+    Mru,
+    /// The order arranged by dragging icons in the overlay.
+    Custom,
+    /// Most switched to lately first, by the counts of the usage statistics.
+    Frecency,
+}
+
+impl AppsSort {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "mru" => Some(Self::Mru),
+            "custom" => Some(Self::Custom),
+            "frecency" => Some(Self::Frecency),
+            _ => None,
+        }
+    }
+}
+
+/// What an `[action.<name>]` hotkey does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Runs the command line, e.g. `run "wt.exe -p Ubuntu"`.
+    Run(String),
+    /// Switches to the most recently used window of the exe, or starts it if it has none,
+    /// e.g. `focus "C:\Program Files\WindowsTerminal\wt.exe"`.
+    Focus(String),
+}
+
+impl HotkeyAction {
+    pub fn parse(v: &str) -> Option<Self> {
+        let (kind, arg) = v.trim().split_once(' ')?;
+        let arg = arg.trim();
+        // Quotes around the whole command line are optional
+        let arg = arg
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(arg);
+        match kind {
+            "run" if !arg.is_empty() => Some(Self::Run(arg.to_string())),
+            "focus" if !arg.is_empty() => Some(Self::Focus(arg.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// How apps flow through the rows and columns of the switch-apps overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlayWrap {
+    /// Left to right, starting a new row when the monitor is full.
+    #[default]
+    Horizontal,
+    /// Top to bottom, starting a new column when `max_rows` are full.
+    Vertical,
+}
+
+impl OverlayWrap {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "horizontal" => Some(Self::Horizontal),
+            "vertical" => Some(Self::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// How the hotkeys are caught.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HotkeyMode {
+    /// The keyboard hook, falling back to registered hotkeys when it cannot be installed.
+    #[default]
+    Auto,
+    /// Only the keyboard hook.
+    Hook,
+    /// Only registered hotkeys, which just switch: the keys pressed while switching and
+    /// the reverse keys are left out.
+    Register,
+}
+
+impl HotkeyMode {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "auto" | "" => Some(Self::Auto),
+            "hook" => Some(Self::Hook),
+            "register" => Some(Self::Register),
+            _ => None,
+        }
+    }
+}
+
+/// Mouse input switching apps like the switch apps hotkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MouseTrigger {
+    #[default]
+    None,
+    /// Hold the back side button and scroll to move on, release it to switch.
+    Back,
+    /// Hold the forward side button and scroll to move on, release it to switch.
+    Forward,
+    /// Tilt the wheel to step through the apps, the app is switched to once tilting stops.
+    Tilt,
+}
+
+impl MouseTrigger {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "none" | "" => Some(Self::None),
+            "back" => Some(Self::Back),
+            "forward" => Some(Self::Forward),
+            "tilt" => Some(Self::Tilt),
+            _ => None,
+        }
+    }
+}
+
+/// How the selected app is marked in the switch-apps overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HighlightStyle {
+    /// A box in the highlight color behind the icon.
+    #[default]
+    Fill,
+    /// A bar below the icon.
+    Underline,
+    /// A thick frame around the icon.
+    Outline,
+    /// The icon drawn larger than the others, without a box.
+    Scale,
+}
+
+impl HighlightStyle {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "fill" => Some(Self::Fill),
+            "underline" => Some(Self::Underline),
+            "outline" => Some(Self::Outline),
+            "scale" => Some(Self::Scale),
+            _ => None,
+        }
+    }
+}
+
+/// Where the switch-apps overlay is placed on its monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlayPosition {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+}
+
+impl OverlayPosition {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "center" => Some(Self::Center),
+            "top" => Some(Self::Top),
+            "bottom" => Some(Self::Bottom),
+            _ => None,
+        }
+    }
+}
+
+/// Monitor the switch-apps overlay is centered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlayMonitor {
+    Primary,
+    /// The monitor with the mouse cursor.
+    #[default]
+    Cursor,
+    /// The monitor with the foreground window.
+    Focused,
+}
+
+impl OverlayMonitor {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "primary" => Some(Self::Primary),
+            "cursor" => Some(Self::Cursor),
+            "focused" => Some(Self::Focused),
+            _ => None,
+        }
+    }
+}
+
+/// Graphics API that draws the switch-apps overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Renderer {
+    /// GDI+ with supersampled icons.
+    #[default]
+    Gdi,
+    /// Direct2D and DirectWrite.
+    D2d,
+}
+
+impl Renderer {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim().trim_matches('"') {
+            "gdi" => Some(Self::Gdi),
+            "d2d" => Some(Self::D2d),
+            _ => None,
+        }
+    }
+}
+
+/// Colors of the switch-apps overlay, a preset with optional overrides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub preset: ThemePreset,
+    /// Colors as `0xRRGGBB`, the preset color is used when not set.
+    pub background: Option<u32>,
+    pub highlight: Option<u32>,
+    /// The overlay has no border when not set.
+    pub border: Option<u32>,
+    /// Alpha of the whole overlay, 255 is opaque.
+    pub opacity: u8,
+    /// Tint the highlight with the dominant color of the selected app's icon.
+    pub accent_from_icon: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            preset: ThemePreset::Auto,
+            background: None,
+            highlight: None,
+            border: None,
+            opacity: 255,
+            accent_from_icon: false,
+        }
+    }
+}
+
+impl Theme {
+    /// Parses `#RRGGBB`, the `#` is optional.
+    pub fn parse_color(v: &str) -> Option<u32> {
+        let v = v.trim();
+        let v = v.strip_prefix('#').unwrap_or(v);
+        if v.len() != 6 {
+            return None;
+        }
+        u32::from_str_radix(v, 16).ok()
+    }
+
+    /// Parses a percentage from 0 to 100 into an alpha value.
+    pub fn parse_opacity(v: &str) -> Option<u8> {
+        let v = v.trim().trim_end_matches('%').parse::<u32>().ok()?;
+        (v <= 100).then(|| (v * 255 / 100) as u8)
+    }
+}
+
+/// Built-in color sets of the overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemePreset {
+    /// Follows the light or dark mode of the system.
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl ThemePreset {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.trim() {
+            "auto" => Some(Self::Auto),
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+}
+
+/// A key pressed without modifiers, a letter is matched by the keyboard layout like the
+/// letters of the hotkeys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SingleKey {
+    /// Scan code of the key on a US keyboard.
+    pub code: u32,
+    pub letter: Option<char>,
+}
+
+impl SingleKey {
+    pub fn parse(value: &str) -> Option<Self> {
+        let key = value.trim().to_ascii_lowercase();
+        Some(Self {
+            code: Hotkey::parse_single_key(&key)?,
+            letter: Hotkey::key_letter(&key),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Hotkey {
     pub id: u32,
     pub name: String,
-    pub modifier: [u32; 2],
+    /// Scan codes of the left and right key of each modifier, all of them are held.
+    pub modifiers: Vec<[u32; 2]>,
+    /// Scan code of the key on a US keyboard.
     pub code: u32,
+    /// The letter of the key, which follows the active keyboard layout.
+    pub letter: Option<char>,
+    /// Scan code of a key switching backwards while the modifiers are held, besides shift.
+    pub reverse_code: Option<u32>,
+    pub reverse_letter: Option<char>,
+    /// The character of a key other than a letter on a US keyboard, e.g. '`' for `oem_3`.
+    pub symbol: Option<char>,
+    pub reverse_symbol: Option<char>,
 }
 
 impl Hotkey {
     pub fn create(id: u32, name: &str, value: &str) -> Result<Self> {
-        let (modifier, code) =
+        let (modifiers, code) =
             Self::parse(value).ok_or_else(|| anyhow!("Invalid {name} hotkey"))?;
         Ok(Self {
             id,
             name: name.to_string(),
-            modifier,
+            modifiers,
             code,
+            letter: Self::parse_letter(value),
+            reverse_code: None,
+            reverse_letter: None,
+            symbol: Self::parse_symbol(value),
+            reverse_symbol: None,
         })
     }
 
-    pub fn get_modifier(&self) -> u32 {
-        self.modifier[0]
+    /// Sets the key switching backwards, e.g. `1` for `alt+1`.
+    pub fn set_reverse_key(&mut self, key: &str) -> Result<()> {
+        let key = key.to_ascii_lowercase().replace(' ', "").replace("vk_", "");
+        let code =
+            Self::parse_key(&key).ok_or_else(|| anyhow!("Invalid {} reverse key", self.name))?;
+        self.reverse_code = Some(code);
+        self.reverse_letter = Self::key_letter(&key);
+        self.reverse_symbol = Self::key_symbol(&key);
+        Ok(())
+    }
+
+    /// Creates the hotkeys of a comma separated list, e.g. `alt+tab, alt+q`.
+    pub fn create_list(id: u32, name: &str, value: &str) -> Result<Vec<Self>> {
+        Self::split_list(value)
+            .into_iter()
+            .filter(|v| !v.is_empty())
+            .map(|v| Self::create(id, name, v))
+            .collect()
+    }
+
+    /// Splits a list of hotkeys at the commas, a comma right after `+` is the key itself.
+    fn split_list(value: &str) -> Vec<&str> {
+        let mut list = vec![];
+        let mut start = 0;
+        let mut after_plus = false;
+        for (i, c) in value.char_indices() {
+            if c == ' ' {
+                continue;
+            }
+            if c == ',' && !after_plus {
+                list.push(value[start..i].trim());
+                start = i + 1;
+            }
+            after_plus = c == '+';
+        }
+        list.push(value[start..].trim());
+        list
+    }
+
+    /// Returns the key if it is a letter, e.g. `q` for `alt+q`.
+    pub fn parse_letter(value: &str) -> Option<char> {
+        let value = value.to_ascii_lowercase().replace(' ', "");
+        let (_, key) = value.rsplit_once('+')?;
+        Self::key_letter(key)
     }
 
-    pub fn parse(value: &str) -> Option<([u32; 2], u32)> {
+    fn key_letter(key: &str) -> Option<char> {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the character of the key if it is not a letter, e.g. '`' for `alt+oem_3`.
+    pub fn parse_symbol(value: &str) -> Option<char> {
         let value = value
             .to_ascii_lowercase()
             .replace(' ', "")
             .replace("vk_", "");
-        let keys: Vec<&str> = value.split('+').collect();
-        if keys.len() != 2 {
-            return None;
-        }
-        let modifier = match keys[0] {
-            "win" => [0x5b, 0x5c],
-            "alt" => [0x38, 0x38],
-            "ctrl" => [0x1d, 0x1d],
+        let (_, key) = value.rsplit_once('+')?;
+        Self::key_symbol(key)
+    }
+
+    fn key_symbol(key: &str) -> Option<char> {
+        let symbol = match key {
+            "oem_1" => ';',
+            "oem_2" => '/',
+            "oem_3" => '`',
+            "oem_4" => '[',
+            "oem_5" => '\\',
+            "oem_6" => ']',
+            "oem_7" => '\'',
+            "oem_minus" => '-',
+            "oem_plus" => '=',
+            "oem_comma" => ',',
+            "oem_period" => '.',
             _ => {
-                return None;
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_ascii_alphabetic() => c,
+                    _ => return None,
+                }
             }
         };
+        Some(symbol)
+    }
+
+    /// Whether ctrl is one of the modifiers, it is not a hint to launch a new instance then.
+    pub fn has_ctrl(&self) -> bool {
+        self.modifiers.contains(&[0x1d, 0x1d])
+    }
+
+    pub fn has_win(&self) -> bool {
+        self.modifiers.contains(&[0x5b, 0x5c])
+    }
+
+    /// Parses one or more of `win`, `ctrl` and `alt` followed by a key, e.g. `ctrl+alt+j`.
+    ///
+    /// Shift is left out, it reverses the direction of switching.
+    pub fn parse(value: &str) -> Option<(Vec<[u32; 2]>, u32)> {
+        let value = value
+            .to_ascii_lowercase()
+            .replace(' ', "")
+            .replace("vk_", "");
+        let (modifier_keys, key) = value.rsplit_once('+')?;
+        let mut modifiers = vec![];
+        for modifier_key in modifier_keys.split('+') {
+            let modifier = match modifier_key {
+                "win" => [0x5b, 0x5c],
+                "alt" => [0x38, 0x38],
+                "ctrl" => [0x1d, 0x1d],
+                _ => {
+                    return None;
+                }
+            };
+            if modifiers.contains(&modifier) {
+                return None;
+            }
+            modifiers.push(modifier);
+        }
+        Some((modifiers, Self::parse_key(key)?))
+    }
+
+    /// Scan code of a key pressed without modifiers, e.g. `delete`.
+    pub fn parse_single_key(key: &str) -> Option<u32> {
+        let key = key.to_ascii_lowercase().replace(' ', "").replace("vk_", "");
+        Self::parse_key(&key)
+    }
+
+    /// Scan codes of comma separated keys pressed without modifiers, e.g. `esc, backspace`.
+    /// The comma key itself is written `oem_comma`.
+    pub fn parse_key_list(value: &str) -> Option<Vec<u32>> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(Self::parse_single_key)
+            .collect()
+    }
+
+    /// Scan code of a key on a US keyboard by its name, e.g. `tab` or `oem_3`.
+    fn parse_key(key: &str) -> Option<u32> {
         // see <https://kbdlayout.info/kbdus/overview+scancodes>
-        let code = match keys[1] {
+        let code = match key {
             "esc" | "escape" => 0x01,
             "1" | "!" => 0x02,
             "2" | "@" => 0x03,
@@ -307,9 +1397,40 @@ impl Hotkey {
             "f11" => 0x57,
             "f12" => 0x58,
             "menu" => 0x5d,
+            "f13" => 0x64,
+            "f14" => 0x65,
+            "f15" => 0x66,
+            "f16" => 0x67,
+            "f17" => 0x68,
+            "f18" => 0x69,
+            "f19" => 0x6a,
+            "f20" => 0x6b,
+            "f21" => 0x6c,
+            "f22" => 0x6d,
+            "f23" => 0x6e,
+            "f24" => 0x76,
+            // Extended keys, sent with an E0 prefix
+            "media_prev_track" => 0xe010,
+            "media_next_track" => 0xe019,
+            "volume_mute" => 0xe020,
+            "launch_app2" => 0xe021,
+            "media_play_pause" => 0xe022,
+            "media_stop" => 0xe024,
+            "volume_down" => 0xe02e,
+            "volume_up" => 0xe030,
+            "browser_home" => 0xe032,
+            "browser_search" => 0xe065,
+            "browser_favorites" => 0xe066,
+            "browser_refresh" => 0xe067,
+            "browser_stop" => 0xe068,
+            "browser_forward" => 0xe069,
+            "browser_back" => 0xe06a,
+            "launch_app1" => 0xe06b,
+            "launch_mail" => 0xe06c,
+            "launch_media_select" => 0xe06d,
             _ => return None,
         };
-        Some((modifier, code))
+        Some(code)
     }
 }
 
@@ -350,7 +1471,20 @@ pub(crate) fn edit_config_file() -> Result<bool> {
     Ok(exit.success())
 }
 
-fn get_config_path() -> Result<PathBuf> {
+/// Uses the given config file instead of looking it up in the default locations.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+pub(crate) fn get_config_path() -> Result<PathBuf> {
+    if let Some(path) = CONFIG_PATH.get() {
+        return Ok(path.clone());
+    }
+    default_config_path()
+}
+
+/// The config file used when none is given.
+fn default_config_path() -> Result<PathBuf> {
     // Try config folder first (user-specific location)
     if let Ok(config_dir) = get_config_folder() {
         let config_path = config_dir.join("window-switcher.ini");
@@ -399,6 +1533,21 @@ fn get_config_folder() -> Result<PathBuf> {
     Ok(PathBuf::from(base_dir).join("WindowSwitcher"))
 }
 
+/// The path with `.` and `..` resolved and in lowercase, as Windows paths are case-insensitive.
+fn canonical_path_key(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+/// 64-bit FNV-1a, unlike the std hashers it is the same for every Rust release.
+fn fnv1a_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, v| {
+        (hash ^ *v as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
 fn normalize_path_value(value: &str) -> String {
     value.replace("\\\\", "\\")
 }
@@ -409,7 +1558,119 @@ mod tests {
 
     #[test]
     fn test_hotkey() {
-        assert_eq!(Hotkey::parse("alt + `"), Some(([0x38, 0x38], 0x29)));
-        assert_eq!(Hotkey::parse("alt + tab"), Some(([0x38, 0x38], 0x0f)));
+        assert_eq!(Hotkey::parse("alt + `"), Some((vec![[0x38, 0x38]], 0x29)));
+        assert_eq!(Hotkey::parse("alt + tab"), Some((vec![[0x38, 0x38]], 0x0f)));
+        assert_eq!(Hotkey::parse("win+oem_3"), Some((vec![[0x5b, 0x5c]], 0x29)));
+        assert_eq!(
+            Hotkey::parse("ctrl+alt+j"),
+            Some((vec![[0x1d, 0x1d], [0x38, 0x38]], 0x24))
+        );
+        assert_eq!(Hotkey::parse("ctrl+f13"), Some((vec![[0x1d, 0x1d]], 0x64)));
+        assert_eq!(
+            Hotkey::parse("win+media_next_track"),
+            Some((vec![[0x5b, 0x5c]], 0xe019))
+        );
+        assert_eq!(Hotkey::parse("alt+alt+j"), None);
+        assert_eq!(Hotkey::parse("shift+j"), None);
+        assert_eq!(Hotkey::parse("j"), None);
+        let mut hotkey =
+            Hotkey::create(SWITCH_WINDOWS_HOTKEY_ID, "switch windows", "alt+`").unwrap();
+        hotkey.set_reverse_key("1").unwrap();
+        assert_eq!(hotkey.reverse_code, Some(0x02));
+        assert!(hotkey.set_reverse_key("alt+1").is_err());
+    }
+
+    #[test]
+    fn test_hotkey_list() {
+        assert_eq!(Hotkey::split_list("alt+tab, alt+q"), ["alt+tab", "alt+q"]);
+        assert_eq!(
+            Hotkey::split_list("alt + ,,ctrl+alt+j"),
+            ["alt + ,", "ctrl+alt+j"]
+        );
+        assert_eq!(Hotkey::split_list("alt+tab"), ["alt+tab"]);
+        let hotkeys =
+            Hotkey::create_list(SWITCH_APPS_HOTKEY_ID, "switch apps", "alt+tab,").unwrap();
+        assert_eq!(hotkeys.len(), 1);
+        assert!(Hotkey::create_list(SWITCH_APPS_HOTKEY_ID, "switch apps", "alt+tab, q").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_list() {
+        assert_eq!(Hotkey::parse_key_list("esc"), Some(vec![0x01]));
+        assert_eq!(
+            Hotkey::parse_key_list("escape, backspace"),
+            Some(vec![0x01, 0x0e])
+        );
+        assert_eq!(Hotkey::parse_key_list(""), Some(vec![]));
+        assert_eq!(Hotkey::parse_key_list("esc, alt+q"), None);
+    }
+
+    #[test]
+    fn test_hotkey_action() {
+        assert_eq!(
+            HotkeyAction::parse(r#"run "wt.exe -p Ubuntu""#),
+            Some(HotkeyAction::Run("wt.exe -p Ubuntu".into()))
+        );
+        assert_eq!(
+            HotkeyAction::parse("run notepad.exe"),
+            Some(HotkeyAction::Run("notepad.exe".into()))
+        );
+        assert_eq!(
+            HotkeyAction::parse(r#"focus "C:\Windows\notepad.exe""#),
+            Some(HotkeyAction::Focus(r"C:\Windows\notepad.exe".into()))
+        );
+        assert_eq!(HotkeyAction::parse("run"), None);
+        assert_eq!(HotkeyAction::parse("open notepad.exe"), None);
+    }
+
+    #[test]
+    fn test_theme() {
+        assert_eq!(Theme::parse_color("#1f2a3b"), Some(0x1f2a3b));
+        assert_eq!(Theme::parse_color("FFFFFF"), Some(0xffffff));
+        assert_eq!(Theme::parse_color("#fff"), None);
+        assert_eq!(Theme::parse_color("#gggggg"), None);
+        assert_eq!(Theme::parse_opacity("100"), Some(255));
+        assert_eq!(Theme::parse_opacity("0%"), Some(0));
+        assert_eq!(Theme::parse_opacity("101"), None);
+        assert_eq!(ThemePreset::parse("dark"), Some(ThemePreset::Dark));
+        assert_eq!(ThemePreset::parse("blue"), None);
+    }
+
+    #[test]
+    fn test_hotkey_letter() {
+        assert_eq!(Hotkey::parse_letter("alt + Q"), Some('q'));
+        assert_eq!(Hotkey::parse_letter("ctrl+alt+j"), Some('j'));
+        assert_eq!(Hotkey::parse_symbol("alt + `"), Some('`'));
+        assert_eq!(Hotkey::parse_symbol("win+oem_3"), Some('`'));
+        assert_eq!(Hotkey::parse_symbol("alt+tab"), None);
+        assert_eq!(Hotkey::parse_symbol("alt+q"), None);
+        assert_eq!(Hotkey::parse_letter("alt + `"), None);
+        assert_eq!(Hotkey::parse_letter("alt + tab"), None);
+    }
+
+    #[test]
+    fn test_single_key() {
+        assert_eq!(
+            SingleKey::parse(" M "),
+            Some(SingleKey {
+                code: 0x32,
+                letter: Some('m'),
+            })
+        );
+        assert_eq!(
+            SingleKey::parse("delete"),
+            Some(SingleKey {
+                code: 0x53,
+                letter: None,
+            })
+        );
+        assert_eq!(SingleKey::parse("alt+m"), None);
+    }
+
+    #[test]
+    fn test_fnv1a_hash() {
+        assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
     }
 }
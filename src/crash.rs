@@ -0,0 +1,75 @@
+use crate::app::WM_USER_TRAYICON;
+
+use std::sync::atomic::{AtomicIsize, Ordering};
+use windows::Win32::{
+    Foundation::HWND,
+    System::Diagnostics::Debug::{
+        EXCEPTION_CONTINUE_SEARCH, EXCEPTION_POINTERS, SetUnhandledExceptionFilter,
+    },
+    UI::{
+        Shell::{NIM_DELETE, NOTIFYICONDATAW, Shell_NotifyIconW},
+        WindowsAndMessaging::{HHOOK, UnhookWindowsHookEx},
+    },
+};
+
+/// Handles released when the process dies on a crash, 0 when not in use.
+static KEYBOARD_HOOK: AtomicIsize = AtomicIsize::new(0);
+static MOUSE_HOOK: AtomicIsize = AtomicIsize::new(0);
+static MOUSE_TRIGGER_HOOK: AtomicIsize = AtomicIsize::new(0);
+/// Window owning the tray icon.
+static TRAYICON_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// Releases the global hooks and the tray icon on panics and unhandled exceptions.
+///
+/// Otherwise a dead low-level hook slows down every key press until Windows drops it,
+/// and the tray icon stays in the taskbar until the mouse moves over it.
+pub fn install_crash_handlers() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        error!("{info}");
+        cleanup();
+        default_hook(info);
+    }));
+    unsafe { SetUnhandledExceptionFilter(Some(unhandled_exception_filter)) };
+}
+
+pub fn set_keyboard_hook(hook: HHOOK) {
+    KEYBOARD_HOOK.store(hook.0 as isize, Ordering::SeqCst);
+}
+
+pub fn set_mouse_hook(hook: HHOOK) {
+    MOUSE_HOOK.store(hook.0 as isize, Ordering::SeqCst);
+}
+
+pub fn set_mouse_trigger_hook(hook: HHOOK) {
+    MOUSE_TRIGGER_HOOK.store(hook.0 as isize, Ordering::SeqCst);
+}
+
+pub fn set_trayicon_window(hwnd: HWND) {
+    TRAYICON_WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+}
+
+unsafe extern "system" fn unhandled_exception_filter(_info: *const EXCEPTION_POINTERS) -> i32 {
+    cleanup();
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+fn cleanup() {
+    for hook in [&KEYBOARD_HOOK, &MOUSE_HOOK, &MOUSE_TRIGGER_HOOK] {
+        let hook = hook.swap(0, Ordering::SeqCst);
+        if hook != 0 {
+            let _ = unsafe { UnhookWindowsHookEx(HHOOK(hook as _)) };
+        }
+    }
+    let hwnd = TRAYICON_WINDOW.swap(0, Ordering::SeqCst);
+    if hwnd != 0 {
+        let data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: HWND(hwnd as _),
+            uID: WM_USER_TRAYICON,
+            ..Default::default()
+        };
+        let _ = unsafe { Shell_NotifyIconW(NIM_DELETE, &data) };
+    }
+    log::logger().flush();
+}
@@ -1,21 +1,42 @@
-use crate::utils::get_window_exe;
+use crate::utils::{
+    get_foreground_window, get_window_exe, get_window_title, is_exclusive_full_screen,
+    is_full_screen_window, is_window, run_command,
+};
 use anyhow::{bail, Result};
-use once_cell::sync::OnceCell;
-use std::collections::HashSet;
-use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
 use windows::Win32::{
     Foundation::HWND,
     UI::{
         Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
         WindowsAndMessaging::{
-            EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
+            KillTimer, SetTimer, EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT,
+            WINEVENT_SKIPOWNPROCESS,
         },
     },
 };
 
-pub static IS_FOREGROUND_IN_BLACKLIST: AtomicBool = AtomicBool::new(false);
+/// Whether the foreground window runs full screen, only tracked with pause_in_full_screen on.
+pub static IS_FOREGROUND_FULL_SCREEN: AtomicBool = AtomicBool::new(false);
+
+static IS_PAUSE_IN_FULL_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Exes each hotkey is left to while they are in the foreground, by hotkey id.
+static BLACKLISTS: LazyLock<Mutex<HashMap<u32, HashSet<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// Ids of the hotkeys left to the foreground window.
+static BLACKLISTED_HOTKEYS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Command line run when the foreground window changes, see `on_foreground_change`.
+static ON_FOREGROUND_CHANGE: Mutex<Option<String>> = Mutex::new(None);
+/// Window which became foreground last, waiting for the debounce timer.
+static PENDING_FOREGROUND: AtomicIsize = AtomicIsize::new(0);
+static DEBOUNCE_TIMER: AtomicUsize = AtomicUsize::new(0);
 
-static BLACKLIST: OnceCell<HashSet<String>> = OnceCell::new();
+/// Quick switches through several windows only run the command for the last one.
+const DEBOUNCE_DELAY: u32 = 300;
 
 #[derive(Debug)]
 pub struct ForegroundWatcher {
@@ -23,34 +44,80 @@ pub struct ForegroundWatcher {
 }
 
 impl ForegroundWatcher {
-    pub fn init(blacklist: &HashSet<String>) -> Result<Self> {
-        if blacklist.is_empty() {
+    pub fn init(
+        blacklists: &HashMap<u32, HashSet<String>>,
+        on_foreground_change: Option<&str>,
+        pause_in_full_screen: bool,
+    ) -> Result<Self> {
+        IS_PAUSE_IN_FULL_SCREEN.store(pause_in_full_screen, Ordering::SeqCst);
+        *BLACKLISTS.lock() = blacklists.clone();
+        *ON_FOREGROUND_CHANGE.lock() = on_foreground_change.map(|v| v.to_string());
+        if blacklists.is_empty() && on_foreground_change.is_none() && !pause_in_full_screen {
             return Ok(Self {
                 hook: HWINEVENTHOOK::default(),
             });
         }
 
-        let _ = BLACKLIST.set(blacklist.iter().map(|v| v.to_lowercase()).collect());
-
-        let hook = unsafe {
-            SetWinEventHook(
-                EVENT_SYSTEM_FOREGROUND,
-                EVENT_SYSTEM_FOREGROUND,
-                None,
-                Some(win_event_proc),
-                0,
-                0,
-                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
-            )
-        };
-        if hook.is_invalid() {
-            bail!("Failed to watch foreground");
-        }
+        let hook = install_hook()?;
 
         info!("foreground watcher start");
 
         Ok(Self { hook })
     }
+
+    /// Reinstalls the hook and refreshes the blacklist state of the current foreground window.
+    pub fn reinstall(&mut self) -> Result<()> {
+        if self.hook.is_invalid() {
+            return Ok(());
+        }
+        unsafe {
+            let _ = UnhookWinEvent(self.hook);
+        }
+        self.hook = HWINEVENTHOOK::default();
+        self.hook = install_hook()?;
+        update_foreground(get_foreground_window());
+        info!("foreground watcher reinstalled");
+        Ok(())
+    }
+
+    /// Takes the blacklists, the foreground command and the tracking of full screen apps of a
+    /// reloaded config, installing the hook when needed.
+    pub fn update(
+        &mut self,
+        blacklists: &HashMap<u32, HashSet<String>>,
+        on_foreground_change: Option<&str>,
+        pause_in_full_screen: bool,
+    ) -> Result<()> {
+        IS_PAUSE_IN_FULL_SCREEN.store(pause_in_full_screen, Ordering::SeqCst);
+        *BLACKLISTS.lock() = blacklists.clone();
+        *ON_FOREGROUND_CHANGE.lock() = on_foreground_change.map(|v| v.to_string());
+        let needs_hook =
+            !blacklists.is_empty() || on_foreground_change.is_some() || pause_in_full_screen;
+        if needs_hook && self.hook.is_invalid() {
+            self.hook = install_hook()?;
+            info!("foreground watcher start");
+        }
+        update_foreground(get_foreground_window());
+        Ok(())
+    }
+}
+
+fn install_hook() -> Result<HWINEVENTHOOK> {
+    let hook = unsafe {
+        SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            None,
+            Some(win_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        )
+    };
+    if hook.is_invalid() {
+        bail!("Failed to watch foreground");
+    }
+    Ok(hook)
 }
 
 impl Drop for ForegroundWatcher {
@@ -61,6 +128,12 @@ impl Drop for ForegroundWatcher {
                 let _ = UnhookWinEvent(self.hook);
             }
         }
+        let timer = DEBOUNCE_TIMER.swap(0, Ordering::SeqCst);
+        if timer != 0 {
+            unsafe {
+                let _ = KillTimer(None, timer);
+            }
+        }
     }
 }
 
@@ -73,15 +146,170 @@ unsafe extern "system" fn win_event_proc(
     _dw_event_thread: u32,
     _dwms_event_time: u32,
 ) {
+    update_foreground(hwnd);
+    schedule_foreground_command(hwnd);
+}
+
+fn update_foreground(hwnd: HWND) {
+    let is_full_screen = update_full_screen(hwnd);
     let exe = match get_window_exe(hwnd) {
         Some(v) => v.to_lowercase(),
         None => return,
     };
-    // SAFETY: BLACKLIST is initialized once before the hook is set and never mutated after
-    let is_in_blacklist = BLACKLIST
-        .get()
-        .map(|bl| bl.contains(&exe))
-        .unwrap_or(false);
-    IS_FOREGROUND_IN_BLACKLIST.store(is_in_blacklist, Ordering::SeqCst);
-    debug!("foreground {exe} {is_in_blacklist}");
+    let blacklisted: Vec<u32> = BLACKLISTS
+        .lock()
+        .iter()
+        .filter(|(_, blacklist)| blacklist.contains(&exe))
+        .map(|(id, _)| *id)
+        .collect();
+    debug!("foreground {exe} {blacklisted:?} {is_full_screen}");
+    *BLACKLISTED_HOTKEYS.lock() = blacklisted;
+}
+
+fn update_full_screen(hwnd: HWND) -> bool {
+    let is_full_screen = IS_PAUSE_IN_FULL_SCREEN.load(Ordering::SeqCst)
+        && (is_exclusive_full_screen() || is_full_screen_window(hwnd));
+    IS_FOREGROUND_FULL_SCREEN.store(is_full_screen, Ordering::SeqCst);
+    is_full_screen
+}
+
+/// Checks again whether the foreground window runs full screen, it may have left full screen
+/// without losing the focus, e.g. a game switched to windowed mode.
+pub fn refresh_foreground_full_screen() -> bool {
+    update_full_screen(get_foreground_window())
+}
+
+/// Whether the hotkey is left to the foreground window by its blacklist.
+pub fn is_hotkey_blacklisted(id: u32) -> bool {
+    BLACKLISTED_HOTKEYS.lock().contains(&id)
+}
+
+fn schedule_foreground_command(hwnd: HWND) {
+    if ON_FOREGROUND_CHANGE.lock().is_none() {
+        return;
+    }
+    PENDING_FOREGROUND.store(hwnd.0 as isize, Ordering::SeqCst);
+    // Timers without a window get a new id on every call, so the previous one is killed first
+    let timer = DEBOUNCE_TIMER.swap(0, Ordering::SeqCst);
+    unsafe {
+        if timer != 0 {
+            let _ = KillTimer(None, timer);
+        }
+        let timer = SetTimer(None, 0, DEBOUNCE_DELAY, Some(debounce_timer_proc));
+        DEBOUNCE_TIMER.store(timer, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "system" fn debounce_timer_proc(_hwnd: HWND, _msg: u32, timer: usize, _time: u32) {
+    unsafe {
+        let _ = KillTimer(None, timer);
+    }
+    DEBOUNCE_TIMER.store(0, Ordering::SeqCst);
+    let hwnd = HWND(PENDING_FOREGROUND.swap(0, Ordering::SeqCst) as _);
+    let Some(command) = ON_FOREGROUND_CHANGE.lock().clone() else {
+        return;
+    };
+    if hwnd.is_invalid() || !is_window(hwnd) {
+        return;
+    }
+    let command = format_foreground_command(
+        &command,
+        &get_window_exe(hwnd).unwrap_or_default(),
+        &get_window_title(hwnd),
+    );
+    debug!("on foreground change: {command}");
+    if let Err(err) = run_command(&command) {
+        error!("{err}");
+    }
+}
+
+/// Fills the `{exe}` and `{title}` placeholders with the values quoted as single arguments.
+/// Quotes around a placeholder are taken as part of it, so they are not doubled.
+fn format_foreground_command(command: &str, exe: &str, title: &str) -> String {
+    let command = command
+        .replace("\"{exe}\"", "{exe}")
+        .replace("\"{title}\"", "{title}");
+    // A single pass, the values may contain the placeholders themselves
+    let mut output = String::new();
+    let mut rest = command.as_str();
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(v) = rest.strip_prefix("{exe}") {
+            output.push_str(&quote_argument(exe));
+            rest = v;
+        } else if let Some(v) = rest.strip_prefix("{title}") {
+            output.push_str(&quote_argument(title));
+            rest = v;
+        } else {
+            output.push('{');
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Quotes the value as `CommandLineToArgvW` reads it back. Backslashes are only special
+/// before a quote, where they are doubled, as they are before the closing quote.
+fn quote_argument(value: &str) -> String {
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_foreground_command() {
+        assert_eq!(
+            format_foreground_command("notify.exe {exe}", "Code.exe", "main.rs"),
+            r#"notify.exe "Code.exe""#
+        );
+        assert_eq!(
+            format_foreground_command(
+                r#"notify.exe "{exe}" "{title}""#,
+                "notepad.exe",
+                r#"Say "hi" - Notepad"#
+            ),
+            r#"notify.exe "notepad.exe" "Say \"hi\" - Notepad""#
+        );
+        assert_eq!(
+            format_foreground_command("notify.exe {title}", "a.exe", "{exe}"),
+            r#"notify.exe "{exe}""#
+        );
+        assert_eq!(
+            format_foreground_command("notify.exe", "a.exe", "b"),
+            "notify.exe"
+        );
+    }
+
+    #[test]
+    fn test_quote_argument() {
+        assert_eq!(quote_argument("a b"), r#""a b""#);
+        assert_eq!(quote_argument(r#"C:\dir\"#), r#""C:\dir\\""#);
+        assert_eq!(quote_argument(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_argument(r"a\\b"), r#""a\\b""#);
+        assert_eq!(quote_argument(""), r#""""#);
+    }
 }
@@ -1,10 +1,22 @@
 use crate::{
     app::{
-        WM_USER_SWITCH_APPS, WM_USER_SWITCH_APPS_CANCEL, WM_USER_SWITCH_APPS_DONE,
-        WM_USER_SWITCH_WINDOWS, WM_USER_SWITCH_WINDOWS_DONE,
+        SWITCH_APPS_ACTION_CLOSE, SWITCH_APPS_ACTION_MINIMIZE, SWITCH_APPS_DONE_ELEVATED,
+        SWITCH_APPS_DONE_MAXIMIZE, SWITCH_APPS_DONE_NEW_INSTANCE, SWITCH_APPS_DONE_SNAP_LEFT,
+        SWITCH_APPS_DONE_SNAP_RIGHT, SWITCH_APPS_REPEAT, SWITCH_APPS_SELECT_FIRST,
+        SWITCH_APPS_SELECT_LAST, SWITCH_APPS_SELECT_NEXT, SWITCH_APPS_SELECT_PREVIOUS,
+        SWITCH_WINDOWS_ALL, WM_USER_HOTKEY, WM_USER_SWITCH_APPS, WM_USER_SWITCH_APPS_ACTION,
+        WM_USER_SWITCH_APPS_CANCEL, WM_USER_SWITCH_APPS_DONE, WM_USER_SWITCH_APPS_KEY_UP,
+        WM_USER_SWITCH_APPS_LAST, WM_USER_SWITCH_APPS_PICK, WM_USER_SWITCH_APPS_SELECT,
+        WM_USER_SWITCH_WINDOWS, WM_USER_SWITCH_WINDOWS_CANCEL, WM_USER_SWITCH_WINDOWS_DONE,
+    },
+    config::{
+        Hotkey, HotkeyMode, PAUSE_HOTKEY_ID, SWITCH_ALL_WINDOWS_HOTKEY_ID, SWITCH_APPS_HOTKEY_ID,
+        SWITCH_WINDOWS_HOTKEY_ID, SingleKey,
+    },
+    crash::set_keyboard_hook,
+    foreground::{
+        IS_FOREGROUND_FULL_SCREEN, is_hotkey_blacklisted, refresh_foreground_full_screen,
     },
-    config::{Hotkey, SWITCH_APPS_HOTKEY_ID, SWITCH_WINDOWS_HOTKEY_ID},
-    foreground::IS_FOREGROUND_IN_BLACKLIST,
 };
 
 use anyhow::{Result, anyhow};
@@ -15,10 +27,18 @@ use windows::Win32::{
     Foundation::{HWND, LPARAM, LRESULT, WPARAM},
     System::LibraryLoader::GetModuleHandleW,
     UI::{
-        Input::KeyboardAndMouse::{SCANCODE_LSHIFT, SCANCODE_RSHIFT},
+        Input::KeyboardAndMouse::{
+            GetAsyncKeyState, GetKeyboardLayout, HKL, INPUT, INPUT_0, INPUT_KEYBOARD,
+            KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC,
+            MAPVK_VSC_TO_VK_EX, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+            MapVirtualKeyExW, MapVirtualKeyW, RegisterHotKey, SCANCODE_LSHIFT, SCANCODE_RSHIFT,
+            SendInput, UnregisterHotKey, VIRTUAL_KEY, VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN,
+            VkKeyScanExW,
+        },
         WindowsAndMessaging::{
-            CallNextHookEx, HHOOK, KBDLLHOOKSTRUCT, LLKHF_UP, SendMessageW, SetWindowsHookExW,
-            UnhookWindowsHookEx, WH_KEYBOARD_LL,
+            CallNextHookEx, GetForegroundWindow, GetWindowThreadProcessId, HHOOK, KBDLLHOOKSTRUCT,
+            LLKHF_EXTENDED, LLKHF_UP, SendMessageW, SetWindowsHookExW, UnhookWindowsHookEx,
+            WH_KEYBOARD_LL,
         },
     },
 };
@@ -28,42 +48,193 @@ static KEYBOARD_STATE: LazyLock<Mutex<Vec<HotKeyState>>> = LazyLock::new(|| Mute
 static WINDOW: AtomicIsize = AtomicIsize::new(0);
 /// Tracks whether shift key is currently pressed for reverse switching.
 static IS_SHIFT_PRESSED: AtomicBool = AtomicBool::new(false);
+/// Tracks whether ctrl key is currently pressed for launching a new instance.
+static IS_CTRL_PRESSED: AtomicBool = AtomicBool::new(false);
+/// Tracks whether the switch apps key is held down, to tell its auto-repeat apart.
+static IS_TRIGGER_DOWN: AtomicBool = AtomicBool::new(false);
 /// Tracks the previous keycode to handle modifier release events.
 static PREVIOUS_KEYCODE: AtomicU32 = AtomicU32::new(0);
+/// Keyboard layout the hotkey codes were resolved for, 0 forces resolving them again.
+static KEYBOARD_LAYOUT: AtomicIsize = AtomicIsize::new(0);
+/// While paused only the pause hotkey is handled, other keys pass through to Windows.
+static IS_PAUSED: AtomicBool = AtomicBool::new(false);
+/// Whether the number keys switch to the numbered apps while the overlay is shown.
+static IS_QUICK_SELECT: AtomicBool = AtomicBool::new(false);
+/// Milliseconds between two taps of the switch apps modifier switching to the last app,
+/// 0 when disabled.
+static DOUBLE_TAP_INTERVAL: AtomicU32 = AtomicU32::new(0);
+/// Scan code of the key pressed last, a modifier released right after it was tapped.
+static LAST_KEY_DOWN: AtomicU32 = AtomicU32::new(0);
+/// Scan code and time of the previous tap of the switch apps modifier.
+static LAST_TAP_CODE: AtomicU32 = AtomicU32::new(0);
+static LAST_TAP_TIME: AtomicU32 = AtomicU32::new(0);
+/// Whether the overlay stays open once the modifier is released.
+static IS_STICKY: AtomicBool = AtomicBool::new(false);
+/// Whether holding ctrl while releasing the modifier keeps the overlay open.
+static IS_INSPECT: AtomicBool = AtomicBool::new(false);
+/// Whether the last switch apps message opened an overlay, sticky mode has nothing to keep
+/// open otherwise, e.g. with no apps on the current desktop.
+static IS_OVERLAY_OPEN: AtomicBool = AtomicBool::new(false);
+/// Tracks whether the overlay was left open by releasing the modifier in sticky mode.
+static IS_STICKY_OPEN: AtomicBool = AtomicBool::new(false);
+/// Scan code of the key closing the selected window while the overlay is shown, 0 for none.
+static CLOSE_KEY: AtomicU32 = AtomicU32::new(0);
+/// Scan code of the key minimizing the selected window while the overlay is shown, 0 for none.
+static MINIMIZE_KEY: AtomicU32 = AtomicU32::new(0);
+/// The close and minimize keys as configured, resolved into the codes above by the layout.
+static ACTION_KEYS: Mutex<[Option<SingleKey>; 2]> = Mutex::new([None; 2]);
+/// Scan codes of the keys cancelling a switch, 0 for unused slots. Read on every key event,
+/// so they are atomics rather than behind a lock.
+static CANCEL_KEYS: [AtomicU32; MAX_CANCEL_KEYS] = [const { AtomicU32::new(0) }; MAX_CANCEL_KEYS];
+const MAX_CANCEL_KEYS: usize = 4;
+/// Whether symbol keys of the hotkeys follow the keyboard layout like letters do.
+static IS_FOLLOW_LAYOUT: AtomicBool = AtomicBool::new(false);
+/// Tracks whether a key was pressed while the win modifier of a hotkey was held.
+static IS_WIN_CHORD: AtomicBool = AtomicBool::new(false);
+/// Time of the ctrl press Windows fakes right before right alt on layouts with AltGr.
+static ALTGR_CTRL_TIME: AtomicU32 = AtomicU32::new(0);
+/// Tracks whether right alt is held as AltGr, it is no alt for the hotkeys then.
+static IS_ALTGR_PRESSED: AtomicBool = AtomicBool::new(false);
 
 #[derive(Debug)]
 pub struct KeyboardListener {
     hook: HHOOK,
+    /// Hotkeys registered with Windows in place of the hook, `None` while the hook is used.
+    /// Each one is registered with the id of twice its index, plus one with shift.
+    registered: Option<Vec<Hotkey>>,
 }
 
 impl KeyboardListener {
-    pub fn init(hwnd: HWND, hotkeys: &[&Hotkey]) -> Result<Self> {
+    pub fn init(hwnd: HWND, hotkeys: &[&Hotkey], mode: HotkeyMode) -> Result<Self> {
         WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
 
-        let keyboard_state = hotkeys
-            .iter()
-            .map(|hotkey| HotKeyState {
-                hotkey: (*hotkey).clone(),
-                is_modifier_pressed: false,
-            })
-            .collect();
-
-        *KEYBOARD_STATE.lock() = keyboard_state;
-
-        let hook = unsafe {
-            let hinstance = { GetModuleHandleW(None) }
-                .map_err(|err| anyhow!("Failed to get module handle, {err}"))?;
-            SetWindowsHookExW(
-                WH_KEYBOARD_LL,
-                Some(keyboard_proc),
-                Some(hinstance.into()),
-                0,
-            )
+        set_keyboard_state(hotkeys);
+
+        if mode != HotkeyMode::Register {
+            match install_hook() {
+                Ok(hook) => {
+                    info!("keyboard listener start");
+                    return Ok(Self {
+                        hook,
+                        registered: None,
+                    });
+                }
+                Err(err) if mode == HotkeyMode::Auto => {
+                    warn!("{err}, falling back to registered hotkeys");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        let hotkeys: Vec<Hotkey> = hotkeys.iter().map(|v| (*v).clone()).collect();
+        register_hotkeys(&hotkeys, false);
+        info!("keyboard listener start with registered hotkeys");
+        Ok(Self {
+            hook: HHOOK::default(),
+            registered: Some(hotkeys),
+        })
+    }
+
+    /// The hotkey of a `WM_HOTKEY` message by its id, if hotkeys are registered.
+    pub fn registered_hotkey(&self, id: usize) -> Option<&Hotkey> {
+        self.registered.as_ref()?.get(id / 2)
+    }
+
+    /// Reinstalls the keyboard hook.
+    ///
+    /// Windows silently removes low-level hooks in some situations, e.g. across session
+    /// lock or remote desktop reconnects, so the hook is refreshed when those happen.
+    pub fn reinstall(&mut self) -> Result<()> {
+        if self.registered.is_some() {
+            return Ok(());
+        }
+        if !self.hook.is_invalid() {
+            let _ = unsafe { UnhookWindowsHookEx(self.hook) };
+            self.hook = HHOOK::default();
+            set_keyboard_hook(HHOOK::default());
+        }
+        // Key up events may have been missed while the hook was not working
+        for state in KEYBOARD_STATE.lock().iter_mut() {
+            state.pressed_modifiers.fill(false);
         }
-        .map_err(|err| anyhow!("Failed to set windows hook, {err}"))?;
-        info!("keyboard listener start");
+        IS_SHIFT_PRESSED.store(false, Ordering::SeqCst);
+        IS_CTRL_PRESSED.store(false, Ordering::SeqCst);
+        IS_WIN_CHORD.store(false, Ordering::SeqCst);
+        self.hook = install_hook()?;
+        info!("keyboard listener reinstalled");
+        Ok(())
+    }
 
-        Ok(Self { hook })
+    /// Removes the keyboard hook until the next [`KeyboardListener::reinstall`].
+    pub fn uninstall(&mut self) {
+        if !self.hook.is_invalid() {
+            let _ = unsafe { UnhookWindowsHookEx(self.hook) };
+            self.hook = HHOOK::default();
+            set_keyboard_hook(HHOOK::default());
+            info!("keyboard listener uninstalled");
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        IS_PAUSED.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        IS_PAUSED.store(paused, Ordering::SeqCst);
+        // Registered hotkeys are taken from Windows, so they are given back while paused
+        if let Some(hotkeys) = &self.registered {
+            unregister_hotkeys(hotkeys);
+            register_hotkeys(hotkeys, paused);
+        }
+        info!("keyboard listener paused:{paused}");
+    }
+
+    pub fn set_quick_select(&mut self, enabled: bool) {
+        IS_QUICK_SELECT.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn set_double_tap_interval(&mut self, interval: u32) {
+        DOUBLE_TAP_INTERVAL.store(interval, Ordering::SeqCst);
+        LAST_TAP_CODE.store(0, Ordering::SeqCst);
+    }
+
+    pub fn set_sticky(&mut self, enabled: bool) {
+        IS_STICKY.store(enabled, Ordering::SeqCst);
+        if !enabled {
+            self.end_sticky();
+        }
+    }
+
+    pub fn set_inspect(&mut self, enabled: bool) {
+        IS_INSPECT.store(enabled, Ordering::SeqCst);
+    }
+
+    /// The overlay was closed, keys without the modifier go to the apps again.
+    pub fn end_sticky(&mut self) {
+        IS_OVERLAY_OPEN.store(false, Ordering::SeqCst);
+        if IS_STICKY_OPEN.swap(false, Ordering::SeqCst) {
+            PREVIOUS_KEYCODE.store(0, Ordering::SeqCst);
+        }
+    }
+
+    pub fn set_action_keys(&mut self, close: Option<SingleKey>, minimize: Option<SingleKey>) {
+        *ACTION_KEYS.lock() = [close, minimize];
+        CLOSE_KEY.store(close.map(|v| v.code).unwrap_or(0), Ordering::SeqCst);
+        MINIMIZE_KEY.store(minimize.map(|v| v.code).unwrap_or(0), Ordering::SeqCst);
+        KEYBOARD_LAYOUT.store(0, Ordering::SeqCst);
+    }
+
+    pub fn set_cancel_keys(&mut self, keys: &[u32]) {
+        if keys.len() > MAX_CANCEL_KEYS {
+            warn!("Only the first {MAX_CANCEL_KEYS} cancel keys are used");
+        }
+        for (i, slot) in CANCEL_KEYS.iter().enumerate() {
+            slot.store(keys.get(i).copied().unwrap_or(0), Ordering::SeqCst);
+        }
+    }
+
+    pub fn set_follow_layout(&mut self, enabled: bool) {
+        IS_FOLLOW_LAYOUT.store(enabled, Ordering::SeqCst);
+        KEYBOARD_LAYOUT.store(0, Ordering::SeqCst);
     }
 
     pub fn update_hotkeys(
@@ -76,15 +247,12 @@ impl KeyboardListener {
             old_hotkeys, new_hotkeys
         );
 
-        let keyboard_state = new_hotkeys
-            .iter()
-            .map(|hotkey| HotKeyState {
-                hotkey: (*hotkey).clone(),
-                is_modifier_pressed: false,
-            })
-            .collect();
-
-        *KEYBOARD_STATE.lock() = keyboard_state;
+        set_keyboard_state(new_hotkeys);
+        if let Some(hotkeys) = self.registered.as_mut() {
+            unregister_hotkeys(hotkeys);
+            *hotkeys = new_hotkeys.iter().map(|v| (*v).clone()).collect();
+            register_hotkeys(hotkeys, IS_PAUSED.load(Ordering::SeqCst));
+        }
         info!("Hotkeys updated successfully");
         Ok(())
     }
@@ -95,14 +263,368 @@ impl Drop for KeyboardListener {
         debug!("keyboard listener destroyed");
         if !self.hook.is_invalid() {
             let _ = unsafe { UnhookWindowsHookEx(self.hook) };
+            set_keyboard_hook(HHOOK::default());
+        }
+        if let Some(hotkeys) = &self.registered {
+            unregister_hotkeys(hotkeys);
         }
     }
 }
 
+/// Registers the hotkeys with Windows, only the pause hotkey while paused.
+/// The switch hotkeys are registered with shift too, which switches backwards.
+fn register_hotkeys(hotkeys: &[Hotkey], paused: bool) {
+    let window = HWND(WINDOW.load(Ordering::SeqCst) as _);
+    for (index, hotkey) in hotkeys.iter().enumerate() {
+        if paused && hotkey.id != PAUSE_HOTKEY_ID {
+            continue;
+        }
+        // Holding the key does not repeat, the slideshow takes over like with the hook
+        let mut modifiers = MOD_NOREPEAT;
+        for modifier in &hotkey.modifiers {
+            modifiers |= match modifier[0] {
+                0x5b => MOD_WIN,
+                0x38 => MOD_ALT,
+                _ => MOD_CONTROL,
+            };
+        }
+        let vk = hotkey_vk(hotkey);
+        let is_switch = hotkey.id == SWITCH_APPS_HOTKEY_ID || is_switch_windows(hotkey.id);
+        let variants = if is_switch {
+            vec![modifiers, modifiers | MOD_SHIFT]
+        } else {
+            vec![modifiers]
+        };
+        for (i, modifiers) in variants.into_iter().enumerate() {
+            let id = (index * 2 + i) as i32;
+            if let Err(err) = unsafe { RegisterHotKey(Some(window), id, modifiers, vk) } {
+                error!("Failed to register {} hotkey, {err}", hotkey.name);
+            }
+        }
+    }
+}
+
+fn unregister_hotkeys(hotkeys: &[Hotkey]) {
+    let window = HWND(WINDOW.load(Ordering::SeqCst) as _);
+    for id in 0..hotkeys.len() * 2 {
+        let _ = unsafe { UnregisterHotKey(Some(window), id as i32) };
+    }
+}
+
+/// Virtual key of the hotkey's key, letters follow the keyboard layout.
+fn hotkey_vk(hotkey: &Hotkey) -> u32 {
+    match hotkey.letter {
+        Some(letter) => letter.to_ascii_uppercase() as u32,
+        None => unsafe { MapVirtualKeyW(hotkey.code, MAPVK_VSC_TO_VK_EX) },
+    }
+}
+
+/// Whether the key of the hotkey, besides its modifiers, is held right now.
+pub fn is_key_held(hotkey: &Hotkey) -> bool {
+    unsafe { GetAsyncKeyState(hotkey_vk(hotkey) as i32) < 0 }
+}
+
+/// Whether all the modifiers of the hotkey are held right now.
+/// Registered hotkeys report no key releases, so the end of a switch is polled with this.
+pub fn is_modifier_held(hotkey: &Hotkey) -> bool {
+    hotkey.modifiers.iter().all(|modifier| {
+        let keys = match modifier[0] {
+            0x5b => [VK_LWIN, VK_RWIN],
+            0x38 => [VK_MENU, VK_MENU],
+            _ => [VK_CONTROL, VK_CONTROL],
+        };
+        keys.iter()
+            .any(|key| unsafe { GetAsyncKeyState(key.0 as i32) } < 0)
+    })
+}
+
+fn install_hook() -> Result<HHOOK> {
+    let hook = unsafe {
+        let hinstance = { GetModuleHandleW(None) }
+            .map_err(|err| anyhow!("Failed to get module handle, {err}"))?;
+        SetWindowsHookExW(
+            WH_KEYBOARD_LL,
+            Some(keyboard_proc),
+            Some(hinstance.into()),
+            0,
+        )
+    }
+    .map_err(|err| anyhow!("Failed to set windows hook, {err}"))?;
+    set_keyboard_hook(hook);
+    Ok(hook)
+}
+
 #[derive(Debug)]
 struct HotKeyState {
     hotkey: Hotkey,
-    is_modifier_pressed: bool,
+    /// Scan code of the hotkey in the active keyboard layout.
+    code: u32,
+    reverse_code: Option<u32>,
+    /// Which of the hotkey's modifiers are held, in the same order.
+    pressed_modifiers: Vec<bool>,
+}
+
+impl HotKeyState {
+    fn is_modifier_pressed(&self) -> bool {
+        self.pressed_modifiers.iter().all(|v| *v)
+    }
+}
+
+fn set_keyboard_state(hotkeys: &[&Hotkey]) {
+    let keyboard_state = hotkeys
+        .iter()
+        .map(|hotkey| HotKeyState {
+            hotkey: (*hotkey).clone(),
+            code: hotkey.code,
+            reverse_code: hotkey.reverse_code,
+            pressed_modifiers: vec![false; hotkey.modifiers.len()],
+        })
+        .collect();
+
+    *KEYBOARD_STATE.lock() = keyboard_state;
+    KEYBOARD_LAYOUT.store(0, Ordering::SeqCst);
+}
+
+/// Resolves the hotkey codes again if the keyboard layout of the foreground window changed.
+fn update_keyboard_layout(states: &mut [HotKeyState]) {
+    let hkl = unsafe { GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None)) };
+    if hkl.is_invalid() || KEYBOARD_LAYOUT.swap(hkl.0 as isize, Ordering::SeqCst) == hkl.0 as isize
+    {
+        return;
+    }
+    debug!("keyboard layout {hkl:?}");
+    let follow_layout = IS_FOLLOW_LAYOUT.load(Ordering::SeqCst);
+    let character =
+        |letter: Option<char>, symbol: Option<char>| letter.or(symbol.filter(|_| follow_layout));
+    for state in states.iter_mut() {
+        let hotkey = &state.hotkey;
+        state.code = resolve_code(character(hotkey.letter, hotkey.symbol), hotkey.code, hkl);
+        state.reverse_code = hotkey.reverse_code.map(|code| {
+            resolve_code(
+                character(hotkey.reverse_letter, hotkey.reverse_symbol),
+                code,
+                hkl,
+            )
+        });
+    }
+    let action_keys = *ACTION_KEYS.lock();
+    for (slot, key) in [&CLOSE_KEY, &MINIMIZE_KEY].into_iter().zip(action_keys) {
+        let code = key.map(|v| resolve_code(v.letter, v.code, hkl));
+        slot.store(code.unwrap_or(0), Ordering::SeqCst);
+    }
+}
+
+/// Finds the scan code of the key typing the character in the keyboard layout.
+///
+/// Falls back to the US scan code, e.g. for layouts without latin letters.
+fn resolve_code(character: Option<char>, code: u32, hkl: HKL) -> u32 {
+    let Some(character) = character else {
+        return code;
+    };
+    let ret = unsafe { VkKeyScanExW(character as u16, hkl) };
+    // The high byte is the shift state, characters typed with ctrl or alt are not usable
+    let [vk, shift_state] = ret.to_le_bytes();
+    if ret == -1 || shift_state & 0x06 != 0 {
+        return code;
+    }
+    match unsafe { MapVirtualKeyExW(vk as u32, MAPVK_VK_TO_VSC, Some(hkl)) } {
+        0 => code,
+        resolved => resolved,
+    }
+}
+
+/// Scan code of both ctrl keys, the right one is flagged as extended.
+const SCANCODE_CTRL: u32 = 0x1d;
+/// Marks the scan codes of extended keys, which are sent with an E0 prefix.
+const SCANCODE_EXTENDED: u32 = 0xe000;
+/// Scan code of the ctrl key faked by AltGr.
+const SCANCODE_ALTGR_CTRL: u32 = 0x21d;
+/// Scan code of both alt keys, the right one is flagged as extended.
+const SCANCODE_ALT: u32 = 0x38;
+const SCANCODE_ENTER: u32 = 0x1c;
+const SCANCODE_LEFT: u32 = 0x4b;
+const SCANCODE_RIGHT: u32 = 0x4d;
+const SCANCODE_UP: u32 = 0x48;
+const SCANCODE_HOME: u32 = 0x47;
+const SCANCODE_END: u32 = 0x4f;
+const SCANCODE_1: u32 = 0x02;
+const SCANCODE_9: u32 = 0x0a;
+const SCANCODE_LWIN: u32 = 0x5b;
+const SCANCODE_RWIN: u32 = 0x5c;
+/// Key code without a meaning, pressed to keep the Start menu closed.
+const VK_MASK: VIRTUAL_KEY = VIRTUAL_KEY(0xe8);
+
+/// What to do with the selected app, holding ctrl starts a new instance of it,
+/// holding ctrl and shift starts it as administrator.
+fn switch_apps_done_action(hotkey: &Hotkey) -> isize {
+    if hotkey.has_ctrl() || !IS_CTRL_PRESSED.load(Ordering::SeqCst) {
+        0
+    } else if IS_SHIFT_PRESSED.load(Ordering::SeqCst) {
+        SWITCH_APPS_DONE_ELEVATED
+    } else {
+        SWITCH_APPS_DONE_NEW_INSTANCE
+    }
+}
+
+/// Whether the overlay stays open as in sticky mode, because ctrl is held while the modifier
+/// is released. Hotkeys with ctrl keep ctrl for starting a new instance.
+fn is_inspect(hotkey: &Hotkey) -> bool {
+    IS_INSPECT.load(Ordering::SeqCst)
+        && !hotkey.has_ctrl()
+        && IS_CTRL_PRESSED.load(Ordering::SeqCst)
+}
+
+/// Ctrl with left/right/up while the overlay is shown snaps the selected window.
+fn snap_action(kbd_data: &KBDLLHOOKSTRUCT) -> Option<isize> {
+    if !is_extended(kbd_data) {
+        return None;
+    }
+    match kbd_data.scanCode {
+        SCANCODE_LEFT => Some(SWITCH_APPS_DONE_SNAP_LEFT),
+        SCANCODE_RIGHT => Some(SWITCH_APPS_DONE_SNAP_RIGHT),
+        SCANCODE_UP => Some(SWITCH_APPS_DONE_MAXIMIZE),
+        _ => None,
+    }
+}
+
+/// Left/right/home/end while the overlay is shown move the selection.
+fn select_action(kbd_data: &KBDLLHOOKSTRUCT) -> Option<isize> {
+    if !is_extended(kbd_data) {
+        return None;
+    }
+    match kbd_data.scanCode {
+        SCANCODE_LEFT => Some(SWITCH_APPS_SELECT_PREVIOUS),
+        SCANCODE_RIGHT => Some(SWITCH_APPS_SELECT_NEXT),
+        SCANCODE_HOME => Some(SWITCH_APPS_SELECT_FIRST),
+        SCANCODE_END => Some(SWITCH_APPS_SELECT_LAST),
+        _ => None,
+    }
+}
+
+/// Keys acting on the window of the selected app while the overlay is shown.
+fn window_action(kbd_data: &KBDLLHOOKSTRUCT) -> Option<isize> {
+    let scan_code = kbd_data.scanCode;
+    // Injected keys have no scan code
+    if scan_code == 0 || (is_navigation_key(scan_code) && !is_extended(kbd_data)) {
+        None
+    } else if scan_code == CLOSE_KEY.load(Ordering::SeqCst) {
+        Some(SWITCH_APPS_ACTION_CLOSE)
+    } else if scan_code == MINIMIZE_KEY.load(Ordering::SeqCst) {
+        Some(SWITCH_APPS_ACTION_MINIMIZE)
+    } else {
+        None
+    }
+}
+
+/// Index of the app for the number keys 1-9 above the letters.
+fn quick_select_index(scan_code: u32) -> Option<isize> {
+    if IS_QUICK_SELECT.load(Ordering::SeqCst) && (SCANCODE_1..=SCANCODE_9).contains(&scan_code) {
+        Some((scan_code - SCANCODE_1) as isize)
+    } else {
+        None
+    }
+}
+
+/// Releases the win key with the mask key pressed before, the Start menu opens when
+/// Windows sees the win key released with nothing in between.
+fn release_win_key_masked(kbd_data: &KBDLLHOOKSTRUCT) {
+    let key_input = |vk: VIRTUAL_KEY, scan: u32, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: scan as u16,
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    };
+    let inputs = [
+        key_input(VK_MASK, 0, KEYBD_EVENT_FLAGS(0)),
+        key_input(VK_MASK, 0, KEYEVENTF_KEYUP),
+        key_input(
+            VIRTUAL_KEY(kbd_data.vkCode as u16),
+            kbd_data.scanCode,
+            KEYEVENTF_KEYUP | KEYEVENTF_EXTENDEDKEY,
+        ),
+    ];
+    // SAFETY: the inputs are fully initialized keyboard inputs
+    unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+}
+
+/// Whether the key event completes a second tap of a switch apps modifier in a row.
+fn is_double_tap(kbd_data: &KBDLLHOOKSTRUCT, is_pressed: bool, is_modifier: bool) -> bool {
+    let interval = DOUBLE_TAP_INTERVAL.load(Ordering::SeqCst);
+    let scan_code = kbd_data.scanCode;
+    if interval == 0 {
+        return false;
+    }
+    if is_pressed {
+        // Any other key in between starts over
+        if scan_code != LAST_TAP_CODE.load(Ordering::SeqCst) {
+            LAST_TAP_CODE.store(0, Ordering::SeqCst);
+        }
+        LAST_KEY_DOWN.store(scan_code, Ordering::SeqCst);
+        return false;
+    }
+    if !is_modifier || LAST_KEY_DOWN.load(Ordering::SeqCst) != scan_code {
+        return false;
+    }
+    let previous_code = LAST_TAP_CODE.swap(scan_code, Ordering::SeqCst);
+    let previous_time = LAST_TAP_TIME.swap(kbd_data.time, Ordering::SeqCst);
+    if previous_code == scan_code && kbd_data.time.wrapping_sub(previous_time) <= interval {
+        LAST_TAP_CODE.store(0, Ordering::SeqCst);
+        return true;
+    }
+    false
+}
+
+/// Hotkeys switching windows while held, releasing their modifiers ends the switch.
+fn is_switch_windows(id: u32) -> bool {
+    id == SWITCH_WINDOWS_HOTKEY_ID || id == SWITCH_ALL_WINDOWS_HOTKEY_ID
+}
+
+/// Whether the key is the one of a hotkey. Extended keys such as media keys only match
+/// when flagged as extended, other keys match with or without the flag.
+/// Whether the key is sent with an E0 prefix. The arrows and home/end share their scan codes
+/// with the numpad keys, which send them without it while num lock is off.
+fn is_extended(kbd_data: &KBDLLHOOKSTRUCT) -> bool {
+    kbd_data.flags.0 & LLKHF_EXTENDED.0 != 0
+}
+
+/// Whether the key is one of delete/insert/home/end/page up/page down or the arrows, which
+/// are configured by their names and not meant as the numpad keys sharing their scan codes.
+fn is_navigation_key(scan_code: u32) -> bool {
+    (0x47..=0x53).contains(&scan_code) && ![0x4a, 0x4c, 0x4e].contains(&scan_code)
+}
+
+fn is_hotkey_key(code: u32, kbd_data: &KBDLLHOOKSTRUCT) -> bool {
+    if code & SCANCODE_EXTENDED == 0 {
+        return code == kbd_data.scanCode;
+    }
+    is_extended(kbd_data) && code == SCANCODE_EXTENDED | kbd_data.scanCode
+}
+
+/// Whether the key event belongs to AltGr, the faked ctrl or right alt on layouts with AltGr.
+/// Typing characters with AltGr is then not taken for hotkeys using alt or ctrl.
+fn is_altgr(kbd_data: &KBDLLHOOKSTRUCT, is_pressed: bool) -> bool {
+    if kbd_data.scanCode == SCANCODE_ALTGR_CTRL {
+        if is_pressed {
+            ALTGR_CTRL_TIME.store(kbd_data.time, Ordering::SeqCst);
+        }
+        return true;
+    }
+    if kbd_data.scanCode != SCANCODE_ALT || kbd_data.flags.0 & LLKHF_EXTENDED.0 == 0 {
+        return false;
+    }
+    if is_pressed {
+        // The faked ctrl comes with the same time, also for auto-repeat
+        let is_altgr = ALTGR_CTRL_TIME.load(Ordering::SeqCst) == kbd_data.time;
+        IS_ALTGR_PRESSED.store(is_altgr, Ordering::SeqCst);
+        is_altgr
+    } else {
+        IS_ALTGR_PRESSED.swap(false, Ordering::SeqCst)
+    }
 }
 
 /// Helper to get the window handle safely from atomic storage.
@@ -115,70 +637,257 @@ unsafe extern "system" fn keyboard_proc(code: i32, w_param: WPARAM, l_param: LPA
     let kbd_data: &KBDLLHOOKSTRUCT = unsafe { &*(l_param.0 as *const _) };
     debug!("keyboard {kbd_data:?}");
     let mut is_modifier = false;
+    let mut is_switch_apps_modifier = false;
     let scan_code = kbd_data.scanCode;
     let is_key_pressed = || kbd_data.flags.0 & LLKHF_UP.0 == 0;
     if [SCANCODE_LSHIFT, SCANCODE_RSHIFT].contains(&scan_code) {
         IS_SHIFT_PRESSED.store(is_key_pressed(), Ordering::SeqCst);
     }
+    if scan_code == SCANCODE_CTRL {
+        IS_CTRL_PRESSED.store(is_key_pressed(), Ordering::SeqCst);
+    }
+    if is_altgr(kbd_data, is_key_pressed()) {
+        // SAFETY: CallNextHookEx is called with valid parameters from the hook chain
+        return unsafe { CallNextHookEx(None, code, w_param, l_param) };
+    }
     let window = get_window();
+    update_keyboard_layout(&mut KEYBOARD_STATE.lock());
     for state in KEYBOARD_STATE.lock().iter_mut() {
-        if state.hotkey.modifier.contains(&scan_code) {
+        let index = state
+            .hotkey
+            .modifiers
+            .iter()
+            .position(|v| v.contains(&scan_code));
+        if let Some(index) = index {
             is_modifier = true;
+            if state.hotkey.id == SWITCH_APPS_HOTKEY_ID {
+                is_switch_apps_modifier = true;
+            }
             if is_key_pressed() {
-                state.is_modifier_pressed = true;
+                state.pressed_modifiers[index] = true;
             } else {
-                state.is_modifier_pressed = false;
-                if PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.hotkey.code {
+                state.pressed_modifiers[index] = false;
+                if state.hotkey.id == SWITCH_APPS_HOTKEY_ID {
+                    IS_TRIGGER_DOWN.store(false, Ordering::SeqCst);
+                }
+                if PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code {
                     let id = state.hotkey.id;
+                    if id == SWITCH_APPS_HOTKEY_ID
+                        && IS_OVERLAY_OPEN.load(Ordering::SeqCst)
+                        && (IS_STICKY.load(Ordering::SeqCst) || is_inspect(&state.hotkey))
+                    {
+                        // The overlay stays open, Enter switches and Esc cancels
+                        IS_STICKY_OPEN.store(true, Ordering::SeqCst);
+                        continue;
+                    }
                     if id == SWITCH_APPS_HOTKEY_ID {
                         // SAFETY: window is a valid HWND set during init
-                        unsafe { SendMessageW(window, WM_USER_SWITCH_APPS_DONE, None, None) };
-                    } else if id == SWITCH_WINDOWS_HOTKEY_ID {
+                        unsafe {
+                            SendMessageW(
+                                window,
+                                WM_USER_SWITCH_APPS_DONE,
+                                None,
+                                Some(LPARAM(switch_apps_done_action(&state.hotkey))),
+                            )
+                        };
+                    } else if is_switch_windows(id) {
                         // SAFETY: window is a valid HWND set during init
                         unsafe { SendMessageW(window, WM_USER_SWITCH_WINDOWS_DONE, None, None) };
                     }
+                    // Switching is over, keys like alt+left go to the apps again
+                    PREVIOUS_KEYCODE.store(0, Ordering::SeqCst);
                 }
             }
         }
     }
+    if is_double_tap(kbd_data, is_key_pressed(), is_switch_apps_modifier)
+        && !IS_PAUSED.load(Ordering::SeqCst)
+        && !IS_FOREGROUND_FULL_SCREEN.load(Ordering::SeqCst)
+    {
+        // SAFETY: window is a valid HWND set during init
+        unsafe { SendMessageW(window, WM_USER_SWITCH_APPS_LAST, None, None) };
+    }
+    if is_modifier
+        && !is_key_pressed()
+        && [SCANCODE_LWIN, SCANCODE_RWIN].contains(&scan_code)
+        && IS_WIN_CHORD.swap(false, Ordering::SeqCst)
+    {
+        // The release is sent again after the mask key, it comes through the hook once more
+        release_win_key_masked(kbd_data);
+        return LRESULT(1);
+    }
     if !is_modifier {
+        // Full screen apps such as games get their keybinds untouched, like when paused
+        let is_paused = IS_PAUSED.load(Ordering::SeqCst);
+        let mut is_full_screen = IS_FOREGROUND_FULL_SCREEN.load(Ordering::SeqCst);
+        let is_cancel_key = scan_code != 0
+            && CANCEL_KEYS
+                .iter()
+                .any(|v| v.load(Ordering::SeqCst) == scan_code);
         for state in KEYBOARD_STATE.lock().iter_mut() {
-            if is_key_pressed() && state.is_modifier_pressed {
+            // Only checked again once a hotkey is pressed, every key would slow down typing
+            if is_full_screen
+                && state.hotkey.id != PAUSE_HOTKEY_ID
+                && is_hotkey_key(state.code, kbd_data)
+            {
+                is_full_screen = refresh_foreground_full_screen();
+            }
+            if (is_paused || is_full_screen) && state.hotkey.id != PAUSE_HOTKEY_ID {
+                continue;
+            }
+            let is_sticky_open =
+                state.hotkey.id == SWITCH_APPS_HOTKEY_ID && IS_STICKY_OPEN.load(Ordering::SeqCst);
+            if is_key_pressed() && (state.is_modifier_pressed() || is_sticky_open) {
+                if state.hotkey.has_win() {
+                    IS_WIN_CHORD.store(true, Ordering::SeqCst);
+                }
                 let id = state.hotkey.id;
-                if scan_code == state.hotkey.code {
-                    let reverse = if IS_SHIFT_PRESSED.load(Ordering::SeqCst) {
+                let is_reverse_key = state
+                    .reverse_code
+                    .is_some_and(|code| is_hotkey_key(code, kbd_data));
+                if is_hotkey_key(state.code, kbd_data) || is_reverse_key {
+                    let reverse = if is_reverse_key || IS_SHIFT_PRESSED.load(Ordering::SeqCst) {
                         1
                     } else {
                         0
                     };
-                    if id == SWITCH_APPS_HOTKEY_ID {
+                    // A switch going on is not stopped by the windows it passes
+                    let is_switching = (id == SWITCH_APPS_HOTKEY_ID || is_switch_windows(id))
+                        && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code;
+                    let is_blacklisted = !is_switching && is_hotkey_blacklisted(id);
+                    if id == SWITCH_APPS_HOTKEY_ID && !is_blacklisted {
+                        let flags = if IS_TRIGGER_DOWN.swap(true, Ordering::SeqCst) {
+                            reverse | SWITCH_APPS_REPEAT
+                        } else {
+                            reverse
+                        };
+                        // SAFETY: window is a valid HWND set during init
+                        let result = unsafe {
+                            SendMessageW(window, WM_USER_SWITCH_APPS, None, Some(LPARAM(flags)))
+                        };
+                        IS_OVERLAY_OPEN.store(result.0 != 0, Ordering::SeqCst);
+                        // The hotkey's own code, releasing the modifier ends switching
+                        PREVIOUS_KEYCODE.store(state.code, Ordering::SeqCst);
+                        return LRESULT(1);
+                    } else if is_switch_windows(id) && !is_blacklisted {
+                        let flags = if id == SWITCH_ALL_WINDOWS_HOTKEY_ID {
+                            reverse | SWITCH_WINDOWS_ALL
+                        } else {
+                            reverse
+                        };
                         // SAFETY: window is a valid HWND set during init
                         unsafe {
-                            SendMessageW(window, WM_USER_SWITCH_APPS, None, Some(LPARAM(reverse)))
+                            SendMessageW(window, WM_USER_SWITCH_WINDOWS, None, Some(LPARAM(flags)))
                         };
-                        PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
+                        PREVIOUS_KEYCODE.store(state.code, Ordering::SeqCst);
                         return LRESULT(1);
-                    } else if id == SWITCH_WINDOWS_HOTKEY_ID
-                        && !IS_FOREGROUND_IN_BLACKLIST.load(Ordering::SeqCst)
+                    } else if id != SWITCH_APPS_HOTKEY_ID
+                        && !is_switch_windows(id)
+                        && !is_blacklisted
                     {
                         // SAFETY: window is a valid HWND set during init
                         unsafe {
-                            SendMessageW(
-                                window,
-                                WM_USER_SWITCH_WINDOWS,
-                                None,
-                                Some(LPARAM(reverse)),
-                            )
+                            SendMessageW(window, WM_USER_HOTKEY, Some(WPARAM(id as _)), None)
                         };
                         PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
                         return LRESULT(1);
                     }
-                } else if scan_code == 0x01 && id == SWITCH_APPS_HOTKEY_ID {
+                } else if scan_code == SCANCODE_ENTER
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // Only while the overlay is shown, e.g. alt+enter is left to the apps
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe {
+                        SendMessageW(
+                            window,
+                            WM_USER_SWITCH_APPS_DONE,
+                            None,
+                            Some(LPARAM(switch_apps_done_action(&state.hotkey))),
+                        )
+                    };
+                    PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
+                    return LRESULT(1);
+                } else if let Some(action) = snap_action(kbd_data)
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && IS_CTRL_PRESSED.load(Ordering::SeqCst)
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe {
+                        SendMessageW(window, WM_USER_SWITCH_APPS_DONE, None, Some(LPARAM(action)))
+                    };
+                    PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
+                    return LRESULT(1);
+                } else if let Some(select) = select_action(kbd_data)
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // The previous keycode is kept, releasing the modifier still switches
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe {
+                        SendMessageW(
+                            window,
+                            WM_USER_SWITCH_APPS_SELECT,
+                            None,
+                            Some(LPARAM(select)),
+                        )
+                    };
+                    return LRESULT(1);
+                } else if let Some(action) = window_action(kbd_data)
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // The previous keycode is kept, releasing the modifier still switches
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe {
+                        SendMessageW(
+                            window,
+                            WM_USER_SWITCH_APPS_ACTION,
+                            None,
+                            Some(LPARAM(action)),
+                        )
+                    };
+                    return LRESULT(1);
+                } else if let Some(index) = quick_select_index(scan_code)
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe {
+                        SendMessageW(window, WM_USER_SWITCH_APPS_PICK, None, Some(LPARAM(index)))
+                    };
+                    PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
+                    return LRESULT(1);
+                } else if is_cancel_key
+                    && id == SWITCH_APPS_HOTKEY_ID
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // Only while the overlay is shown, e.g. alt+backspace is left to the apps
                     // SAFETY: window is a valid HWND set during init
                     unsafe { SendMessageW(window, WM_USER_SWITCH_APPS_CANCEL, None, None) };
                     PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
                     return LRESULT(1);
+                } else if is_cancel_key
+                    && is_switch_windows(id)
+                    && PREVIOUS_KEYCODE.load(Ordering::SeqCst) == state.code
+                {
+                    // Only while switching, e.g. alt+esc is left to Windows
+                    // SAFETY: window is a valid HWND set during init
+                    unsafe { SendMessageW(window, WM_USER_SWITCH_WINDOWS_CANCEL, None, None) };
+                    PREVIOUS_KEYCODE.store(scan_code, Ordering::SeqCst);
+                    return LRESULT(1);
                 }
+            } else if !is_key_pressed()
+                && (is_hotkey_key(state.code, kbd_data)
+                    || state
+                        .reverse_code
+                        .is_some_and(|code| is_hotkey_key(code, kbd_data)))
+                && state.hotkey.id == SWITCH_APPS_HOTKEY_ID
+                && IS_TRIGGER_DOWN.swap(false, Ordering::SeqCst)
+            {
+                // SAFETY: window is a valid HWND set during init
+                unsafe { SendMessageW(window, WM_USER_SWITCH_APPS_KEY_UP, None, None) };
             }
         }
     }
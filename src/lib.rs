@@ -6,11 +6,20 @@ extern crate log;
 
 mod app;
 mod config;
+mod crash;
 mod foreground;
 mod keyboard;
+mod matcher;
+mod mouse;
+mod mouse_trigger;
 mod painter;
 mod startup;
+mod statistics;
+mod storage;
+mod tooltip;
 mod trayicon;
+mod window_menu;
+mod window_ops;
 
 pub use crate::app::start;
-pub use crate::config::{load_config, Config};
+pub use crate::config::{load_config, set_config_path, Config};
@@ -3,10 +3,10 @@
 use anyhow::{anyhow, Result};
 use std::{
     fs::{File, OpenOptions},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use window_switcher::{alert, load_config, start, utils::SingleInstance};
+use window_switcher::{alert, load_config, set_config_path, start, utils::SingleInstance};
 
 fn main() {
     if let Err(err) = run() {
@@ -16,6 +16,10 @@ fn main() {
 }
 
 fn run() -> Result<()> {
+    let args = Args::parse()?;
+    if let Some(path) = args.config_path {
+        set_config_path(path);
+    }
     let config = load_config().unwrap_or_default();
     if let Some(log_file) = &config.log_file {
         let file = prepare_log_file(log_file).map_err(|err| {
@@ -26,11 +30,19 @@ fn run() -> Result<()> {
         })?;
         simple_logging::log_to(file, config.log_level);
     }
-    
-    let instance = SingleInstance::create("WindowSwitcherMutex")?;
+    for arg in &args.unknown {
+        log::warn!("Ignored unknown argument '{arg}'");
+    }
+
+    let instance_id = config.instance_id();
+    let instance = SingleInstance::create(instance_id.as_deref())?;
     if !instance.is_single() {
-        // Another instance is running, signal it to reload config
-        SingleInstance::signal_reload_config()?;
+        // Another instance is running, signal it to reload config or to show itself
+        if args.reload {
+            SingleInstance::signal_reload_config(instance_id.as_deref())?;
+        } else {
+            SingleInstance::signal_second_instance(instance_id.as_deref())?;
+        }
         return Ok(());
     }
 
@@ -44,3 +56,33 @@ fn prepare_log_file(path: &Path) -> std::io::Result<File> {
         File::create(path)
     }
 }
+
+#[derive(Debug, Default)]
+struct Args {
+    config_path: Option<PathBuf>,
+    reload: bool,
+    /// Arguments of other versions, e.g. from an older scheduled task, warned about later.
+    unknown: Vec<String>,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = iter
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing value for '--config'"))?;
+                    let path = std::path::absolute(&path)
+                        .map_err(|err| anyhow!("Invalid config path '{path}', {err}"))?;
+                    args.config_path = Some(path);
+                }
+                "--reload" => args.reload = true,
+                _ => args.unknown.push(arg),
+            }
+        }
+        Ok(args)
+    }
+}
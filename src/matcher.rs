@@ -0,0 +1,82 @@
+//! Fuzzy matching of what is typed into the search palette against window titles and exe names.
+
+/// Scores how well `query` matches `text`, `None` unless its characters all appear in order.
+/// Matches at the start of words and runs of consecutive characters score higher, spaces in
+/// the query are ignored.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut start = 0;
+    let mut previous: Option<usize> = None;
+    for c in query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+    {
+        let i = start + text[start..].iter().position(|v| *v == c)?;
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(p) if p + 1 == i => score += 5,
+            Some(p) => score -= (i - p - 1).min(5) as i32,
+            None => score -= i.min(5) as i32,
+        }
+        previous = Some(i);
+        start = i + 1;
+    }
+    Some(score)
+}
+
+/// Indices of the items matching `query`, best first. An item scores as its best matching
+/// text, items scoring the same keep their order so an empty query lists them all as given.
+pub fn rank<'a, I>(query: &str, items: impl IntoIterator<Item = I>) -> Vec<usize>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut scores: Vec<(usize, i32)> = items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, texts)| {
+            texts
+                .into_iter()
+                .filter_map(|text| fuzzy_score(query, text))
+                .max()
+                .map(|score| (i, score))
+        })
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    scores.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "Notepad"), Some(0));
+        assert!(fuzzy_score("ntp", "Notepad").is_some());
+        assert!(fuzzy_score("NOTE", "notepad").is_some());
+        assert_eq!(fuzzy_score("pn", "Notepad"), None);
+        assert_eq!(fuzzy_score("notepadx", "Notepad"), None);
+        // Word starts and consecutive characters win over scattered ones
+        assert!(fuzzy_score("vsc", "Visual Studio Code") > fuzzy_score("vsc", "vlc music"));
+        assert!(fuzzy_score("code", "Code.exe") > fuzzy_score("code", "cmd - docker"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let items = [
+            ["Untitled - Notepad", "notepad.exe"],
+            ["Inbox - Mail", "outlook.exe"],
+            ["README.md - Visual Studio Code", "Code.exe"],
+        ];
+        assert_eq!(rank("", items), vec![0, 1, 2]);
+        assert_eq!(rank("code", items), vec![2]);
+        assert_eq!(rank("outlook", items), vec![1]);
+        assert_eq!(rank("mail", items), vec![1]);
+        assert_eq!(rank("xyz", items), Vec::<usize>::new());
+    }
+}
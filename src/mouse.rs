@@ -0,0 +1,83 @@
+use crate::app::WM_USER_SWITCH_APPS_CANCEL;
+use crate::crash::set_mouse_hook;
+
+use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicIsize, Ordering};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM},
+    Graphics::Gdi::PtInRect,
+    System::LibraryLoader::GetModuleHandleW,
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, GetWindowRect, HHOOK, MSLLHOOKSTRUCT, PostMessageW, SetWindowsHookExW,
+        UnhookWindowsHookEx, WH_MOUSE_LL, WM_LBUTTONDOWN, WM_MBUTTONDOWN, WM_RBUTTONDOWN,
+        WM_XBUTTONDOWN,
+    },
+};
+
+/// Window handle of the overlay for the hook callback.
+static WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// Watches mouse clicks while the switch apps overlay is shown, a click outside of it
+/// cancels the switch. The hook is removed when dropped.
+#[derive(Debug)]
+pub struct MouseListener {
+    hook: HHOOK,
+}
+
+impl MouseListener {
+    pub fn init(hwnd: HWND) -> Result<Self> {
+        WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+        let hook = unsafe {
+            let hinstance = { GetModuleHandleW(None) }
+                .map_err(|err| anyhow!("Failed to get module handle, {err}"))?;
+            SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), Some(hinstance.into()), 0)
+        }
+        .map_err(|err| anyhow!("Failed to set mouse hook, {err}"))?;
+        set_mouse_hook(hook);
+        debug!("mouse listener start");
+        Ok(Self { hook })
+    }
+}
+
+impl Drop for MouseListener {
+    fn drop(&mut self) {
+        debug!("mouse listener destroyed");
+        if !self.hook.is_invalid() {
+            let _ = unsafe { UnhookWindowsHookEx(self.hook) };
+            set_mouse_hook(HHOOK::default());
+        }
+    }
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let msg = w_param.0 as u32;
+    if code >= 0
+        && [
+            WM_LBUTTONDOWN,
+            WM_RBUTTONDOWN,
+            WM_MBUTTONDOWN,
+            WM_XBUTTONDOWN,
+        ]
+        .contains(&msg)
+    {
+        // SAFETY: l_param points to a valid MSLLHOOKSTRUCT provided by Windows
+        let mouse_data: &MSLLHOOKSTRUCT = unsafe { &*(l_param.0 as *const _) };
+        let window = HWND(WINDOW.load(Ordering::SeqCst) as _);
+        let mut rect = RECT::default();
+        // SAFETY: window is the valid HWND of the overlay set during init
+        unsafe {
+            if GetWindowRect(window, &mut rect).is_ok() && !PtInRect(&rect, mouse_data.pt).as_bool()
+            {
+                // Posted since the hook must return quickly, the click itself goes through
+                let _ = PostMessageW(
+                    Some(window),
+                    WM_USER_SWITCH_APPS_CANCEL,
+                    WPARAM(0),
+                    LPARAM(0),
+                );
+            }
+        }
+    }
+    // SAFETY: CallNextHookEx is called with valid parameters from the hook chain
+    unsafe { CallNextHookEx(None, code, w_param, l_param) }
+}
@@ -0,0 +1,118 @@
+use crate::app::{
+    SWITCH_APPS_MOUSE, SWITCH_APPS_REVERSE, WM_USER_SWITCH_APPS, WM_USER_SWITCH_APPS_DONE,
+};
+use crate::config::MouseTrigger;
+use crate::crash::set_mouse_trigger_hook;
+
+use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU16, Ordering};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, HHOOK, MSLLHOOKSTRUCT, PostMessageW, SetWindowsHookExW,
+        UnhookWindowsHookEx, WH_MOUSE_LL, WM_MOUSEHWHEEL, WM_MOUSEWHEEL, WM_XBUTTONDOWN,
+        WM_XBUTTONUP, XBUTTON1, XBUTTON2,
+    },
+};
+
+/// Window handle for the hook callback.
+static WINDOW: AtomicIsize = AtomicIsize::new(0);
+/// Side button switching apps, 0 for none.
+static XBUTTON: AtomicU16 = AtomicU16::new(0);
+/// Whether tilting the wheel switches apps.
+static IS_TILT: AtomicBool = AtomicBool::new(false);
+/// Tracks whether the side button is held, the wheel moves the selection meanwhile.
+static IS_BUTTON_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Switches apps with a mouse side button or by tilting the wheel, like the switch apps
+/// hotkey. The hook is removed when dropped.
+#[derive(Debug)]
+pub struct MouseTriggerListener {
+    hook: HHOOK,
+}
+
+impl MouseTriggerListener {
+    pub fn init(hwnd: HWND, trigger: MouseTrigger) -> Result<Self> {
+        WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+        let xbutton = match trigger {
+            MouseTrigger::Back => XBUTTON1,
+            MouseTrigger::Forward => XBUTTON2,
+            _ => 0,
+        };
+        XBUTTON.store(xbutton, Ordering::SeqCst);
+        IS_TILT.store(trigger == MouseTrigger::Tilt, Ordering::SeqCst);
+        IS_BUTTON_DOWN.store(false, Ordering::SeqCst);
+        let hook = unsafe {
+            let hinstance = { GetModuleHandleW(None) }
+                .map_err(|err| anyhow!("Failed to get module handle, {err}"))?;
+            SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_proc), Some(hinstance.into()), 0)
+        }
+        .map_err(|err| anyhow!("Failed to set mouse trigger hook, {err}"))?;
+        set_mouse_trigger_hook(hook);
+        info!("mouse trigger listener start");
+        Ok(Self { hook })
+    }
+}
+
+impl Drop for MouseTriggerListener {
+    fn drop(&mut self) {
+        debug!("mouse trigger listener destroyed");
+        if !self.hook.is_invalid() {
+            let _ = unsafe { UnhookWindowsHookEx(self.hook) };
+            set_mouse_trigger_hook(HHOOK::default());
+        }
+    }
+}
+
+/// Posts the message for the mouse input, false if it is left to the apps.
+fn handle_mouse(msg: u32, mouse_data: &MSLLHOOKSTRUCT) -> bool {
+    // The high word is the button or the wheel delta
+    let high_word = (mouse_data.mouseData >> 16) as u16;
+    let (message, lparam) = match msg {
+        WM_XBUTTONDOWN | WM_XBUTTONUP if high_word == XBUTTON.load(Ordering::SeqCst) => {
+            let is_down = msg == WM_XBUTTONDOWN;
+            IS_BUTTON_DOWN.store(is_down, Ordering::SeqCst);
+            if is_down {
+                (WM_USER_SWITCH_APPS, SWITCH_APPS_MOUSE)
+            } else {
+                (WM_USER_SWITCH_APPS_DONE, 0)
+            }
+        }
+        WM_MOUSEWHEEL if IS_BUTTON_DOWN.load(Ordering::SeqCst) => {
+            // Scrolling up selects the previous app
+            let reverse = if high_word as i16 > 0 {
+                SWITCH_APPS_REVERSE
+            } else {
+                0
+            };
+            (WM_USER_SWITCH_APPS, SWITCH_APPS_MOUSE | reverse)
+        }
+        WM_MOUSEHWHEEL if IS_TILT.load(Ordering::SeqCst) => {
+            // Tilting left selects the previous app
+            let reverse = if (high_word as i16) < 0 {
+                SWITCH_APPS_REVERSE
+            } else {
+                0
+            };
+            (WM_USER_SWITCH_APPS, SWITCH_APPS_MOUSE | reverse)
+        }
+        _ => return false,
+    };
+    let window = HWND(WINDOW.load(Ordering::SeqCst) as _);
+    // Posted since the hook must return quickly
+    let _ = unsafe { PostMessageW(Some(window), message, WPARAM(0), LPARAM(lparam)) };
+    true
+}
+
+unsafe extern "system" fn mouse_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    if code >= 0 {
+        // SAFETY: l_param points to a valid MSLLHOOKSTRUCT provided by Windows
+        let mouse_data: &MSLLHOOKSTRUCT = unsafe { &*(l_param.0 as *const _) };
+        if handle_mouse(w_param.0 as u32, mouse_data) {
+            return LRESULT(1);
+        }
+    }
+    // SAFETY: CallNextHookEx is called with valid parameters from the hook chain
+    unsafe { CallNextHookEx(None, code, w_param, l_param) }
+}
@@ -1,56 +1,222 @@
-use crate::app::SwitchAppsState;
-use crate::utils::{check_error, get_moinitor_rect, is_light_theme, is_win11};
+mod d2d;
+mod fade;
+mod mirrors;
+mod search;
+mod slide;
+mod titles;
+
+pub use d2d::D2dPainter;
+pub use search::SearchPainter;
+pub use titles::TitlesPainter;
+use fade::Fade;
+use mirrors::Mirrors;
+use slide::Slide;
+
+use crate::app::{SwitchAppsItem, SwitchAppsState};
+use crate::config::{
+    Config, HighlightStyle, OverlayMonitor, OverlayPosition, OverlayWrap, Renderer, Theme,
+    ThemePreset,
+};
+use crate::utils::{
+    check_error, get_moinitor_dpi, get_moinitor_rect, get_shield_icon, get_sys_color,
+    is_high_contrast, is_light_theme, is_win11, set_window_blur, set_window_capture_exclusion,
+    to_wstring,
+};
 
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use windows::Win32::{
     Foundation::{COLORREF, HWND, POINT, RECT, SIZE},
     Graphics::{
+        Dwm::{
+            DwmQueryThumbnailSourceSize, DwmRegisterThumbnail, DwmUnregisterThumbnail,
+            DwmSetWindowAttribute, DwmUpdateThumbnailProperties, DWMWA_WINDOW_CORNER_PREFERENCE,
+            DWMWCP_DONOTROUND, DWMWCP_ROUND, DWM_THUMBNAIL_PROPERTIES, DWM_TNP_OPACITY,
+            DWM_TNP_RECTDESTINATION, DWM_TNP_SOURCECLIENTAREAONLY, DWM_TNP_VISIBLE,
+        },
         Gdi::{
-            CreateCompatibleBitmap, CreateCompatibleDC, CreateRoundRectRgn, CreateSolidBrush,
-            DeleteDC, DeleteObject, FillRect, FillRgn, GetDC, ReleaseDC, SelectObject,
-            SetStretchBltMode, StretchBlt, AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, HALFTONE,
-            HBITMAP, HDC, HPALETTE, SRCCOPY,
+            AlphaBlend, CreateCompatibleBitmap, CreateCompatibleDC, CreateRoundRectRgn,
+            CreateSolidBrush, DeleteDC, DeleteObject, FillRect, FillRgn, FrameRgn, GetDC,
+            MonitorFromPoint, MonitorFromWindow, ReleaseDC, SelectObject, SetStretchBltMode,
+            StretchBlt, AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, COLOR_HIGHLIGHT, COLOR_WINDOW,
+            COLOR_WINDOWTEXT, HALFTONE, HBITMAP, HDC, HMONITOR, HPALETTE, MONITOR_DEFAULTTONEAREST,
+            MONITOR_DEFAULTTOPRIMARY, SRCCOPY,
         },
         GdiPlus::{
-            FillModeAlternate, GdipAddPathArc, GdipClosePathFigure, GdipCreateBitmapFromHBITMAP,
-            GdipCreateFromHDC, GdipCreatePath, GdipCreatePen1, GdipDeleteBrush, GdipDeleteGraphics,
-            GdipDeletePath, GdipDeletePen, GdipDisposeImage, GdipDrawImageRect, GdipFillPath,
-            GdipFillRectangle, GdipGetPenBrushFill, GdipSetInterpolationMode, GdipSetSmoothingMode,
-            GdiplusShutdown, GdiplusStartup, GdiplusStartupInput, GpBitmap, GpBrush, GpGraphics,
-            GpImage, GpPath, GpPen, InterpolationModeHighQualityBicubic, SmoothingModeAntiAlias,
-            Unit,
+            FillModeAlternate, FontStyleRegular, GdipAddPathArc, GdipClosePathFigure,
+            GdipCreateBitmapFromHBITMAP, GdipCreateBitmapFromHICON, GdipCreateFont,
+            GdipCreateFontFamilyFromName, GdipCreateFromHDC, GdipCreatePath, GdipCreatePen1,
+            GdipCreateSolidFill, GdipCreateStringFormat, GdipDeleteBrush, GdipDeleteFont,
+            GdipDeleteFontFamily, GdipDeleteGraphics, GdipDeletePath, GdipDeletePen,
+            GdipDeleteStringFormat, GdipDisposeImage, GdipDrawImageRect, GdipDrawPath,
+            GdipDrawRectangle, GdipDrawString, GdipFillPath, GdipFillRectangle, GdipGetPenBrushFill,
+            GdipSetInterpolationMode, GdipSetSmoothingMode, GdipSetStringFormatAlign,
+            GdipSetStringFormatFlags, GdipSetStringFormatLineAlign, GdipSetStringFormatTrimming,
+            GdipSetTextRenderingHint, GdiplusShutdown, GdiplusStartup, GdiplusStartupInput,
+            GpBitmap, GpBrush, GpFont, GpFontFamily, GpGraphics, GpImage, GpPath, GpPen,
+            GpSolidFill, GpStringFormat, InterpolationModeHighQualityBicubic, RectF,
+            SmoothingModeAntiAlias, StringAlignmentCenter, StringFormatFlagsNoWrap, StringTrimming,
+            StringTrimmingEllipsisCharacter, StringTrimmingEllipsisPath,
+            TextRenderingHintAntiAliasGridFit, Unit, UnitPixel,
         },
     },
     UI::{
         Input::KeyboardAndMouse::SetFocus,
         WindowsAndMessaging::{
-            DrawIconEx, GetCursorPos, ShowWindow, UpdateLayeredWindow, DI_NORMAL, SW_HIDE, SW_SHOW,
-            ULW_ALPHA,
+            DestroyIcon, DrawIconEx, GetCursorPos, GetForegroundWindow, ShowWindow,
+            UpdateLayeredWindow, DI_NORMAL, HICON, SW_HIDE, SW_SHOW, ULW_ALPHA,
+            USER_DEFAULT_SCREEN_DPI,
         },
     },
 };
+use windows::core::PCWSTR;
 
 pub const BG_DARK_COLOR: u32 = 0x4c4c4c;
 pub const FG_DARK_COLOR: u32 = 0x3b3b3b;
 pub const BG_LIGHT_COLOR: u32 = 0xe0e0e0;
 pub const FG_LIGHT_COLOR: u32 = 0xf2f2f2;
 pub const ALPHA_MASK: u32 = 0xff000000;
+/// Default size of the icons, `[switch-apps] icon_size` overrides it.
 pub const ICON_SIZE: i32 = 64;
+/// Size of the live window previews that replace icons in thumbnail mode.
+pub const THUMBNAIL_SIZE: i32 = 160;
 pub const WINDOW_BORDER_SIZE: i32 = 10;
 pub const ICON_BORDER_SIZE: i32 = 4;
 pub const SCALE_FACTOR: i32 = 6;
+pub const FOOTER_HEIGHT: i32 = 24;
+pub const FOOTER_MIN_WIDTH: i32 = 480;
+pub const FOOTER_FONT_SIZE: f32 = 12.0;
+/// Height of the window title below each icon.
+pub const TITLE_HEIGHT: i32 = 18;
+pub const TITLE_FONT_SIZE: f32 = 11.0;
+/// Size of the preview of the selected window, at most half the monitor high.
+pub const PREVIEW_HEIGHT: i32 = 240;
+pub const PREVIEW_MIN_WIDTH: i32 = 400;
+/// Distance of the overlay from the monitor edge at the top and bottom positions.
+pub const POSITION_MARGIN: i32 = 80;
+/// Room at the sides for the arrows that mark scrolled out apps.
+pub const SCROLL_MARGIN: i32 = 16;
+/// Share of the icon's color in the highlight when tinting it.
+pub const ACCENT_TINT: f32 = 0.6;
+/// Opacity of the icons that are not selected when dimming them.
+pub const DIMMED_OPACITY: f32 = 0.4;
+/// Opacity of the background when the windows behind it are blurred.
+pub const BLUR_BACKGROUND_OPACITY: f32 = 0.6;
+pub const TEXT_DARK_COLOR: u32 = 0xffffff;
+pub const TEXT_LIGHT_COLOR: u32 = 0x000000;
+
+/// Left, top, width and height of something drawn in the overlay.
+type Bounds = (f32, f32, f32, f32);
+
+/// Overlay painter of the renderer selected in the config.
+pub enum Painter {
+    Gdi(GdiAAPainter),
+    D2d(D2dPainter),
+}
+
+impl Painter {
+    /// Falls back to GDI when Direct2D is not available.
+    pub fn new(hwnd: HWND, config: &Config) -> Result<Self> {
+        if config.overlay_renderer == Renderer::D2d {
+            match D2dPainter::new(hwnd, config) {
+                Ok(v) => return Ok(Self::D2d(v)),
+                Err(err) => error!("{err}, falling back to GDI"),
+            }
+        }
+        GdiAAPainter::new(hwnd, config).map(Self::Gdi)
+    }
+
+    /// Switches the renderer when it changed, keeping the current one if the new one fails.
+    pub fn update_config(&mut self, config: &Config) {
+        let (hwnd, renderer) = match self {
+            Self::Gdi(painter) => (painter.hwnd, Renderer::Gdi),
+            Self::D2d(painter) => (painter.hwnd(), Renderer::D2d),
+        };
+        if renderer != config.overlay_renderer {
+            match Self::new(hwnd, config) {
+                Ok(painter) => {
+                    *self = painter;
+                    return;
+                }
+                Err(err) => error!("{err}"),
+            }
+        }
+        match self {
+            Self::Gdi(painter) => painter.update_config(config),
+            Self::D2d(painter) => painter.update_config(config),
+        }
+    }
+
+    pub fn update_theme(&mut self) {
+        match self {
+            Self::Gdi(painter) => painter.update_theme(),
+            Self::D2d(painter) => painter.update_theme(),
+        }
+    }
+
+    pub fn paint(&mut self, state: &SwitchAppsState) {
+        match self {
+            Self::Gdi(painter) => painter.paint(state),
+            Self::D2d(painter) => painter.paint(state),
+        }
+    }
+
+    pub fn unpaint(&mut self, state: SwitchAppsState) {
+        match self {
+            Self::Gdi(painter) => painter.unpaint(state),
+            Self::D2d(painter) => painter.unpaint(state),
+        }
+    }
+
+    /// Steps the fade in or out of the overlay.
+    pub fn on_fade_timer(&mut self) {
+        match self {
+            Self::Gdi(painter) => painter.on_fade_timer(),
+            Self::D2d(painter) => painter.on_fade_timer(),
+        }
+    }
+
+    /// Steps the slide of the selection highlight.
+    pub fn on_slide_timer(&mut self, state: &SwitchAppsState) {
+        match self {
+            Self::Gdi(painter) => painter.on_slide_timer(state),
+            Self::D2d(painter) => painter.on_slide_timer(state),
+        }
+    }
+
+    pub fn find_clicked_app_index(&self, state: &SwitchAppsState) -> Option<usize> {
+        match self {
+            Self::Gdi(painter) => painter.find_clicked_app_index(state),
+            Self::D2d(painter) => painter.find_clicked_app_index(state),
+        }
+    }
+}
 
 // GDI Antialiasing Painter
 pub struct GdiAAPainter {
     token: usize,
     hwnd: HWND,
     hdc_screen: HDC,
-    rounded_corner: bool,
+    system_theme: SystemTheme,
+    theme: Theme,
+    layout: Layout,
+    thumbnails: Thumbnails,
+    fade: Fade,
+    slide: Slide,
+    mirrors: Mirrors,
+    /// First app shown when not all apps fit.
+    scroll: usize,
+    /// Whether the windows behind the overlay are blurred.
+    blur: bool,
+    /// Marks the apps running as administrator.
+    shield: Option<HICON>,
+    /// Fonts of the footer and the badges, of the scroll arrows and of the titles.
+    fonts: [Option<Font>; 3],
     show: bool,
 }
 
 impl GdiAAPainter {
-    pub fn new(hwnd: HWND) -> Result<Self> {
+    pub fn new(hwnd: HWND, config: &Config) -> Result<Self> {
         let startup_input = GdiplusStartupInput {
             GdiplusVersion: 1,
             ..Default::default()
@@ -60,37 +226,88 @@ impl GdiAAPainter {
             .context("Failed to initialize GDI+")?;
 
         let hdc_screen = unsafe { GetDC(Some(hwnd)) };
-        let rounded_corner = is_win11();
+        let layout = Layout::new(config);
+        layout.apply_window_attributes(hwnd);
 
         Ok(Self {
             token,
             hwnd,
             hdc_screen,
-            rounded_corner,
+            system_theme: SystemTheme::current(),
+            theme: config.theme,
+            layout,
+            thumbnails: Thumbnails::default(),
+            fade: Fade::new(config.overlay_animations),
+            slide: Slide::new(config.overlay_animations),
+            mirrors: Mirrors::new(hwnd, config),
+            scroll: 0,
+            blur: config.overlay_blur && set_window_blur(hwnd, true),
+            shield: get_shield_icon(),
+            fonts: Default::default(),
             show: false,
         })
     }
 
+    pub fn update_config(&mut self, config: &Config) {
+        self.theme = config.theme;
+        self.fade.set_enabled(config.overlay_animations);
+        self.slide.set_enabled(config.overlay_animations);
+        self.mirrors.update_config(config);
+        self.layout = Layout::new(config);
+        self.layout.apply_window_attributes(self.hwnd);
+        self.fonts = Default::default();
+        if config.overlay_blur != self.blur {
+            self.blur = set_window_blur(self.hwnd, config.overlay_blur) && config.overlay_blur;
+        }
+    }
+
+    /// Re-reads the system theme, the next paint uses its colors.
+    pub fn update_theme(&mut self) {
+        self.system_theme = SystemTheme::current();
+        debug!("system theme {:?}", self.system_theme);
+    }
+
     pub fn paint(&mut self, state: &SwitchAppsState) {
+        if !self.show {
+            self.fade.fade_in(self.hwnd);
+        }
+        self.scroll = scroll_to_selection(
+            self.scroll,
+            state.index,
+            state.apps.len(),
+            self.layout.max_items,
+        );
+        let coordinate = self.layout.coordinate(state, self.scroll);
         let Coordinate {
             x,
             y,
             width,
             height,
-            icon_size,
+            icons_x,
             item_size,
-        } = Coordinate::new(state.apps.len() as i32);
-
-        let corner_radius = if self.rounded_corner {
-            item_size / 4
-        } else {
-            0
-        };
+            corner_radius,
+            ..
+        } = coordinate;
 
         let hwnd = self.hwnd;
         let hdc_screen = self.hdc_screen;
 
-        let (fg_color, bg_color) = theme_color(is_light_theme());
+        let colors = Colors::new(&self.theme, &self.layout, self.system_theme).for_selection(
+            &self.theme,
+            self.system_theme,
+            state,
+        );
+        // The blur shows through a translucent background
+        let bg_color = if self.blur {
+            (((BLUR_BACKGROUND_OPACITY * 255.0) as u32) << 24) | colors.background
+        } else {
+            ALPHA_MASK | colors.background
+        };
+        let selection = self.slide.position(self.hwnd, coordinate.cell(state.index));
+        let [text_font, arrow_font, title_font] = &mut self.fonts;
+        let text_font = Font::of_size(text_font, coordinate.font_size);
+        let arrow_font = Font::of_size(arrow_font, coordinate.font_size * 1.5);
+        let title_font = Font::of_size(title_font, coordinate.title_font_size);
 
         unsafe {
             let hdc_mem = CreateCompatibleDC(Some(hdc_screen));
@@ -105,13 +322,13 @@ impl GdiAAPainter {
 
             let mut bg_pen = GpPen::default();
             let mut bg_pen_ptr: *mut GpPen = &mut bg_pen;
-            GdipCreatePen1(ALPHA_MASK | bg_color, 0.0, Unit(0), &mut bg_pen_ptr as _);
+            GdipCreatePen1(bg_color, 0.0, Unit(0), &mut bg_pen_ptr as _);
 
             let mut bg_brush = GpBrush::default();
             let mut bg_brush_ptr: *mut GpBrush = &mut bg_brush;
             GdipGetPenBrushFill(bg_pen_ptr, &mut bg_brush_ptr as _);
 
-            if self.rounded_corner {
+            if corner_radius > 0 {
                 draw_round_rect(
                     graphics_ptr,
                     bg_brush_ptr,
@@ -131,18 +348,39 @@ impl GdiAAPainter {
                     height as f32,
                 );
             }
+            if let Some(border_color) = colors.border
+                && coordinate.border_width > 0
+            {
+                draw_border(
+                    graphics_ptr,
+                    border_color,
+                    coordinate.border_width as f32,
+                    width as f32,
+                    height as f32,
+                    corner_radius as f32,
+                );
+            }
 
-            let icons_width = item_size * state.apps.len() as i32;
-            let icons_height = item_size;
+            let icons_width = item_size * coordinate.columns;
+            let icons_height = coordinate.row_height * coordinate.rows;
+            let has_thumbnail = if self.layout.show_thumbnails {
+                self.thumbnails.update(hwnd, state, &coordinate)
+            } else {
+                vec![]
+            };
+            if self.layout.show_preview || state.preview {
+                self.thumbnails.update_preview(hwnd, state, &coordinate);
+            } else {
+                self.thumbnails.clear_preview();
+            }
             let bitmap_icons = draw_icons(
                 state,
+                selection,
+                &has_thumbnail,
                 hdc_screen,
-                icon_size,
-                icons_width,
-                icons_height,
-                corner_radius,
-                fg_color,
-                bg_color,
+                &coordinate,
+                &colors,
+                &self.layout,
             );
 
             let mut bitmap = GpBitmap::default();
@@ -153,15 +391,89 @@ impl GdiAAPainter {
             GdipDrawImageRect(
                 graphics_ptr,
                 image_ptr,
-                WINDOW_BORDER_SIZE as f32,
-                WINDOW_BORDER_SIZE as f32,
+                icons_x as f32,
+                coordinate.border_size as f32,
                 icons_width as f32,
                 icons_height as f32,
             );
 
+            for (i, item) in coordinate.visible_apps(state) {
+                for (text, corner) in badges(&self.layout, i, item) {
+                    draw_badge(
+                        graphics_ptr,
+                        &text,
+                        coordinate.badge_rect(i, &text, corner),
+                        text_font,
+                        &colors,
+                    );
+                }
+                if item.elevated
+                    && let Some(shield) = self.shield
+                {
+                    draw_shield(graphics_ptr, shield, coordinate.shield_rect(i));
+                }
+            }
+
+            for (text, (left, top, width, height)) in coordinate.scroll_arrows() {
+                let rect = RectF {
+                    X: left,
+                    Y: top,
+                    Width: width,
+                    Height: height,
+                };
+                draw_text(
+                    graphics_ptr,
+                    text,
+                    &rect,
+                    arrow_font,
+                    colors.text,
+                    StringTrimmingEllipsisCharacter,
+                );
+            }
+
+            if self.layout.show_titles {
+                for (i, item) in coordinate.visible_apps(state) {
+                    let (left, top, width, height) = coordinate.title_rect(i);
+                    let rect = RectF {
+                        X: left,
+                        Y: top,
+                        Width: width,
+                        Height: height,
+                    };
+                    draw_text(
+                        graphics_ptr,
+                        &item.title,
+                        &rect,
+                        title_font,
+                        colors.text,
+                        StringTrimmingEllipsisCharacter,
+                    );
+                }
+            }
+
+            if self.layout.show_details
+                && let Some(details) = state.apps.get(state.index).and_then(|v| v.details.as_ref())
+            {
+                let (left, top, width, height) = coordinate.footer_rect();
+                let rect = RectF {
+                    X: left,
+                    Y: top,
+                    Width: width,
+                    Height: height,
+                };
+                draw_text(
+                    graphics_ptr,
+                    details,
+                    &rect,
+                    text_font,
+                    colors.text,
+                    StringTrimmingEllipsisPath,
+                );
+            }
+
             let blend = BLENDFUNCTION {
                 BlendOp: AC_SRC_OVER as _,
-                SourceConstantAlpha: 255,
+                SourceConstantAlpha: self.fade.alpha(self.theme.opacity),
                 AlphaFormat: AC_SRC_ALPHA as _,
                 ..Default::default()
             };
@@ -179,6 +491,15 @@ impl GdiAAPainter {
                 Some(&blend),
                 ULW_ALPHA,
             );
+            self.mirrors.update(
+                hdc_mem,
+                POINT { x, y },
+                SIZE {
+                    cx: width,
+                    cy: height,
+                },
+                &blend,
+            );
 
             GdipDisposeImage(image_ptr);
             GdipDeleteBrush(bg_brush_ptr);
@@ -201,41 +522,151 @@ impl GdiAAPainter {
     }
 
     pub fn unpaint(&mut self, _state: SwitchAppsState) {
-        unsafe {
-            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        self.thumbnails.clear();
+        self.slide.reset(self.hwnd);
+        self.scroll = 0;
+        if self.fade.fade_out(self.hwnd) {
+            unsafe {
+                let _ = ShowWindow(self.hwnd, SW_HIDE);
+            }
+            self.mirrors.hide();
         }
         self.show = false;
     }
+
+    pub fn on_fade_timer(&mut self) {
+        self.fade.tick(self.hwnd, self.theme.opacity);
+        self.mirrors.set_alpha(self.fade.alpha(self.theme.opacity));
+    }
+
+    pub fn on_slide_timer(&mut self, state: &SwitchAppsState) {
+        if self.slide.on_timer(self.hwnd) {
+            self.paint(state);
+        }
+    }
 }
 
 impl Drop for GdiAAPainter {
     fn drop(&mut self) {
+        self.thumbnails.clear();
+        if self.blur {
+            set_window_blur(self.hwnd, false);
+        }
         unsafe {
+            if let Some(shield) = self.shield {
+                let _ = DestroyIcon(shield);
+            }
             ReleaseDC(Some(self.hwnd), self.hdc_screen);
+            // The fonts are deleted while GDI+ is still running
+            self.fonts = Default::default();
             GdiplusShutdown(self.token);
         }
     }
 }
 
-pub fn find_clicked_app_index(state: &SwitchAppsState) -> Option<usize> {
-    let Coordinate {
-        x, y, item_size, ..
-    } = Coordinate::new(state.apps.len() as i32);
+impl GdiAAPainter {
+    pub fn find_clicked_app_index(&self, state: &SwitchAppsState) -> Option<usize> {
+        self.layout
+            .coordinate(state, self.scroll)
+            .app_index_at_cursor()
+    }
+}
 
-    let mut cursor_pos = POINT::default();
-    let _ = unsafe { GetCursorPos(&mut cursor_pos) };
+/// Overlay settings from the config that shape the layout, shared by the renderers.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    show_details: bool,
+    show_titles: bool,
+    max_rows: usize,
+    max_items: usize,
+    wrap: OverlayWrap,
+    show_thumbnails: bool,
+    icon_size: i32,
+    monitor: OverlayMonitor,
+    number_badges: bool,
+    window_count: bool,
+    minimized_badge: bool,
+    audio_badge: bool,
+    dim_icons: bool,
+    highlight_style: HighlightStyle,
+    hide_from_capture: bool,
+    corner_radius: Option<i32>,
+    border_width: Option<i32>,
+    show_preview: bool,
+    rtl: bool,
+    position: OverlayPosition,
+    offset: (i32, i32),
+}
+
+impl Layout {
+    fn new(config: &Config) -> Self {
+        Self {
+            show_details: config.overlay_show_details,
+            show_titles: config.overlay_show_titles,
+            max_rows: config.overlay_max_rows,
+            max_items: config.overlay_max_items,
+            wrap: config.overlay_wrap,
+            show_thumbnails: config.overlay_thumbnails,
+            icon_size: config.switch_apps_icon_size,
+            monitor: config.overlay_monitor,
+            number_badges: config.overlay_quick_select,
+            window_count: config.overlay_window_count,
+            minimized_badge: config.overlay_minimized_badge,
+            audio_badge: config.overlay_audio_badge,
+            dim_icons: config.overlay_dim_icons,
+            highlight_style: config.overlay_highlight_style,
+            hide_from_capture: config.overlay_hide_from_capture,
+            corner_radius: config.overlay_corner_radius,
+            border_width: config.overlay_border,
+            show_preview: config.overlay_preview,
+            rtl: config.overlay_rtl(),
+            position: config.overlay_position,
+            offset: config.overlay_offset,
+        }
+    }
 
-    let xpos = cursor_pos.x - x;
-    let ypos = cursor_pos.y - y;
+    /// Sets up the window as the layout wants it, the corner preference and capture exclusion.
+    fn apply_window_attributes(&self, hwnd: HWND) {
+        self.apply_corner_preference(hwnd);
+        set_window_capture_exclusion(hwnd, self.hide_from_capture);
+    }
 
-    let cy = WINDOW_BORDER_SIZE;
-    for (i, _) in state.apps.iter().enumerate() {
-        let cx = WINDOW_BORDER_SIZE + item_size * (i as i32);
-        if xpos >= cx && xpos < cx + item_size && ypos >= cy && ypos < cy + item_size {
-            return Some(i);
+    /// Asks Windows 11 to round the corners of the window like the drawn ones, or not at all.
+    fn apply_corner_preference(&self, hwnd: HWND) {
+        if !is_win11() {
+            return;
+        }
+        let preference = match self.corner_radius {
+            Some(0) => DWMWCP_DONOTROUND,
+            _ => DWMWCP_ROUND,
+        };
+        if let Err(err) = unsafe {
+            DwmSetWindowAttribute(
+                hwnd,
+                DWMWA_WINDOW_CORNER_PREFERENCE,
+                &preference as *const _ as _,
+                std::mem::size_of_val(&preference) as u32,
+            )
+        } {
+            debug!("Failed to set window corner preference, {err}");
         }
     }
-    None
+
+    /// Coordinate of the apps from `first` on, as many as fit. Room for the preview is also
+    /// left when the state asks for it.
+    fn coordinate(&self, state: &SwitchAppsState, first: usize) -> Coordinate {
+        let layout = Layout {
+            show_preview: self.show_preview || state.preview,
+            ..*self
+        };
+        Coordinate::new(state.apps.len(), first, &layout)
+    }
+
+    /// Whether the app's icon grows into its border, as the selected one does with the
+    /// scale highlight.
+    fn is_grown(&self, state: &SwitchAppsState, index: usize) -> bool {
+        self.highlight_style == HighlightStyle::Scale && index == state.index
+    }
 }
 
 const fn theme_color(light_theme: bool) -> (u32, u32) {
@@ -245,6 +676,229 @@ const fn theme_color(light_theme: bool) -> (u32, u32) {
     }
 }
 
+const fn theme_text_color(light_theme: bool) -> u32 {
+    match light_theme {
+        true => TEXT_LIGHT_COLOR,
+        false => TEXT_DARK_COLOR,
+    }
+}
+
+/// Theme of Windows the overlay follows.
+#[derive(Debug, Clone, Copy)]
+struct SystemTheme {
+    light: bool,
+    high_contrast: bool,
+}
+
+impl SystemTheme {
+    fn current() -> Self {
+        Self {
+            light: is_light_theme(),
+            high_contrast: is_high_contrast(),
+        }
+    }
+}
+
+/// Colors of the overlay as `0xRRGGBB`, the theme preset with the configured overrides.
+#[derive(Debug, Clone, Copy)]
+struct Colors {
+    background: u32,
+    highlight: u32,
+    border: Option<u32>,
+    text: u32,
+}
+
+impl Colors {
+    fn new(theme: &Theme, layout: &Layout, system_theme: SystemTheme) -> Self {
+        // Contrast themes win over the configured colors, they are an accessibility need
+        if system_theme.high_contrast {
+            return Self {
+                background: get_sys_color(COLOR_WINDOW),
+                highlight: get_sys_color(COLOR_HIGHLIGHT),
+                border: Some(get_sys_color(COLOR_WINDOWTEXT)),
+                text: get_sys_color(COLOR_WINDOWTEXT),
+            };
+        }
+        let light_theme = match theme.preset {
+            ThemePreset::Auto => system_theme.light,
+            ThemePreset::Dark => false,
+            ThemePreset::Light => true,
+        };
+        let (fg_color, bg_color) = theme_color(light_theme);
+        Self {
+            background: theme.background.unwrap_or(bg_color),
+            highlight: theme.highlight.unwrap_or(fg_color),
+            // A border width without a color borders in the highlight color
+            border: theme.border.or(layout
+                .border_width
+                .map(|_| theme.highlight.unwrap_or(fg_color))),
+            text: theme_text_color(light_theme),
+        }
+    }
+
+    /// Colors for the selected app, the highlight takes on some of its icon's color.
+    fn for_selection(
+        mut self,
+        theme: &Theme,
+        system_theme: SystemTheme,
+        state: &SwitchAppsState,
+    ) -> Self {
+        if theme.accent_from_icon
+            && !system_theme.high_contrast
+            && let Some(accent) = state.apps.get(state.index).and_then(|v| v.accent)
+        {
+            self.highlight = mix_colors(self.highlight, accent, ACCENT_TINT);
+        }
+        self
+    }
+}
+
+/// Blends `0xRRGGBB` colors, `amount` is the share of the second one.
+fn mix_colors(a: u32, b: u32, amount: f32) -> u32 {
+    let channel = |shift: u32| {
+        let (a, b) = (((a >> shift) & 0xff) as f32, ((b >> shift) & 0xff) as f32);
+        ((a + (b - a) * amount).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+/// GDI expects `0x00BBGGRR`.
+const fn to_colorref(color: u32) -> COLORREF {
+    COLORREF(((color & 0xff) << 16) | (color & 0xff00) | ((color >> 16) & 0xff))
+}
+
+/// Segoe UI in one size, kept by the painters until the size changes with the DPI of the
+/// monitor.
+pub(super) struct Font {
+    size: f32,
+    family: *mut GpFontFamily,
+    font: *mut GpFont,
+}
+
+impl Font {
+    pub(super) fn new(size: f32) -> Self {
+        let family_name = to_wstring("Segoe UI");
+        let mut family: *mut GpFontFamily = std::ptr::null_mut();
+        let mut font: *mut GpFont = std::ptr::null_mut();
+        unsafe {
+            GdipCreateFontFamilyFromName(
+                PCWSTR(family_name.as_ptr()),
+                std::ptr::null_mut(),
+                &mut family as _,
+            );
+            GdipCreateFont(family, size, FontStyleRegular.0, UnitPixel, &mut font as _);
+        }
+        Self { size, family, font }
+    }
+
+    /// The font of the size, created again only when the size differs.
+    pub(super) fn of_size(font: &mut Option<Self>, size: f32) -> *mut GpFont {
+        match font {
+            Some(v) if v.size == size => v.font,
+            _ => font.insert(Self::new(size)).font,
+        }
+    }
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        unsafe {
+            GdipDeleteFont(self.font);
+            GdipDeleteFontFamily(self.family);
+        }
+    }
+}
+
+/// Draws a single line of text centered in the rect, too long text is elided as trimming says.
+unsafe fn draw_text(
+    graphics_ptr: *mut GpGraphics,
+    text: &str,
+    rect: &RectF,
+    font_ptr: *mut GpFont,
+    color: u32,
+    trimming: StringTrimming,
+) {
+    unsafe {
+        let mut format_ptr: *mut GpStringFormat = std::ptr::null_mut();
+        GdipCreateStringFormat(0, 0, &mut format_ptr as _);
+        GdipSetStringFormatAlign(format_ptr, StringAlignmentCenter);
+        GdipSetStringFormatLineAlign(format_ptr, StringAlignmentCenter);
+        GdipSetStringFormatFlags(format_ptr, StringFormatFlagsNoWrap.0);
+        GdipSetStringFormatTrimming(format_ptr, trimming);
+
+        let mut brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+        GdipCreateSolidFill(ALPHA_MASK | color, &mut brush_ptr as _);
+
+        GdipSetTextRenderingHint(graphics_ptr, TextRenderingHintAntiAliasGridFit);
+        let text = to_wstring(text);
+        GdipDrawString(
+            graphics_ptr,
+            PCWSTR(text.as_ptr()),
+            -1,
+            font_ptr,
+            rect,
+            format_ptr,
+            brush_ptr as *const GpBrush,
+        );
+
+        GdipDeleteBrush(brush_ptr as *mut GpBrush);
+        GdipDeleteStringFormat(format_ptr);
+    }
+}
+
+/// Draws the shield icon into the rect.
+unsafe fn draw_shield(graphics: *mut GpGraphics, shield: HICON, rect: Bounds) {
+    let (left, top, width, height) = rect;
+    let mut bitmap_ptr: *mut GpBitmap = std::ptr::null_mut();
+    unsafe {
+        GdipCreateBitmapFromHICON(shield, &mut bitmap_ptr);
+        if bitmap_ptr.is_null() {
+            return;
+        }
+        let image_ptr = bitmap_ptr as *mut GpImage;
+        GdipDrawImageRect(graphics, image_ptr, left, top, width, height);
+        GdipDisposeImage(image_ptr);
+    }
+}
+
+/// Draws the text on a pill in the inverted colors of the overlay.
+unsafe fn draw_badge(
+    graphics_ptr: *mut GpGraphics,
+    text: &str,
+    (left, top, width, height): Bounds,
+    font_ptr: *mut GpFont,
+    colors: &Colors,
+) {
+    unsafe {
+        let mut brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+        GdipCreateSolidFill(ALPHA_MASK | colors.text, &mut brush_ptr as _);
+        draw_round_rect(
+            graphics_ptr,
+            brush_ptr as *mut GpBrush,
+            left,
+            top,
+            left + width,
+            top + height,
+            height,
+        );
+        GdipDeleteBrush(brush_ptr as *mut GpBrush);
+        let rect = RectF {
+            X: left,
+            Y: top,
+            Width: width,
+            Height: height,
+        };
+        draw_text(
+            graphics_ptr,
+            text,
+            &rect,
+            font_ptr,
+            colors.background,
+            StringTrimmingEllipsisCharacter,
+        );
+    }
+}
+
 unsafe fn draw_round_rect(
     graphic_ptr: *mut GpGraphics,
     brush_ptr: *mut GpBrush,
@@ -254,6 +908,59 @@ unsafe fn draw_round_rect(
     bottom: f32,
     corner_radius: f32,
 ) {
+    unsafe {
+        let path_ptr = round_rect_path(left, top, right, bottom, corner_radius);
+        GdipFillPath(graphic_ptr, brush_ptr, path_ptr);
+        GdipDeletePath(path_ptr);
+    }
+}
+
+/// Strokes a border along the edge of the overlay.
+unsafe fn draw_border(
+    graphic_ptr: *mut GpGraphics,
+    color: u32,
+    border_width: f32,
+    width: f32,
+    height: f32,
+    corner_radius: f32,
+) {
+    unsafe {
+        let mut pen_ptr: *mut GpPen = std::ptr::null_mut();
+        GdipCreatePen1(
+            ALPHA_MASK | color,
+            border_width,
+            UnitPixel,
+            &mut pen_ptr as _,
+        );
+        // Half the stroke in, so that it is not clipped by the window
+        let inset = border_width / 2.0;
+        if corner_radius > 0.0 {
+            let path_ptr =
+                round_rect_path(inset, inset, width - inset, height - inset, corner_radius);
+            GdipDrawPath(graphic_ptr, pen_ptr, path_ptr);
+            GdipDeletePath(path_ptr);
+        } else {
+            GdipDrawRectangle(
+                graphic_ptr,
+                pen_ptr,
+                inset,
+                inset,
+                width - border_width,
+                height - border_width,
+            );
+        }
+        GdipDeletePen(pen_ptr);
+    }
+}
+
+/// The caller deletes the returned path.
+unsafe fn round_rect_path(
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    corner_radius: f32,
+) -> *mut GpPath {
     unsafe {
         let mut path = GpPath::default();
         let mut path_ptr: *mut GpPath = &mut path;
@@ -295,28 +1002,32 @@ unsafe fn draw_round_rect(
             90.0,
         );
         GdipClosePathFigure(path_ptr);
-        GdipFillPath(graphic_ptr, brush_ptr, path_ptr);
-        GdipDeletePath(path_ptr);
+        path_ptr
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Draws the icons with the highlight at `selection`, the column and row it slid to, except
+/// of apps whose live thumbnail covers the cell.
 fn draw_icons(
     state: &SwitchAppsState,
+    selection: (f32, f32),
+    has_thumbnail: &[bool],
     hdc_screen: HDC,
-    icon_size: i32,
-    width: i32,
-    height: i32,
-    corner_radius: i32,
-    fg_color: u32,
-    bg_color: u32,
+    coordinate: &Coordinate,
+    colors: &Colors,
+    layout: &Layout,
 ) -> HBITMAP {
+    let width = coordinate.item_size * coordinate.columns;
+    let height = coordinate.row_height * coordinate.rows;
     let scaled_width = width * SCALE_FACTOR;
     let scaled_height = height * SCALE_FACTOR;
-    let scaled_corner_radius = corner_radius * SCALE_FACTOR;
-    let scaled_border_size = ICON_BORDER_SIZE * SCALE_FACTOR;
-    let scaled_icon_inner_size = icon_size * SCALE_FACTOR;
+    let scaled_corner_radius = coordinate.corner_radius * SCALE_FACTOR;
+    let scaled_border_size = coordinate.icon_border_size * SCALE_FACTOR;
+    let scaled_icon_inner_size = coordinate.icon_size * SCALE_FACTOR;
     let scaled_icon_outer_size = scaled_icon_inner_size + scaled_border_size * 2;
+    // The bitmap only holds the icons, scaled up to be drawn smoother
+    let scale_x = |x: i32| (x - coordinate.icons_x) * SCALE_FACTOR;
+    let scale_y = |y: i32| (y - coordinate.border_size) * SCALE_FACTOR;
 
     unsafe {
         let hdc_tmp = CreateCompatibleDC(Some(hdc_screen));
@@ -327,8 +1038,28 @@ fn draw_icons(
         let bitmap_scaled = CreateCompatibleBitmap(hdc_screen, scaled_width, scaled_height);
         SelectObject(hdc_scaled, bitmap_scaled.into());
 
-        let fg_brush = CreateSolidBrush(COLORREF(fg_color));
-        let bg_brush = CreateSolidBrush(COLORREF(bg_color));
+        let fg_brush = CreateSolidBrush(to_colorref(colors.highlight));
+        let bg_brush = CreateSolidBrush(to_colorref(colors.background));
+
+        // Background blended over the icons that are not selected to dim them
+        let hdc_dim = CreateCompatibleDC(Some(hdc_screen));
+        let bitmap_dim = CreateCompatibleBitmap(hdc_screen, 1, 1);
+        SelectObject(hdc_dim, bitmap_dim.into());
+        FillRect(
+            hdc_dim,
+            &RECT {
+                left: 0,
+                top: 0,
+                right: 1,
+                bottom: 1,
+            },
+            bg_brush,
+        );
+        let dim_blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as _,
+            SourceConstantAlpha: ((1.0 - DIMMED_OPACITY) * 255.0) as u8,
+            ..Default::default()
+        };
 
         let rect = RECT {
             left: 0,
@@ -339,11 +1070,51 @@ fn draw_icons(
 
         FillRect(hdc_scaled, &rect, bg_brush);
 
-        for (i, (icon, _)) in state.apps.iter().enumerate() {
-            // draw the box for selected icon
-            if i == state.index {
-                let left = scaled_icon_outer_size * (i as i32);
-                let top = 0;
+        // draw the box for selected icon
+        let (left, top, _, _) = coordinate.selection_rect(selection);
+        let left = ((left - coordinate.icons_x as f32) * SCALE_FACTOR as f32).round() as i32;
+        let top = ((top - coordinate.border_size as f32) * SCALE_FACTOR as f32).round() as i32;
+        match layout.highlight_style {
+            HighlightStyle::Fill | HighlightStyle::Outline => {
+                let rgn = CreateRoundRectRgn(
+                    left,
+                    top,
+                    left + scaled_icon_outer_size,
+                    top + scaled_icon_outer_size,
+                    scaled_corner_radius,
+                    scaled_corner_radius,
+                );
+                if layout.highlight_style == HighlightStyle::Fill {
+                    let _ = FillRgn(hdc_scaled, rgn, fg_brush);
+                } else {
+                    let _ = FrameRgn(
+                        hdc_scaled,
+                        rgn,
+                        fg_brush,
+                        scaled_border_size,
+                        scaled_border_size,
+                    );
+                }
+                let _ = DeleteObject(rgn.into());
+            }
+            HighlightStyle::Underline => {
+                let bar = RECT {
+                    left: left + scaled_border_size,
+                    top: top + scaled_icon_outer_size - scaled_border_size,
+                    right: left + scaled_icon_outer_size - scaled_border_size,
+                    bottom: top + scaled_icon_outer_size,
+                };
+                FillRect(hdc_scaled, &bar, fg_brush);
+            }
+            HighlightStyle::Scale => {}
+        }
+
+        for (i, item) in coordinate.visible_apps(state) {
+            // and a frame for the icon under the mouse
+            if i != state.index && state.hover == Some(i) {
+                let (left, top) = coordinate.cell_origin(i);
+                let left = scale_x(left);
+                let top = scale_y(top);
                 let right = left + scaled_icon_outer_size;
                 let bottom = top + scaled_icon_outer_size;
                 let rgn = CreateRoundRectRgn(
@@ -354,22 +1125,36 @@ fn draw_icons(
                     scaled_corner_radius,
                     scaled_corner_radius,
                 );
-                let _ = FillRgn(hdc_scaled, rgn, fg_brush);
+                let frame_size = 2 * SCALE_FACTOR;
+                let _ = FrameRgn(hdc_scaled, rgn, fg_brush, frame_size, frame_size);
                 let _ = DeleteObject(rgn.into());
             }
 
-            let cx = scaled_border_size + scaled_icon_outer_size * (i as i32);
+            if has_thumbnail.get(i).copied().unwrap_or_default() {
+                continue;
+            }
+            let icon = coordinate.icon_rect(i, layout.is_grown(state, i));
+            let cx = scale_x(icon.left);
+            let cy = scale_y(icon.top);
+            let size = (icon.right - icon.left) * SCALE_FACTOR;
             let _ = DrawIconEx(
-                hdc_scaled,
-                cx,
-                scaled_border_size,
-                *icon,
-                scaled_icon_inner_size,
-                scaled_icon_inner_size,
-                0,
-                None,
-                DI_NORMAL,
+                hdc_scaled, cx, cy, item.hicon, size, size, 0, None, DI_NORMAL,
             );
+            if layout.dim_icons && i != state.index {
+                let _ = AlphaBlend(
+                    hdc_scaled,
+                    cx,
+                    cy,
+                    scaled_icon_inner_size,
+                    scaled_icon_inner_size,
+                    hdc_dim,
+                    0,
+                    0,
+                    1,
+                    1,
+                    dim_blend,
+                );
+            }
         }
 
         SetStretchBltMode(hdc_tmp, HALFTONE);
@@ -389,6 +1174,8 @@ fn draw_icons(
 
         let _ = DeleteObject(fg_brush.into());
         let _ = DeleteObject(bg_brush.into());
+        let _ = DeleteObject(bitmap_dim.into());
+        let _ = DeleteDC(hdc_dim);
         let _ = DeleteObject(bitmap_scaled.into());
         let _ = DeleteDC(hdc_scaled);
         let _ = DeleteDC(hdc_tmp);
@@ -397,38 +1184,563 @@ fn draw_icons(
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum BadgeCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Badges drawn on the app's icon, with their corners.
+fn badges(layout: &Layout, index: usize, item: &SwitchAppsItem) -> Vec<(String, BadgeCorner)> {
+    let mut badges = vec![];
+    if layout.number_badges && index < 9 {
+        badges.push(((index + 1).to_string(), BadgeCorner::TopLeft));
+    }
+    if layout.window_count && item.windows > 1 {
+        badges.push((item.windows.to_string(), BadgeCorner::BottomRight));
+    }
+    // The minimized mark and the audio note share a corner
+    let mut bottom_left = String::new();
+    if layout.minimized_badge && item.minimized {
+        bottom_left.push('\u{2013}');
+    }
+    if layout.audio_badge && item.playing_audio {
+        bottom_left.push('\u{266A}');
+    }
+    if !bottom_left.is_empty() {
+        badges.push((bottom_left, BadgeCorner::BottomLeft));
+    }
+    // The shield of elevated apps takes the top right corner
+    if let Some(desktop) = item.desktop.filter(|_| !item.elevated) {
+        badges.push((format!("D{desktop}"), BadgeCorner::TopRight));
+    }
+    badges
+}
+
 struct Coordinate {
     x: i32,
     y: i32,
     width: i32,
     height: i32,
+    /// Left of the first icon, icons are centered when the footer widens the window.
+    icons_x: i32,
     icon_size: i32,
     item_size: i32,
+    /// Size of the icon and the title below it.
+    row_height: i32,
+    /// Sizes below are scaled to the DPI of the monitor.
+    border_size: i32,
+    icon_border_size: i32,
+    title_height: i32,
+    footer_height: i32,
+    preview_height: i32,
+    font_size: f32,
+    title_font_size: f32,
+    /// Roundness of the overlay and the selection, 0 for square corners.
+    corner_radius: i32,
+    border_width: i32,
+    columns: i32,
+    rows: i32,
+    wrap: OverlayWrap,
+    /// Whether the first column is on the right.
+    rtl: bool,
+    /// First app shown, and how many of all apps are shown from there.
+    first: usize,
+    count: usize,
+    total: usize,
+    /// Room at each side for the scroll arrows, 0 when all apps are shown.
+    scroll_margin: i32,
 }
 
 impl Coordinate {
-    fn new(num_apps: i32) -> Self {
-        let monitor_rect = get_moinitor_rect();
+    fn new(num_apps: usize, first: usize, layout: &Layout) -> Self {
+        let Layout {
+            show_details,
+            show_titles,
+            max_rows,
+            max_items,
+            wrap,
+            show_thumbnails,
+            icon_size,
+            monitor,
+            corner_radius,
+            border_width,
+            show_preview,
+            rtl,
+            position,
+            offset,
+            ..
+        } = *layout;
+        let hmonitor = get_overlay_monitor(monitor);
+        let monitor_rect = get_moinitor_rect(hmonitor);
         let monitor_width = monitor_rect.right - monitor_rect.left;
         let monitor_height = monitor_rect.bottom - monitor_rect.top;
+        let dpi = get_moinitor_dpi(hmonitor) as i32;
+        let scale = |v: i32| v * dpi / USER_DEFAULT_SCREEN_DPI as i32;
+        let border_size = scale(WINDOW_BORDER_SIZE);
+        // Borders grow and shrink with the icons
+        let icon_border_size = scale(ICON_BORDER_SIZE * icon_size / ICON_SIZE).max(1);
+        let footer_height = if show_details {
+            scale(FOOTER_HEIGHT)
+        } else {
+            0
+        };
+        let title_height = if show_titles { scale(TITLE_HEIGHT) } else { 0 };
+        let preview_height = if show_preview {
+            scale(PREVIEW_HEIGHT).min(monitor_height / 2)
+        } else {
+            0
+        };
+        let max_icon_size = scale(if show_thumbnails {
+            THUMBNAIL_SIZE
+        } else {
+            icon_size
+        });
 
-        let icon_size = ((monitor_width - 2 * WINDOW_BORDER_SIZE) / num_apps
-            - ICON_BORDER_SIZE * 2)
-            .min(ICON_SIZE);
+        let total = num_apps;
+        let count = match max_items {
+            0 => num_apps,
+            v => num_apps.min(v),
+        };
+        let first = first.min(total - count);
+        let scroll_margin = if count < total {
+            scale(SCROLL_MARGIN)
+        } else {
+            0
+        };
+        let num_apps = count.max(1) as i32;
+        let max_rows = max_rows.max(1) as i32;
+        let rows = match wrap {
+            OverlayWrap::Horizontal => {
+                // Rows are only added once the icons no longer fit at full size
+                let fit_columns = ((monitor_width - 2 * border_size - 2 * scroll_margin)
+                    / (max_icon_size + icon_border_size * 2))
+                    .max(1);
+                ((num_apps + fit_columns - 1) / fit_columns).min(max_rows)
+            }
+            OverlayWrap::Vertical => num_apps.min(max_rows),
+        };
+        let columns = (num_apps + rows - 1) / rows;
+
+        let max_item_width = (monitor_width - 2 * border_size - 2 * scroll_margin) / columns;
+        let max_item_height = (monitor_height - 2 * border_size - footer_height - preview_height)
+            / rows
+            - title_height;
+        let icon_size =
+            (max_item_width.min(max_item_height) - icon_border_size * 2).min(max_icon_size);
 
-        let item_size = icon_size + ICON_BORDER_SIZE * 2;
-        let width = item_size * num_apps + WINDOW_BORDER_SIZE * 2;
-        let height = item_size + WINDOW_BORDER_SIZE * 2;
-        let x = monitor_rect.left + (monitor_width - width) / 2;
-        let y = monitor_rect.top + (monitor_height - height) / 2;
+        let item_size = icon_size + icon_border_size * 2;
+        let row_height = item_size + title_height;
+        let icons_width = item_size * columns;
+        let mut width = icons_width + border_size * 2 + scroll_margin * 2;
+        let height = row_height * rows + border_size * 2 + preview_height + footer_height;
+        if show_details {
+            width = width.max(scale(FOOTER_MIN_WIDTH).min(monitor_width));
+        }
+        if show_preview {
+            width = width.max(scale(PREVIEW_MIN_WIDTH).min(monitor_width));
+        }
+        let icons_x = (width - icons_width) / 2;
+        let corner_radius = match corner_radius {
+            Some(v) => scale(v),
+            None if is_win11() => item_size / 4,
+            None => 0,
+        };
+        let border_width = scale(border_width.unwrap_or(1));
+        let y = match position {
+            OverlayPosition::Center => monitor_rect.top + (monitor_height - height) / 2,
+            OverlayPosition::Top => monitor_rect.top + scale(POSITION_MARGIN),
+            OverlayPosition::Bottom => monitor_rect.bottom - scale(POSITION_MARGIN) - height,
+        };
+        // The offset may not push the overlay off the monitor
+        let x = (monitor_rect.left + (monitor_width - width) / 2 + scale(offset.0)).clamp(
+            monitor_rect.left,
+            (monitor_rect.right - width).max(monitor_rect.left),
+        );
+        let y = (y + scale(offset.1)).clamp(
+            monitor_rect.top,
+            (monitor_rect.bottom - height).max(monitor_rect.top),
+        );
 
         Self {
             x,
             y,
             width,
             height,
+            icons_x,
             icon_size,
             item_size,
+            row_height,
+            border_size,
+            icon_border_size,
+            title_height,
+            footer_height,
+            preview_height,
+            font_size: FOOTER_FONT_SIZE * dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+            title_font_size: TITLE_FONT_SIZE * dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+            corner_radius,
+            border_width,
+            columns,
+            rows,
+            wrap,
+            rtl,
+            first,
+            count,
+            total,
+            scroll_margin,
         }
     }
+
+    /// Apps shown in the overlay, with their indexes.
+    fn visible_apps<'a>(
+        &self,
+        state: &'a SwitchAppsState,
+    ) -> impl Iterator<Item = (usize, &'a SwitchAppsItem)> {
+        state
+            .apps
+            .iter()
+            .enumerate()
+            .skip(self.first)
+            .take(self.count)
+    }
+
+    fn is_visible(&self, index: usize) -> bool {
+        index >= self.first && index < self.first + self.count
+    }
+
+    /// Arrows at the sides pointing at the apps scrolled out of view, with their bounds.
+    fn scroll_arrows(&self) -> Vec<(&'static str, Bounds)> {
+        let before = self.first > 0;
+        let after = self.first + self.count < self.total;
+        // The apps before the first shown one are on the right in right-to-left layouts
+        let (left, right) = if self.rtl {
+            (after, before)
+        } else {
+            (before, after)
+        };
+        let top = self.border_size as f32;
+        let width = self.scroll_margin as f32;
+        let height = (self.row_height * self.rows) as f32;
+        let mut arrows = vec![];
+        if left {
+            let x = (self.icons_x - self.scroll_margin) as f32;
+            arrows.push(("\u{2039}", (x, top, width, height)));
+        }
+        if right {
+            let x = (self.icons_x + self.item_size * self.columns) as f32;
+            arrows.push(("\u{203a}", (x, top, width, height)));
+        }
+        arrows
+    }
+
+    /// Index of the app under the mouse cursor.
+    fn app_index_at_cursor(&self) -> Option<usize> {
+        let mut cursor_pos = POINT::default();
+        let _ = unsafe { GetCursorPos(&mut cursor_pos) };
+
+        let xpos = cursor_pos.x - self.x;
+        let ypos = cursor_pos.y - self.y;
+
+        (self.first..self.first + self.count).find(|&i| {
+            let (cx, cy) = self.cell_origin(i);
+            xpos >= cx && xpos < cx + self.item_size && ypos >= cy && ypos < cy + self.row_height
+        })
+    }
+
+    /// Top left corner of the app's cell, which holds the icon and the title below it.
+    fn cell_origin(&self, index: usize) -> (i32, i32) {
+        let (column, row) = self.cell(index);
+        (
+            self.icons_x + self.item_size * column,
+            self.border_size + self.row_height * row,
+        )
+    }
+
+    /// Box of the highlight at `selection`, the column and row it slid to.
+    fn selection_rect(&self, selection: (f32, f32)) -> Bounds {
+        let size = self.item_size as f32;
+        (
+            self.icons_x as f32 + size * selection.0,
+            self.border_size as f32 + self.row_height as f32 * selection.1,
+            size,
+            size,
+        )
+    }
+
+    /// Square of the app's icon, a grown icon takes the border too, which is empty without
+    /// a box around it.
+    fn icon_rect(&self, index: usize, grown: bool) -> RECT {
+        let (left, top) = self.cell_origin(index);
+        let inset = if grown { 0 } else { self.icon_border_size };
+        RECT {
+            left: left + inset,
+            top: top + inset,
+            right: left + self.item_size - inset,
+            bottom: top + self.item_size - inset,
+        }
+    }
+
+    /// Area of the app's title, below its icon.
+    fn title_rect(&self, index: usize) -> Bounds {
+        let (left, top) = self.cell_origin(index);
+        (
+            left as f32,
+            (top + self.item_size) as f32,
+            self.item_size as f32,
+            self.title_height as f32,
+        )
+    }
+
+    /// Area of the preview of the selected window, between the icons and the footer.
+    fn preview_rect(&self) -> RECT {
+        let top = self.border_size + self.row_height * self.rows;
+        RECT {
+            left: self.border_size,
+            top,
+            right: self.width - self.border_size,
+            bottom: top + self.preview_height,
+        }
+    }
+
+    /// Area of the details of the selected app, at the bottom.
+    fn footer_rect(&self) -> Bounds {
+        (
+            self.border_size as f32,
+            (self.border_size + self.row_height * self.rows + self.preview_height) as f32,
+            (self.width - self.border_size * 2) as f32,
+            self.footer_height as f32,
+        )
+    }
+
+    /// Bounds of a badge in a corner of the app's icon.
+    fn badge_rect(&self, index: usize, text: &str, corner: BadgeCorner) -> Bounds {
+        let icon = self.icon_rect(index, false);
+        let size = (self.font_size * 1.5).round();
+        let width = size + (text.chars().count().max(1) - 1) as f32 * self.font_size * 0.6;
+        let left = icon.left as f32;
+        let top = icon.top as f32;
+        let inner_size = (icon.right - icon.left) as f32;
+        match corner {
+            BadgeCorner::TopLeft => (left, top, width, size),
+            BadgeCorner::TopRight => (left + inner_size - width, top, width, size),
+            BadgeCorner::BottomLeft => (left, top + inner_size - size, width, size),
+            BadgeCorner::BottomRight => (
+                left + inner_size - width,
+                top + inner_size - size,
+                width,
+                size,
+            ),
+        }
+    }
+
+    /// Square at the top right corner of the app's icon for the shield, as large as a badge.
+    fn shield_rect(&self, index: usize) -> Bounds {
+        let icon = self.icon_rect(index, false);
+        let size = (self.font_size * 1.5).round();
+        (icon.right as f32 - size, icon.top as f32, size, size)
+    }
+
+    /// Column and row of the app at the index, which has to be visible.
+    fn cell(&self, index: usize) -> (i32, i32) {
+        let index = index.saturating_sub(self.first) as i32;
+        let (column, row) = match self.wrap {
+            OverlayWrap::Horizontal => (index % self.columns, index / self.columns),
+            OverlayWrap::Vertical => (index / self.rows, index % self.rows),
+        };
+        if self.rtl {
+            (self.columns - 1 - column, row)
+        } else {
+            (column, row)
+        }
+    }
+}
+
+/// First app to show when only `max_items` of the apps fit, scrolled just far enough that
+/// the selected app is shown.
+fn scroll_to_selection(first: usize, index: usize, num_apps: usize, max_items: usize) -> usize {
+    if max_items == 0 || num_apps <= max_items {
+        return 0;
+    }
+    let first = first.min(num_apps - max_items);
+    if index < first {
+        index
+    } else if index >= first + max_items {
+        index + 1 - max_items
+    } else {
+        first
+    }
+}
+
+/// Monitor to center the overlay on.
+fn get_overlay_monitor(monitor: OverlayMonitor) -> HMONITOR {
+    unsafe {
+        match monitor {
+            OverlayMonitor::Primary => MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY),
+            OverlayMonitor::Cursor => {
+                let mut cursor = POINT::default();
+                let _ = GetCursorPos(&mut cursor);
+                MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST)
+            }
+            OverlayMonitor::Focused => {
+                MonitorFromWindow(GetForegroundWindow(), MONITOR_DEFAULTTONEAREST)
+            }
+        }
+    }
+}
+
+/// Live DWM previews of the candidate windows, drawn by DWM on top of the overlay.
+#[derive(Default)]
+struct Thumbnails {
+    registered: HashMap<isize, isize>,
+    /// Window and thumbnail of the preview of the selected app.
+    preview: Option<(isize, isize)>,
+}
+
+impl Thumbnails {
+    /// Registers previews for the apps and places them in their cells, stale ones are unregistered.
+    /// Returns which apps got a preview, the others keep their icon.
+    fn update(
+        &mut self,
+        hwnd: HWND,
+        state: &SwitchAppsState,
+        coordinate: &Coordinate,
+    ) -> Vec<bool> {
+        let mut registered = HashMap::new();
+        let mut has_thumbnail = vec![];
+        for (i, item) in state.apps.iter().enumerate() {
+            if !coordinate.is_visible(i) {
+                has_thumbnail.push(false);
+                continue;
+            }
+            let thumbnail = match self.registered.remove(&(item.hwnd.0 as isize)) {
+                Some(v) => Some(v),
+                None => match unsafe { DwmRegisterThumbnail(hwnd, item.hwnd) } {
+                    Ok(v) => Some(v),
+                    Err(err) => {
+                        debug!("Failed to register thumbnail of {:?}, {err}", item.hwnd);
+                        None
+                    }
+                },
+            };
+            let shown = match thumbnail {
+                Some(thumbnail) => {
+                    registered.insert(item.hwnd.0 as isize, thumbnail);
+                    place_thumbnail(thumbnail, coordinate, i)
+                }
+                None => false,
+            };
+            has_thumbnail.push(shown);
+        }
+        for (_, thumbnail) in self.registered.drain() {
+            let _ = unsafe { DwmUnregisterThumbnail(thumbnail) };
+        }
+        self.registered = registered;
+        has_thumbnail
+    }
+
+    /// Shows the selected app in the preview area, the area stays empty if that fails.
+    fn update_preview(&mut self, hwnd: HWND, state: &SwitchAppsState, coordinate: &Coordinate) {
+        let Some(item) = state.apps.get(state.index) else {
+            return;
+        };
+        let source = item.hwnd.0 as isize;
+        let thumbnail = match self.preview {
+            Some((preview_source, thumbnail)) if preview_source == source => thumbnail,
+            _ => {
+                self.clear_preview();
+                match unsafe { DwmRegisterThumbnail(hwnd, item.hwnd) } {
+                    Ok(v) => {
+                        self.preview = Some((source, v));
+                        v
+                    }
+                    Err(err) => {
+                        debug!("Failed to register preview of {:?}, {err}", item.hwnd);
+                        return;
+                    }
+                }
+            }
+        };
+        fit_thumbnail(thumbnail, coordinate.preview_rect());
+    }
+
+    fn clear_preview(&mut self) {
+        if let Some((_, thumbnail)) = self.preview.take() {
+            let _ = unsafe { DwmUnregisterThumbnail(thumbnail) };
+        }
+    }
+
+    fn clear(&mut self) {
+        for (_, thumbnail) in self.registered.drain() {
+            let _ = unsafe { DwmUnregisterThumbnail(thumbnail) };
+        }
+        self.clear_preview();
+    }
+}
+
+/// Fits the preview into the icon area of the cell, keeping the aspect ratio of the window.
+fn place_thumbnail(thumbnail: isize, coordinate: &Coordinate, index: usize) -> bool {
+    fit_thumbnail(thumbnail, coordinate.icon_rect(index, false))
+}
+
+/// Centers the thumbnail in the area, keeping the aspect ratio of the window.
+fn fit_thumbnail(thumbnail: isize, area: RECT) -> bool {
+    let size = match unsafe { DwmQueryThumbnailSourceSize(thumbnail) } {
+        Ok(v) if v.cx > 0 && v.cy > 0 => v,
+        _ => return false,
+    };
+    let area_width = area.right - area.left;
+    let area_height = area.bottom - area.top;
+    let (width, height) = if size.cx * area_height >= size.cy * area_width {
+        (area_width, area_width * size.cy / size.cx)
+    } else {
+        (area_height * size.cx / size.cy, area_height)
+    };
+    let left = area.left + (area_width - width) / 2;
+    let top = area.top + (area_height - height) / 2;
+    let properties = DWM_THUMBNAIL_PROPERTIES {
+        dwFlags: DWM_TNP_RECTDESTINATION
+            | DWM_TNP_OPACITY
+            | DWM_TNP_VISIBLE
+            | DWM_TNP_SOURCECLIENTAREAONLY,
+        rcDestination: RECT {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        },
+        opacity: 255,
+        fVisible: true.into(),
+        fSourceClientAreaOnly: false.into(),
+        ..Default::default()
+    };
+    unsafe { DwmUpdateThumbnailProperties(thumbnail, &properties) }.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_to_selection() {
+        // All apps fit
+        assert_eq!(scroll_to_selection(0, 5, 6, 0), 0);
+        assert_eq!(scroll_to_selection(3, 5, 6, 10), 0);
+        // Moving within the shown apps keeps the scroll
+        assert_eq!(scroll_to_selection(2, 4, 20, 5), 2);
+        // Moving past the last shown app scrolls by one
+        assert_eq!(scroll_to_selection(2, 7, 20, 5), 3);
+        // Moving before the first shown app, e.g. wrapping around
+        assert_eq!(scroll_to_selection(15, 0, 20, 5), 0);
+        // The apps got fewer
+        assert_eq!(scroll_to_selection(15, 6, 8, 5), 3);
+    }
+
+    #[test]
+    fn test_mix_colors() {
+        assert_eq!(mix_colors(0x000000, 0xffffff, 0.0), 0x000000);
+        assert_eq!(mix_colors(0x000000, 0xffffff, 1.0), 0xffffff);
+        assert_eq!(mix_colors(0x204060, 0x6080a0, 0.5), 0x406080);
+    }
 }
@@ -0,0 +1,765 @@
+//! Direct2D renderer of the switch-apps overlay, antialiased by Direct2D and DirectWrite.
+
+use super::fade::Fade;
+use super::mirrors::Mirrors;
+use super::slide::Slide;
+use super::{
+    BLUR_BACKGROUND_OPACITY, Bounds, Colors, Coordinate, DIMMED_OPACITY, Layout, SystemTheme,
+    Thumbnails, badges, scroll_to_selection,
+};
+use crate::app::SwitchAppsState;
+use crate::config::{Config, HighlightStyle, Theme};
+use crate::utils::{get_shield_icon, set_window_blur};
+
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use windows::Win32::{
+    Foundation::{COLORREF, D2DERR_RECREATE_TARGET, HWND, POINT, RECT, SIZE},
+    Graphics::{
+        Direct2D::{
+            Common::{D2D_RECT_F, D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_PIXEL_FORMAT},
+            D2D1_BITMAP_INTERPOLATION_MODE_LINEAR, D2D1_DRAW_TEXT_OPTIONS_CLIP,
+            D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_RENDER_TARGET_PROPERTIES,
+            D2D1_RENDER_TARGET_TYPE_DEFAULT, D2D1_ROUNDED_RECT, D2D1CreateFactory, ID2D1Bitmap,
+            ID2D1DCRenderTarget, ID2D1Factory, ID2D1RenderTarget, ID2D1SolidColorBrush,
+        },
+        DirectWrite::{
+            DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_WEIGHT_NORMAL, DWRITE_MEASURING_MODE_NATURAL,
+            DWRITE_PARAGRAPH_ALIGNMENT_CENTER, DWRITE_TEXT_ALIGNMENT_CENTER, DWRITE_TRIMMING,
+            DWRITE_TRIMMING_GRANULARITY_CHARACTER, DWRITE_WORD_WRAPPING_NO_WRAP,
+            DWriteCreateFactory, IDWriteFactory, IDWriteTextFormat,
+        },
+        Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM,
+        Gdi::{
+            AC_SRC_ALPHA, AC_SRC_OVER, BI_RGB, BITMAPINFO, BITMAPINFOHEADER, BLENDFUNCTION,
+            CreateCompatibleDC, CreateDIBSection, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDC,
+            HBITMAP, HDC, ReleaseDC, SelectObject,
+        },
+        Imaging::{
+            CLSID_WICImagingFactory, GUID_WICPixelFormat32bppPBGRA, IWICImagingFactory,
+            IWICPalette, WICBitmapDitherTypeNone, WICBitmapPaletteTypeMedianCut,
+        },
+    },
+    System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+    UI::{
+        Input::KeyboardAndMouse::SetFocus,
+        WindowsAndMessaging::{
+            DestroyIcon, HICON, SW_HIDE, SW_SHOW, ShowWindow, ULW_ALPHA, USER_DEFAULT_SCREEN_DPI,
+            UpdateLayeredWindow,
+        },
+    },
+};
+use windows::core::w;
+
+// Direct2D Painter
+pub struct D2dPainter {
+    hwnd: HWND,
+    hdc_screen: HDC,
+    factory: ID2D1Factory,
+    write_factory: IDWriteFactory,
+    wic_factory: IWICImagingFactory,
+    /// Holds the DIB the target draws into.
+    hdc_mem: HDC,
+    /// DIB of the overlay and its size.
+    surface: Option<(HBITMAP, SIZE)>,
+    /// Kept between paints, so are the brushes and the bitmaps created on it.
+    target: Option<Target>,
+    /// Icons converted to bitmaps of the target, by HICON.
+    bitmaps: RefCell<HashMap<isize, ID2D1Bitmap>>,
+    text_formats: Option<TextFormats>,
+    system_theme: SystemTheme,
+    theme: Theme,
+    layout: Layout,
+    thumbnails: Thumbnails,
+    fade: Fade,
+    slide: Slide,
+    mirrors: Mirrors,
+    /// First app shown when not all apps fit.
+    scroll: usize,
+    /// Whether the windows behind the overlay are blurred.
+    blur: bool,
+    /// Marks the apps running as administrator.
+    shield: Option<HICON>,
+    show: bool,
+}
+
+impl D2dPainter {
+    pub fn new(hwnd: HWND, config: &Config) -> Result<Self> {
+        let factory: ID2D1Factory =
+            unsafe { D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None) }
+                .map_err(|err| anyhow!("Failed to create Direct2D factory, {err}"))?;
+        let write_factory: IDWriteFactory =
+            unsafe { DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED) }
+                .map_err(|err| anyhow!("Failed to create DirectWrite factory, {err}"))?;
+        let wic_factory: IWICImagingFactory =
+            unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) }
+                .map_err(|err| anyhow!("Failed to create WIC factory, {err}"))?;
+
+        let hdc_screen = unsafe { GetDC(Some(hwnd)) };
+        let hdc_mem = unsafe { CreateCompatibleDC(Some(hdc_screen)) };
+        let layout = Layout::new(config);
+        layout.apply_window_attributes(hwnd);
+
+        Ok(Self {
+            hwnd,
+            hdc_screen,
+            factory,
+            write_factory,
+            wic_factory,
+            hdc_mem,
+            surface: None,
+            target: None,
+            bitmaps: Default::default(),
+            text_formats: None,
+            system_theme: SystemTheme::current(),
+            theme: config.theme,
+            layout,
+            thumbnails: Thumbnails::default(),
+            fade: Fade::new(config.overlay_animations),
+            slide: Slide::new(config.overlay_animations),
+            mirrors: Mirrors::new(hwnd, config),
+            scroll: 0,
+            blur: config.overlay_blur && set_window_blur(hwnd, true),
+            shield: get_shield_icon(),
+            show: false,
+        })
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
+    pub fn update_config(&mut self, config: &Config) {
+        self.theme = config.theme;
+        self.fade.set_enabled(config.overlay_animations);
+        self.slide.set_enabled(config.overlay_animations);
+        self.mirrors.update_config(config);
+        self.layout = Layout::new(config);
+        self.layout.apply_window_attributes(self.hwnd);
+        // The icons are loaded again after a reload
+        self.bitmaps.borrow_mut().clear();
+        if config.overlay_blur != self.blur {
+            self.blur = set_window_blur(self.hwnd, config.overlay_blur) && config.overlay_blur;
+        }
+    }
+
+    /// Re-reads the system theme, the next paint uses its colors.
+    pub fn update_theme(&mut self) {
+        self.system_theme = SystemTheme::current();
+        debug!("system theme {:?}", self.system_theme);
+    }
+
+    pub fn paint(&mut self, state: &SwitchAppsState) {
+        if !self.show {
+            self.fade.fade_in(self.hwnd);
+        }
+        self.scroll = scroll_to_selection(
+            self.scroll,
+            state.index,
+            state.apps.len(),
+            self.layout.max_items,
+        );
+        let coordinate = self.layout.coordinate(state, self.scroll);
+        let has_thumbnail = if self.layout.show_thumbnails {
+            self.thumbnails.update(self.hwnd, state, &coordinate)
+        } else {
+            vec![]
+        };
+        if self.layout.show_preview || state.preview {
+            self.thumbnails
+                .update_preview(self.hwnd, state, &coordinate);
+        } else {
+            self.thumbnails.clear_preview();
+        }
+
+        let selection = self.slide.position(self.hwnd, coordinate.cell(state.index));
+
+        if let Err(err) = self.render(state, selection, &coordinate, &has_thumbnail) {
+            error!("Failed to paint overlay with Direct2D, {err}");
+            return;
+        }
+
+        if self.show {
+            return;
+        }
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_SHOW);
+            let _ = SetFocus(Some(self.hwnd));
+        }
+        self.show = true;
+    }
+
+    pub fn unpaint(&mut self, _state: SwitchAppsState) {
+        self.thumbnails.clear();
+        self.slide.reset(self.hwnd);
+        self.scroll = 0;
+        if self.fade.fade_out(self.hwnd) {
+            unsafe {
+                let _ = ShowWindow(self.hwnd, SW_HIDE);
+            }
+            self.mirrors.hide();
+        }
+        self.show = false;
+    }
+
+    pub fn on_fade_timer(&mut self) {
+        self.fade.tick(self.hwnd, self.theme.opacity);
+        self.mirrors.set_alpha(self.fade.alpha(self.theme.opacity));
+    }
+
+    pub fn on_slide_timer(&mut self, state: &SwitchAppsState) {
+        if self.slide.on_timer(self.hwnd) {
+            self.paint(state);
+        }
+    }
+
+    pub fn find_clicked_app_index(&self, state: &SwitchAppsState) -> Option<usize> {
+        self.layout
+            .coordinate(state, self.scroll)
+            .app_index_at_cursor()
+    }
+
+    /// Draws the overlay into the DIB and pushes it to the layered window.
+    fn render(
+        &mut self,
+        state: &SwitchAppsState,
+        selection: (f32, f32),
+        coordinate: &Coordinate,
+        has_thumbnail: &[bool],
+    ) -> windows::core::Result<()> {
+        let Coordinate {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } = *coordinate;
+
+        let target = self.bind_target(width, height)?;
+        let text_formats = self.text_formats(coordinate)?;
+        let result = unsafe {
+            self.draw(
+                &target,
+                &text_formats,
+                state,
+                selection,
+                coordinate,
+                has_thumbnail,
+            )
+        };
+        if let Err(err) = &result
+            && err.code() == D2DERR_RECREATE_TARGET
+        {
+            // E.g. the device was lost, the next paint starts over with a new target
+            self.target = None;
+            self.bitmaps.borrow_mut().clear();
+        }
+        result?;
+
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as _,
+            SourceConstantAlpha: self.fade.alpha(self.theme.opacity),
+            AlphaFormat: AC_SRC_ALPHA as _,
+            ..Default::default()
+        };
+        let size = SIZE {
+            cx: width,
+            cy: height,
+        };
+        unsafe {
+            let _ = UpdateLayeredWindow(
+                self.hwnd,
+                Some(self.hdc_screen),
+                Some(&POINT { x, y }),
+                Some(&size),
+                Some(self.hdc_mem),
+                Some(&POINT::default()),
+                COLORREF(0),
+                Some(&blend),
+                ULW_ALPHA,
+            );
+        }
+        self.mirrors
+            .update(self.hdc_mem, POINT { x, y }, size, &blend);
+        Ok(())
+    }
+
+    /// Render target bound to a 32-bit DIB of the overlay size. The DIB is only replaced
+    /// when the size changes, the target only when it was lost.
+    fn bind_target(&mut self, width: i32, height: i32) -> windows::core::Result<Target> {
+        let size = SIZE {
+            cx: width,
+            cy: height,
+        };
+        let resized = self.surface.is_none_or(|(_, v)| v != size);
+        if resized {
+            let bitmap_info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    biHeight: -height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut bits = std::ptr::null_mut();
+            unsafe {
+                let bitmap = CreateDIBSection(
+                    Some(self.hdc_mem),
+                    &bitmap_info,
+                    DIB_RGB_COLORS,
+                    &mut bits,
+                    None,
+                    0,
+                )?;
+                SelectObject(self.hdc_mem, bitmap.into());
+                if let Some((previous, _)) = self.surface.replace((bitmap, size)) {
+                    let _ = DeleteObject(previous.into());
+                }
+            }
+        }
+        if let Some(target) = &self.target
+            && !resized
+        {
+            return Ok(target.clone());
+        }
+
+        let properties = D2D1_RENDER_TARGET_PROPERTIES {
+            r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            // Coordinates are in pixels, they are already scaled to the DPI of the monitor
+            dpiX: USER_DEFAULT_SCREEN_DPI as f32,
+            dpiY: USER_DEFAULT_SCREEN_DPI as f32,
+            ..Default::default()
+        };
+        let target = match self.target.take() {
+            Some(v) => v,
+            None => Target::new(unsafe { self.factory.CreateDCRenderTarget(&properties)? })?,
+        };
+        let rect = RECT {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+        unsafe { target.target.BindDC(self.hdc_mem, &rect)? };
+        self.target = Some(target.clone());
+        Ok(target)
+    }
+
+    /// Text formats of the font sizes of the monitor, created again when they change.
+    fn text_formats(&mut self, coordinate: &Coordinate) -> windows::core::Result<TextFormats> {
+        let sizes = (coordinate.font_size, coordinate.title_font_size);
+        if let Some(formats) = &self.text_formats
+            && formats.sizes == sizes
+        {
+            return Ok(formats.clone());
+        }
+        let formats = TextFormats {
+            sizes,
+            text: create_text_format(&self.write_factory, coordinate.font_size, false)?,
+            title: create_text_format(&self.write_factory, coordinate.title_font_size, false)?,
+            details: create_text_format(&self.write_factory, coordinate.font_size, true)?,
+        };
+        self.text_formats = Some(formats.clone());
+        Ok(formats)
+    }
+
+    /// Draws the overlay with the highlight at `selection`, the column and row it slid to.
+    unsafe fn draw(
+        &self,
+        target: &Target,
+        text_formats: &TextFormats,
+        state: &SwitchAppsState,
+        selection: (f32, f32),
+        coordinate: &Coordinate,
+        has_thumbnail: &[bool],
+    ) -> windows::core::Result<()> {
+        let Coordinate { width, height, .. } = *coordinate;
+        let corner_radius = coordinate.corner_radius as f32;
+        let colors = Colors::new(&self.theme, &self.layout, self.system_theme).for_selection(
+            &self.theme,
+            self.system_theme,
+            state,
+        );
+        let brushes = &target.brushes;
+        brushes.set_colors(&colors, self.blur);
+        let target: &ID2D1RenderTarget = &target.target;
+
+        unsafe {
+            target.BeginDraw();
+            target.Clear(None);
+            target.FillRoundedRectangle(
+                &rounded_rect(0, 0, width, height, corner_radius),
+                &brushes.fill,
+            );
+            if colors.border.is_some() && coordinate.border_width > 0 {
+                let border_width = coordinate.border_width as f32;
+                // Half the stroke in, so that it is not clipped by the window
+                let inset = border_width / 2.0;
+                let rect = D2D1_ROUNDED_RECT {
+                    rect: D2D_RECT_F {
+                        left: inset,
+                        top: inset,
+                        right: width as f32 - inset,
+                        bottom: height as f32 - inset,
+                    },
+                    radiusX: corner_radius,
+                    radiusY: corner_radius,
+                };
+                target.DrawRoundedRectangle(&rect, &brushes.border, border_width, None);
+            }
+
+            let rect = D2D1_ROUNDED_RECT {
+                rect: to_rect_f(coordinate.selection_rect(selection)),
+                radiusX: corner_radius,
+                radiusY: corner_radius,
+            };
+            match self.layout.highlight_style {
+                HighlightStyle::Fill => target.FillRoundedRectangle(&rect, &brushes.highlight),
+                HighlightStyle::Outline => {
+                    let stroke = coordinate.icon_border_size as f32;
+                    let inset = stroke / 2.0;
+                    let rect = D2D1_ROUNDED_RECT {
+                        rect: D2D_RECT_F {
+                            left: rect.rect.left + inset,
+                            top: rect.rect.top + inset,
+                            right: rect.rect.right - inset,
+                            bottom: rect.rect.bottom - inset,
+                        },
+                        ..rect
+                    };
+                    target.DrawRoundedRectangle(&rect, &brushes.highlight, stroke, None);
+                }
+                HighlightStyle::Underline => {
+                    let bar_size = coordinate.icon_border_size as f32;
+                    let bar = D2D_RECT_F {
+                        left: rect.rect.left + bar_size,
+                        top: rect.rect.bottom - bar_size,
+                        right: rect.rect.right - bar_size,
+                        bottom: rect.rect.bottom,
+                    };
+                    target.FillRectangle(&bar, &brushes.highlight);
+                }
+                HighlightStyle::Scale => {}
+            }
+
+            for (i, item) in coordinate.visible_apps(state) {
+                if i != state.index && state.hover == Some(i) {
+                    let (left, top) = coordinate.cell_origin(i);
+                    let size = coordinate.item_size - 2;
+                    target.DrawRoundedRectangle(
+                        &rounded_rect(left + 1, top + 1, size, size, corner_radius),
+                        &brushes.highlight,
+                        2.0,
+                        None,
+                    );
+                }
+                if !has_thumbnail.get(i).copied().unwrap_or_default() {
+                    let opacity = if self.layout.dim_icons && i != state.index {
+                        DIMMED_OPACITY
+                    } else {
+                        1.0
+                    };
+                    let icon = coordinate.icon_rect(i, self.layout.is_grown(state, i));
+                    self.draw_icon(
+                        target,
+                        item.hicon,
+                        icon.left,
+                        icon.top,
+                        icon.right - icon.left,
+                        opacity,
+                    );
+                }
+                if self.layout.show_titles {
+                    draw_text(
+                        target,
+                        &item.title,
+                        &text_formats.title,
+                        &to_rect_f(coordinate.title_rect(i)),
+                        &brushes.text,
+                    );
+                }
+            }
+
+            for (i, item) in coordinate.visible_apps(state) {
+                for (text, corner) in badges(&self.layout, i, item) {
+                    let bounds = coordinate.badge_rect(i, &text, corner);
+                    let rect = D2D1_ROUNDED_RECT {
+                        rect: to_rect_f(bounds),
+                        radiusX: bounds.3 / 2.0,
+                        radiusY: bounds.3 / 2.0,
+                    };
+                    target.FillRoundedRectangle(&rect, &brushes.text);
+                    draw_text(
+                        target,
+                        &text,
+                        &text_formats.text,
+                        &rect.rect,
+                        &brushes.background,
+                    );
+                }
+                if item.elevated
+                    && let Some(shield) = self.shield
+                {
+                    let (left, top, size, _) = coordinate.shield_rect(i);
+                    self.draw_icon(target, shield, left as i32, top as i32, size as i32, 1.0);
+                }
+            }
+
+            for (text, bounds) in coordinate.scroll_arrows() {
+                draw_text(
+                    target,
+                    text,
+                    &text_formats.text,
+                    &to_rect_f(bounds),
+                    &brushes.text,
+                );
+            }
+
+            if self.layout.show_details
+                && let Some(details) = state.apps.get(state.index).and_then(|v| v.details.as_ref())
+            {
+                draw_text(
+                    target,
+                    details,
+                    &text_formats.details,
+                    &to_rect_f(coordinate.footer_rect()),
+                    &brushes.text,
+                );
+            }
+
+            target.EndDraw(None, None)
+        }
+    }
+
+    /// Converts the icon through WIC once, icons that fail to convert are skipped.
+    unsafe fn draw_icon(
+        &self,
+        target: &ID2D1RenderTarget,
+        hicon: HICON,
+        left: i32,
+        top: i32,
+        size: i32,
+        opacity: f32,
+    ) {
+        let cached = self.bitmaps.borrow().get(&(hicon.0 as isize)).cloned();
+        let bitmap = match cached {
+            Some(v) => Ok(v),
+            None => unsafe {
+                self.wic_factory
+                    .CreateBitmapFromHICON(hicon)
+                    .and_then(|source| {
+                        let converter = self.wic_factory.CreateFormatConverter()?;
+                        converter.Initialize(
+                            &source,
+                            &GUID_WICPixelFormat32bppPBGRA,
+                            WICBitmapDitherTypeNone,
+                            None::<&IWICPalette>,
+                            0.0,
+                            WICBitmapPaletteTypeMedianCut,
+                        )?;
+                        target.CreateBitmapFromWicBitmap(&converter, None)
+                    })
+                    .inspect(|bitmap| {
+                        self.bitmaps
+                            .borrow_mut()
+                            .insert(hicon.0 as isize, bitmap.clone());
+                    })
+            },
+        };
+        match bitmap {
+            Ok(bitmap) => unsafe {
+                target.DrawBitmap(
+                    &bitmap,
+                    Some(&to_rect(left, top, size, size)),
+                    opacity,
+                    D2D1_BITMAP_INTERPOLATION_MODE_LINEAR,
+                    None,
+                );
+            },
+            Err(err) => debug!("Failed to convert icon {hicon:?}, {err}"),
+        }
+    }
+}
+
+unsafe fn draw_text<P>(
+    target: &ID2D1RenderTarget,
+    text: &str,
+    text_format: &IDWriteTextFormat,
+    rect: &D2D_RECT_F,
+    brush: P,
+) where
+    P: windows::core::Param<windows::Win32::Graphics::Direct2D::ID2D1Brush>,
+{
+    let text: Vec<u16> = text.encode_utf16().collect();
+    unsafe {
+        target.DrawText(
+            &text,
+            text_format,
+            rect,
+            brush,
+            D2D1_DRAW_TEXT_OPTIONS_CLIP,
+            DWRITE_MEASURING_MODE_NATURAL,
+        );
+    }
+}
+
+impl Drop for D2dPainter {
+    fn drop(&mut self) {
+        self.thumbnails.clear();
+        if self.blur {
+            set_window_blur(self.hwnd, false);
+        }
+        unsafe {
+            if let Some(shield) = self.shield {
+                let _ = DestroyIcon(shield);
+            }
+            // The DIB is no longer selected once the DC is gone
+            let _ = DeleteDC(self.hdc_mem);
+            if let Some((bitmap, _)) = self.surface {
+                let _ = DeleteObject(bitmap.into());
+            }
+            ReleaseDC(Some(self.hwnd), self.hdc_screen);
+        }
+    }
+}
+
+/// DC render target with the brushes created on it.
+#[derive(Clone)]
+struct Target {
+    target: ID2D1DCRenderTarget,
+    brushes: Brushes,
+}
+
+impl Target {
+    fn new(target: ID2D1DCRenderTarget) -> windows::core::Result<Self> {
+        let brushes = Brushes::new(&target)?;
+        Ok(Self { target, brushes })
+    }
+}
+
+/// Brushes of the overlay, their colors are set before each paint.
+#[derive(Clone)]
+struct Brushes {
+    background: ID2D1SolidColorBrush,
+    /// Background of the overlay, translucent when the windows behind are blurred.
+    fill: ID2D1SolidColorBrush,
+    highlight: ID2D1SolidColorBrush,
+    text: ID2D1SolidColorBrush,
+    border: ID2D1SolidColorBrush,
+}
+
+impl Brushes {
+    fn new(target: &ID2D1RenderTarget) -> windows::core::Result<Self> {
+        let create = || unsafe { target.CreateSolidColorBrush(&to_color(0), None) };
+        Ok(Self {
+            background: create()?,
+            fill: create()?,
+            highlight: create()?,
+            text: create()?,
+            border: create()?,
+        })
+    }
+
+    fn set_colors(&self, colors: &Colors, blur: bool) {
+        let mut fill = to_color(colors.background);
+        if blur {
+            fill.a = BLUR_BACKGROUND_OPACITY;
+        }
+        unsafe {
+            self.background.SetColor(&to_color(colors.background));
+            self.fill.SetColor(&fill);
+            self.highlight.SetColor(&to_color(colors.highlight));
+            self.text.SetColor(&to_color(colors.text));
+            if let Some(border) = colors.border {
+                self.border.SetColor(&to_color(border));
+            }
+        }
+    }
+}
+
+/// Text formats of the footer and the badges, of the titles and of the details.
+#[derive(Clone)]
+struct TextFormats {
+    /// Font sizes of the footer and of the titles they were created for.
+    sizes: (f32, f32),
+    text: IDWriteTextFormat,
+    title: IDWriteTextFormat,
+    details: IDWriteTextFormat,
+}
+
+/// Centered single line text, elided at the end when too long. Paths are elided in the
+/// middle instead, keeping the file name after the last backslash.
+fn create_text_format(
+    write_factory: &IDWriteFactory,
+    font_size: f32,
+    path: bool,
+) -> windows::core::Result<IDWriteTextFormat> {
+    unsafe {
+        let format = write_factory.CreateTextFormat(
+            w!("Segoe UI"),
+            None,
+            DWRITE_FONT_WEIGHT_NORMAL,
+            DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_STRETCH_NORMAL,
+            font_size,
+            w!(""),
+        )?;
+        format.SetTextAlignment(DWRITE_TEXT_ALIGNMENT_CENTER)?;
+        format.SetParagraphAlignment(DWRITE_PARAGRAPH_ALIGNMENT_CENTER)?;
+        format.SetWordWrapping(DWRITE_WORD_WRAPPING_NO_WRAP)?;
+        let sign = write_factory.CreateEllipsisTrimmingSign(&format)?;
+        let trimming = DWRITE_TRIMMING {
+            granularity: DWRITE_TRIMMING_GRANULARITY_CHARACTER,
+            delimiter: if path { '\\' as u32 } else { 0 },
+            delimiterCount: path as u32,
+        };
+        format.SetTrimming(&trimming, &sign)?;
+        Ok(format)
+    }
+}
+
+/// Opaque color from a `0xRRGGBB` value.
+fn to_color(color: u32) -> D2D1_COLOR_F {
+    D2D1_COLOR_F {
+        r: ((color >> 16) & 0xff) as f32 / 255.0,
+        g: ((color >> 8) & 0xff) as f32 / 255.0,
+        b: (color & 0xff) as f32 / 255.0,
+        a: 1.0,
+    }
+}
+
+fn to_rect(left: i32, top: i32, width: i32, height: i32) -> D2D_RECT_F {
+    D2D_RECT_F {
+        left: left as f32,
+        top: top as f32,
+        right: (left + width) as f32,
+        bottom: (top + height) as f32,
+    }
+}
+
+fn to_rect_f((left, top, width, height): Bounds) -> D2D_RECT_F {
+    D2D_RECT_F {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    }
+}
+
+fn rounded_rect(left: i32, top: i32, width: i32, height: i32, radius: f32) -> D2D1_ROUNDED_RECT {
+    D2D1_ROUNDED_RECT {
+        rect: to_rect(left, top, width, height),
+        radiusX: radius,
+        radiusY: radius,
+    }
+}
@@ -0,0 +1,182 @@
+//! Fades the layered overlay window in and out by ramping its alpha on a timer.
+
+use crate::app::TIMER_FADE;
+use crate::utils::is_remote_session;
+
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::{COLORREF, HWND},
+    Graphics::Gdi::{AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION},
+    UI::WindowsAndMessaging::{
+        GWL_EXSTYLE, GetWindowLongPtrW, KillTimer, SW_HIDE, SetTimer, SetWindowLongPtrW,
+        ShowWindow, ULW_ALPHA, UpdateLayeredWindow, WS_EX_TRANSPARENT,
+    },
+};
+
+const FADE_DURATION: Duration = Duration::from_millis(80);
+const FADE_INTERVAL: u32 = 10;
+
+pub(super) struct Fade {
+    enabled: bool,
+    /// Visibility of the window from 0.0 to 1.0, scaled by the theme opacity.
+    level: f32,
+    ramp: Option<Ramp>,
+}
+
+struct Ramp {
+    fade_in: bool,
+    from: f32,
+    started: Instant,
+}
+
+impl Fade {
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            level: 0.0,
+            ramp: None,
+        }
+    }
+
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Alpha to paint the window with right now.
+    pub(super) fn alpha(&self, opacity: u8) -> u8 {
+        (opacity as f32 * self.level).round() as u8
+    }
+
+    /// Starts fading in from the current level, the window is shown at once when disabled.
+    pub(super) fn fade_in(&mut self, hwnd: HWND) {
+        if !is_animated(self.enabled, is_remote_session()) {
+            self.stop(hwnd);
+            self.level = 1.0;
+            return;
+        }
+        self.start(hwnd, true);
+    }
+
+    /// Starts fading out. Returns true when the window should be hidden at once.
+    pub(super) fn fade_out(&mut self, hwnd: HWND) -> bool {
+        if !is_animated(self.enabled, is_remote_session()) || self.level <= 0.0 {
+            self.stop(hwnd);
+            self.level = 0.0;
+            return true;
+        }
+        self.start(hwnd, false);
+        false
+    }
+
+    /// Steps the ramp on the timer, hiding the window once it faded out.
+    pub(super) fn tick(&mut self, hwnd: HWND, opacity: u8) {
+        let Some(ramp) = &self.ramp else {
+            // Left over from a painter replaced by a config reload
+            let _ = unsafe { KillTimer(Some(hwnd), TIMER_FADE) };
+            return;
+        };
+        let progress = ramp.started.elapsed().as_secs_f32() / FADE_DURATION.as_secs_f32();
+        let (level, done) = if ramp.fade_in {
+            let level = (ramp.from + progress).min(1.0);
+            (level, level >= 1.0)
+        } else {
+            let level = (ramp.from - progress).max(0.0);
+            (level, level <= 0.0)
+        };
+        let fade_in = ramp.fade_in;
+        self.level = level;
+        set_window_alpha(hwnd, self.alpha(opacity));
+        if done {
+            self.stop(hwnd);
+            if !fade_in {
+                unsafe {
+                    let _ = ShowWindow(hwnd, SW_HIDE);
+                }
+                set_click_through(hwnd, false);
+            }
+        }
+    }
+
+    fn start(&mut self, hwnd: HWND, fade_in: bool) {
+        // Clicks go to the windows below while the overlay fades out, it is already closed
+        set_click_through(hwnd, !fade_in);
+        self.ramp = Some(Ramp {
+            fade_in,
+            from: self.level,
+            started: Instant::now(),
+        });
+        unsafe { SetTimer(Some(hwnd), TIMER_FADE, FADE_INTERVAL, None) };
+    }
+
+    fn stop(&mut self, hwnd: HWND) {
+        if let Some(ramp) = self.ramp.take() {
+            let _ = unsafe { KillTimer(Some(hwnd), TIMER_FADE) };
+            if !ramp.fade_in {
+                set_click_through(hwnd, false);
+            }
+        }
+    }
+}
+
+/// Whether the overlay animates. Never in a Remote Desktop session, which sends every frame
+/// over the network and shows the animations stuttering.
+pub(super) fn is_animated(enabled: bool, remote: bool) -> bool {
+    enabled && !remote
+}
+
+/// Lets the mouse through the window to the ones below it, or takes it again.
+fn set_click_through(hwnd: HWND, enabled: bool) {
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        let style = if enabled {
+            style | WS_EX_TRANSPARENT.0 as isize
+        } else {
+            style & !(WS_EX_TRANSPARENT.0 as isize)
+        };
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style);
+    }
+}
+
+/// Changes the alpha of the layered window, keeping what was drawn last.
+pub(super) fn set_window_alpha(hwnd: HWND, alpha: u8) {
+    let blend = BLENDFUNCTION {
+        BlendOp: AC_SRC_OVER as _,
+        SourceConstantAlpha: alpha,
+        AlphaFormat: AC_SRC_ALPHA as _,
+        ..Default::default()
+    };
+    unsafe {
+        let _ = UpdateLayeredWindow(
+            hwnd,
+            None,
+            None,
+            None,
+            None,
+            None,
+            COLORREF(0),
+            Some(&blend),
+            ULW_ALPHA,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_animated() {
+        assert!(is_animated(true, false));
+        assert!(!is_animated(true, true));
+        assert!(!is_animated(false, false));
+    }
+
+    #[test]
+    fn test_fade_disabled() {
+        let mut fade = Fade::new(false);
+        fade.fade_in(HWND::default());
+        assert_eq!(fade.alpha(200), 200);
+        assert!(fade.fade_out(HWND::default()));
+        assert_eq!(fade.alpha(200), 0);
+    }
+}
@@ -0,0 +1,173 @@
+//! Copies of the overlay on the other monitors, so it is seen wherever the user looks.
+
+use super::fade::set_window_alpha;
+use crate::config::Config;
+use crate::utils::{get_moinitor_rect, set_window_capture_exclusion};
+use crate::window_ops::get_monitors;
+
+use windows::Win32::{
+    Foundation::{COLORREF, HWND, POINT, SIZE},
+    Graphics::Gdi::{BLENDFUNCTION, HDC, MONITOR_DEFAULTTONEAREST, MonitorFromWindow},
+    UI::WindowsAndMessaging::{
+        CreateWindowExW, DestroyWindow, SW_HIDE, SW_SHOWNOACTIVATE, ShowWindow, ULW_ALPHA,
+        UpdateLayeredWindow, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+        WS_EX_TRANSPARENT, WS_POPUP,
+    },
+};
+use windows::core::w;
+
+/// Click-through layered popups showing what the overlay shows. Only the overlay itself
+/// takes clicks, and the live previews of windows are drawn on it alone.
+pub(super) struct Mirrors {
+    owner: HWND,
+    enabled: bool,
+    hide_from_capture: bool,
+    windows: Vec<HWND>,
+    /// How many of the windows are in use, there is one per other monitor.
+    shown: usize,
+}
+
+impl Mirrors {
+    pub(super) fn new(owner: HWND, config: &Config) -> Self {
+        Self {
+            owner,
+            enabled: config.overlay_all_monitors,
+            hide_from_capture: config.overlay_hide_from_capture,
+            windows: vec![],
+            shown: 0,
+        }
+    }
+
+    pub(super) fn update_config(&mut self, config: &Config) {
+        self.enabled = config.overlay_all_monitors;
+        self.hide_from_capture = config.overlay_hide_from_capture;
+        if !self.enabled {
+            self.destroy();
+        }
+        for hwnd in &self.windows {
+            set_window_capture_exclusion(*hwnd, self.hide_from_capture);
+        }
+    }
+
+    /// Shows what was drawn into the DC on the other monitors, at the same place relative
+    /// to the center of each monitor as the overlay on its own.
+    pub(super) fn update(
+        &mut self,
+        hdc_mem: HDC,
+        position: POINT,
+        size: SIZE,
+        blend: &BLENDFUNCTION,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let current = unsafe { MonitorFromWindow(self.owner, MONITOR_DEFAULTTONEAREST) };
+        let current_rect = get_moinitor_rect(current);
+        let offset_x = position.x - (current_rect.left + current_rect.right) / 2;
+        let offset_y = position.y - (current_rect.top + current_rect.bottom) / 2;
+        let monitors: Vec<_> = get_monitors()
+            .into_iter()
+            .filter(|v| *v != current)
+            .collect();
+        for (i, monitor) in monitors.iter().enumerate() {
+            let Some(hwnd) = self.window(i) else {
+                break;
+            };
+            let rect = get_moinitor_rect(*monitor);
+            let x = ((rect.left + rect.right) / 2 + offset_x)
+                .min(rect.right - size.cx)
+                .max(rect.left);
+            let y = ((rect.top + rect.bottom) / 2 + offset_y)
+                .min(rect.bottom - size.cy)
+                .max(rect.top);
+            unsafe {
+                let _ = UpdateLayeredWindow(
+                    hwnd,
+                    None,
+                    Some(&POINT { x, y }),
+                    Some(&size),
+                    Some(hdc_mem),
+                    Some(&POINT::default()),
+                    COLORREF(0),
+                    Some(blend),
+                    ULW_ALPHA,
+                );
+                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            }
+        }
+        for hwnd in self.windows.iter().skip(monitors.len()).take(self.shown) {
+            unsafe {
+                let _ = ShowWindow(*hwnd, SW_HIDE);
+            }
+        }
+        self.shown = monitors.len().min(self.windows.len());
+    }
+
+    /// Follows the fade of the overlay, hiding the copies once they faded out.
+    pub(super) fn set_alpha(&mut self, alpha: u8) {
+        if alpha == 0 {
+            self.hide();
+            return;
+        }
+        for hwnd in &self.windows[..self.shown] {
+            set_window_alpha(*hwnd, alpha);
+        }
+    }
+
+    pub(super) fn hide(&mut self) {
+        for hwnd in &self.windows[..self.shown] {
+            unsafe {
+                let _ = ShowWindow(*hwnd, SW_HIDE);
+            }
+        }
+        self.shown = 0;
+    }
+
+    /// The window for the nth other monitor, created on first use.
+    fn window(&mut self, index: usize) -> Option<HWND> {
+        if let Some(hwnd) = self.windows.get(index) {
+            return Some(*hwnd);
+        }
+        // Layered windows are drawn by UpdateLayeredWindow, any class will do
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WS_EX_LAYERED
+                    | WS_EX_TOPMOST
+                    | WS_EX_TOOLWINDOW
+                    | WS_EX_NOACTIVATE
+                    | WS_EX_TRANSPARENT,
+                w!("STATIC"),
+                None,
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                Some(self.owner),
+                None,
+                None,
+                None,
+            )
+        }
+        .map_err(|err| error!("Failed to create overlay copy, {err}"))
+        .ok()?;
+        set_window_capture_exclusion(hwnd, self.hide_from_capture);
+        self.windows.push(hwnd);
+        Some(hwnd)
+    }
+
+    fn destroy(&mut self) {
+        for hwnd in self.windows.drain(..) {
+            unsafe {
+                let _ = DestroyWindow(hwnd);
+            }
+        }
+        self.shown = 0;
+    }
+}
+
+impl Drop for Mirrors {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
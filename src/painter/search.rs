@@ -0,0 +1,326 @@
+//! Palette searching the windows by what is typed, the one overlay taking keyboard input.
+
+use super::titles::fill_rect;
+use super::{
+    ALPHA_MASK, Colors, Font, Layout, SystemTheme, draw_border, draw_text, get_overlay_monitor,
+};
+use crate::app::{SearchUiState, WM_USER_SEARCH_CHAR, WM_USER_SEARCH_CLOSE, WM_USER_SEARCH_KEY};
+use crate::config::{Config, Theme};
+use crate::utils::{
+    check_error, get_moinitor_dpi, get_moinitor_rect, get_owner_window, set_foreground_window,
+    set_window_capture_exclusion,
+};
+
+use anyhow::{Context, Result, anyhow};
+use std::sync::atomic::{AtomicU16, Ordering};
+use windows::Win32::{
+    Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, SIZE, WPARAM},
+    Graphics::{
+        Gdi::{
+            AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, CreateCompatibleBitmap, CreateCompatibleDC,
+            DeleteDC, DeleteObject, GetDC, HDC, ReleaseDC, SelectObject,
+        },
+        GdiPlus::{
+            GdipCreateFromHDC, GdipCreateSolidFill, GdipDeleteBrush, GdipDeleteGraphics,
+            GdipSetSmoothingMode, GdiplusShutdown, GdiplusStartup, GdiplusStartupInput, GpBrush,
+            GpGraphics, GpSolidFill, RectF, SmoothingModeAntiAlias,
+            StringTrimmingEllipsisCharacter,
+        },
+    },
+    System::LibraryLoader::GetModuleHandleW,
+    UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, SW_HIDE, SW_SHOW,
+        SendMessageW, ShowWindow, ULW_ALPHA, USER_DEFAULT_SCREEN_DPI, UpdateLayeredWindow,
+        WA_INACTIVE, WM_ACTIVATE, WM_CHAR, WM_KEYDOWN, WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW,
+        WS_EX_TOPMOST, WS_POPUP,
+    },
+};
+use windows::core::{PCWSTR, w};
+
+const LIST_WIDTH: i32 = 600;
+const ROW_HEIGHT: i32 = 36;
+const BORDER_SIZE: i32 = 8;
+const FONT_SIZE: f32 = 15.0;
+/// Matches listed at most, typing more narrows them down.
+const MAX_ROWS: usize = 8;
+
+/// Paints the palette into a layered popup that, unlike the other overlays, is activated to
+/// receive the typed characters. They are passed on to the owner as `WM_USER_SEARCH_*`.
+pub struct SearchPainter {
+    token: usize,
+    hwnd: HWND,
+    hdc_screen: HDC,
+    system_theme: SystemTheme,
+    theme: Theme,
+    layout: Layout,
+    /// Created again when the DPI of the monitor changes the font size.
+    font: Option<Font>,
+    show: bool,
+}
+
+impl SearchPainter {
+    pub fn new(owner: HWND, config: &Config) -> Result<Self> {
+        let class = register_class()?;
+        let startup_input = GdiplusStartupInput {
+            GdiplusVersion: 1,
+            ..Default::default()
+        };
+        let mut token: usize = 0;
+        check_error(|| unsafe { GdiplusStartup(&mut token, &startup_input, std::ptr::null_mut()) })
+            .context("Failed to initialize GDI+")?;
+
+        let hwnd = match unsafe {
+            CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW,
+                class,
+                None,
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                Some(owner),
+                None,
+                None,
+                None,
+            )
+        } {
+            Ok(v) => v,
+            Err(err) => {
+                unsafe { GdiplusShutdown(token) };
+                return Err(anyhow!("Failed to create search window, {err}"));
+            }
+        };
+
+        set_window_capture_exclusion(hwnd, config.overlay_hide_from_capture);
+
+        Ok(Self {
+            token,
+            hwnd,
+            hdc_screen: unsafe { GetDC(Some(hwnd)) },
+            system_theme: SystemTheme::current(),
+            theme: config.theme,
+            layout: Layout::new(config),
+            font: None,
+            show: false,
+        })
+    }
+
+    pub fn update_config(&mut self, config: &Config) {
+        self.theme = config.theme;
+        self.layout = Layout::new(config);
+        set_window_capture_exclusion(self.hwnd, config.overlay_hide_from_capture);
+    }
+
+    /// Re-reads the system theme, the next paint uses its colors.
+    pub fn update_theme(&mut self) {
+        self.system_theme = SystemTheme::current();
+    }
+
+    pub fn paint(&mut self, state: &SearchUiState) {
+        let hmonitor = get_overlay_monitor(self.layout.monitor);
+        let monitor_rect = get_moinitor_rect(hmonitor);
+        let monitor_width = monitor_rect.right - monitor_rect.left;
+        let monitor_height = monitor_rect.bottom - monitor_rect.top;
+        let dpi = get_moinitor_dpi(hmonitor) as i32;
+        let scale = |v: i32| v * dpi / USER_DEFAULT_SCREEN_DPI as i32;
+        let border_size = scale(BORDER_SIZE);
+        let row_height = scale(ROW_HEIGHT);
+        let font = Font::of_size(
+            &mut self.font,
+            FONT_SIZE * dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+        );
+
+        // The query row comes first, the matches scroll with the selection below it
+        let rows = state.rows.len().min(MAX_ROWS);
+        let first = (state.index + 1).saturating_sub(rows);
+        let width = scale(LIST_WIDTH).min(monitor_width);
+        let height = row_height * (rows + 1) as i32 + border_size * 2;
+        // Anchored at the top so the palette does not jump while the matches change
+        let x = monitor_rect.left + (monitor_width - width) / 2;
+        let y = monitor_rect.top + monitor_height / 4;
+        let corner_radius = match self.layout.corner_radius {
+            Some(v) => scale(v),
+            None => row_height / 4,
+        } as f32;
+
+        let colors = Colors::new(&self.theme, &self.layout, self.system_theme);
+
+        unsafe {
+            let hdc_mem = CreateCompatibleDC(Some(self.hdc_screen));
+            let bitmap_mem = CreateCompatibleBitmap(self.hdc_screen, width, height);
+            SelectObject(hdc_mem, bitmap_mem.into());
+
+            let mut graphics_ptr: *mut GpGraphics = std::ptr::null_mut();
+            GdipCreateFromHDC(hdc_mem, &mut graphics_ptr as _);
+            GdipSetSmoothingMode(graphics_ptr, SmoothingModeAntiAlias);
+
+            let mut bg_brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+            GdipCreateSolidFill(ALPHA_MASK | colors.background, &mut bg_brush_ptr as _);
+            let mut fg_brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+            GdipCreateSolidFill(ALPHA_MASK | colors.highlight, &mut fg_brush_ptr as _);
+
+            fill_rect(
+                graphics_ptr,
+                bg_brush_ptr as *mut GpBrush,
+                0.0,
+                0.0,
+                width as f32,
+                height as f32,
+                corner_radius,
+            );
+            if let Some(border_color) = colors.border {
+                let border_width = scale(self.layout.border_width.unwrap_or(1));
+                if border_width > 0 {
+                    draw_border(
+                        graphics_ptr,
+                        border_color,
+                        border_width as f32,
+                        width as f32,
+                        height as f32,
+                        corner_radius,
+                    );
+                }
+            }
+
+            let left = border_size as f32;
+            let right = (width - border_size) as f32;
+            let text_rect = |top: f32| RectF {
+                X: left + border_size as f32,
+                Y: top,
+                Width: right - left - border_size as f32 * 2.0,
+                Height: row_height as f32,
+            };
+            let query = if state.query.is_empty() {
+                "Type to search windows".to_string()
+            } else {
+                format!("{}|", state.query)
+            };
+            draw_text(
+                graphics_ptr,
+                &query,
+                &text_rect(border_size as f32),
+                font,
+                colors.text,
+                StringTrimmingEllipsisCharacter,
+            );
+
+            for (row, title) in state.rows.iter().enumerate().skip(first).take(rows) {
+                let top = (border_size + row_height * (row - first + 1) as i32) as f32;
+                if row == state.index {
+                    fill_rect(
+                        graphics_ptr,
+                        fg_brush_ptr as *mut GpBrush,
+                        left,
+                        top,
+                        right,
+                        top + row_height as f32,
+                        corner_radius,
+                    );
+                }
+                draw_text(
+                    graphics_ptr,
+                    title,
+                    &text_rect(top),
+                    font,
+                    colors.text,
+                    StringTrimmingEllipsisCharacter,
+                );
+            }
+
+            let blend = BLENDFUNCTION {
+                BlendOp: AC_SRC_OVER as _,
+                SourceConstantAlpha: self.theme.opacity,
+                AlphaFormat: AC_SRC_ALPHA as _,
+                ..Default::default()
+            };
+            let _ = UpdateLayeredWindow(
+                self.hwnd,
+                Some(self.hdc_screen),
+                Some(&POINT { x, y }),
+                Some(&SIZE {
+                    cx: width,
+                    cy: height,
+                }),
+                Some(hdc_mem),
+                Some(&POINT::default()),
+                COLORREF(0),
+                Some(&blend),
+                ULW_ALPHA,
+            );
+
+            GdipDeleteBrush(bg_brush_ptr as *mut GpBrush);
+            GdipDeleteBrush(fg_brush_ptr as *mut GpBrush);
+            GdipDeleteGraphics(graphics_ptr);
+            let _ = DeleteObject(bitmap_mem.into());
+            let _ = DeleteDC(hdc_mem);
+
+            if !self.show {
+                let _ = ShowWindow(self.hwnd, SW_SHOW);
+                // Typing goes to the foreground window, the palette has to take it over
+                set_foreground_window(self.hwnd);
+                self.show = true;
+            }
+        }
+    }
+
+    pub fn unpaint(&mut self) {
+        if !self.show {
+            return;
+        }
+        // Hidden first, the deactivation it causes then finds the palette closed
+        self.show = false;
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        }
+    }
+}
+
+impl Drop for SearchPainter {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseDC(Some(self.hwnd), self.hdc_screen);
+            let _ = DestroyWindow(self.hwnd);
+            // The font is deleted while GDI+ is still running
+            self.font = None;
+            GdiplusShutdown(self.token);
+        }
+    }
+}
+
+/// Registers the window class of the palette once, it is needed for the window procedure.
+fn register_class() -> Result<PCWSTR> {
+    static ATOM: AtomicU16 = AtomicU16::new(0);
+    if ATOM.load(Ordering::SeqCst) == 0 {
+        let hinstance = unsafe { GetModuleHandleW(None) }
+            .map_err(|err| anyhow!("Failed to get current module handle, {err}"))?;
+        let window_class = WNDCLASSW {
+            hInstance: hinstance.into(),
+            lpszClassName: w!("WindowSwitcherSearch"),
+            lpfnWndProc: Some(window_proc),
+            ..Default::default()
+        };
+        let atom = check_error(|| unsafe { RegisterClassW(&window_class) })
+            .map_err(|err| anyhow!("Failed to register search class, {err}"))?;
+        ATOM.store(atom, Ordering::SeqCst);
+    }
+    Ok(PCWSTR(ATOM.load(Ordering::SeqCst) as _))
+}
+
+/// Passes the typed characters and keys on to the owner, losing the focus closes the palette.
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let message = match msg {
+        WM_CHAR => WM_USER_SEARCH_CHAR,
+        WM_KEYDOWN => WM_USER_SEARCH_KEY,
+        WM_ACTIVATE if (wparam.0 & 0xffff) as u32 == WA_INACTIVE => WM_USER_SEARCH_CLOSE,
+        _ => return unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    };
+    // SAFETY: the owner is the app window, which outlives the palette
+    unsafe { SendMessageW(get_owner_window(hwnd), message, Some(wparam), None) };
+    LRESULT(0)
+}
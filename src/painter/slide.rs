@@ -0,0 +1,108 @@
+//! Slides the selection highlight from the previous app to the selected one on a timer,
+//! instead of jumping there.
+
+use super::fade::is_animated;
+use crate::app::TIMER_SLIDE;
+use crate::utils::is_remote_session;
+
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{KillTimer, SetTimer},
+};
+
+const SLIDE_DURATION: Duration = Duration::from_millis(120);
+const SLIDE_INTERVAL: u32 = 10;
+
+pub(super) struct Slide {
+    enabled: bool,
+    /// Column and row the highlight was drawn at last, fractional while sliding.
+    position: Option<(f32, f32)>,
+    ramp: Option<Ramp>,
+}
+
+struct Ramp {
+    from: (f32, f32),
+    to: (f32, f32),
+    started: Instant,
+}
+
+impl Slide {
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            position: None,
+            ramp: None,
+        }
+    }
+
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Column and row to draw the highlight at for the selected cell, starts sliding
+    /// from where the highlight is when the selection moved.
+    pub(super) fn position(&mut self, hwnd: HWND, cell: (i32, i32)) -> (f32, f32) {
+        let target = (cell.0 as f32, cell.1 as f32);
+        let animated = is_animated(self.enabled, is_remote_session());
+        let Some(position) = self.position.filter(|_| animated) else {
+            self.stop(hwnd);
+            self.position = Some(target);
+            return target;
+        };
+        let redirected = self
+            .ramp
+            .as_ref()
+            .map_or(position != target, |ramp| ramp.to != target);
+        if redirected {
+            self.stop(hwnd);
+            if position != target {
+                self.ramp = Some(Ramp {
+                    from: position,
+                    to: target,
+                    started: Instant::now(),
+                });
+                unsafe { SetTimer(Some(hwnd), TIMER_SLIDE, SLIDE_INTERVAL, None) };
+            }
+        }
+        let Some(ramp) = &self.ramp else {
+            self.position = Some(target);
+            return target;
+        };
+        let progress =
+            (ramp.started.elapsed().as_secs_f32() / SLIDE_DURATION.as_secs_f32()).min(1.0);
+        // Ease out, fast at first and settling on the target
+        let t = 1.0 - (1.0 - progress) * (1.0 - progress);
+        let position = (
+            ramp.from.0 + (ramp.to.0 - ramp.from.0) * t,
+            ramp.from.1 + (ramp.to.1 - ramp.from.1) * t,
+        );
+        if progress >= 1.0 {
+            self.stop(hwnd);
+        }
+        self.position = Some(position);
+        position
+    }
+
+    /// Whether the overlay should be repainted on the timer.
+    pub(super) fn on_timer(&mut self, hwnd: HWND) -> bool {
+        if self.ramp.is_none() {
+            // Left over from a painter replaced by a config reload
+            let _ = unsafe { KillTimer(Some(hwnd), TIMER_SLIDE) };
+            return false;
+        }
+        true
+    }
+
+    /// Forgets the highlight, the next overlay shows it in place at once.
+    pub(super) fn reset(&mut self, hwnd: HWND) {
+        self.stop(hwnd);
+        self.position = None;
+    }
+
+    fn stop(&mut self, hwnd: HWND) {
+        if self.ramp.take().is_some() {
+            let _ = unsafe { KillTimer(Some(hwnd), TIMER_SLIDE) };
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Compact list of the window titles of an app, shown while switching its windows.
+
+use super::{
+    ALPHA_MASK, Colors, Font, Layout, SystemTheme, draw_border, draw_round_rect, draw_text,
+    get_overlay_monitor,
+};
+use crate::app::SwitchWindowsUiState;
+use crate::config::{Config, Theme};
+use crate::utils::{
+    check_error, get_moinitor_dpi, get_moinitor_rect, set_window_capture_exclusion,
+};
+
+use anyhow::{Context, Result, anyhow};
+use windows::Win32::{
+    Foundation::{COLORREF, HWND, POINT, SIZE},
+    Graphics::{
+        Gdi::{
+            AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, CreateCompatibleBitmap, CreateCompatibleDC,
+            DeleteDC, DeleteObject, GetDC, HDC, ReleaseDC, SelectObject,
+        },
+        GdiPlus::{
+            GdipCreateFromHDC, GdipCreateSolidFill, GdipDeleteBrush, GdipDeleteGraphics,
+            GdipFillRectangle, GdipSetSmoothingMode, GdiplusShutdown, GdiplusStartup,
+            GdiplusStartupInput, GpBrush, GpGraphics, GpSolidFill, RectF, SmoothingModeAntiAlias,
+            StringTrimmingEllipsisCharacter,
+        },
+    },
+    UI::WindowsAndMessaging::{
+        CreateWindowExW, DestroyWindow, SW_HIDE, SW_SHOWNOACTIVATE, ShowWindow, ULW_ALPHA,
+        USER_DEFAULT_SCREEN_DPI, UpdateLayeredWindow, WS_EX_LAYERED, WS_EX_NOACTIVATE,
+        WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+    },
+};
+use windows::core::w;
+
+const LIST_WIDTH: i32 = 480;
+const ROW_HEIGHT: i32 = 32;
+const BORDER_SIZE: i32 = 8;
+const FONT_SIZE: f32 = 14.0;
+
+/// Paints the titles into a layered popup of its own, which never takes the focus so the
+/// switched windows are activated as usual.
+pub struct TitlesPainter {
+    token: usize,
+    hwnd: HWND,
+    hdc_screen: HDC,
+    system_theme: SystemTheme,
+    theme: Theme,
+    layout: Layout,
+    /// Created again when the DPI of the monitor changes the font size.
+    font: Option<Font>,
+    show: bool,
+}
+
+impl TitlesPainter {
+    pub fn new(owner: HWND, config: &Config) -> Result<Self> {
+        let startup_input = GdiplusStartupInput {
+            GdiplusVersion: 1,
+            ..Default::default()
+        };
+        let mut token: usize = 0;
+        check_error(|| unsafe { GdiplusStartup(&mut token, &startup_input, std::ptr::null_mut()) })
+            .context("Failed to initialize GDI+")?;
+
+        // Layered windows are drawn by UpdateLayeredWindow, any class will do
+        let hwnd = match unsafe {
+            CreateWindowExW(
+                WS_EX_LAYERED
+                    | WS_EX_TOPMOST
+                    | WS_EX_TOOLWINDOW
+                    | WS_EX_NOACTIVATE
+                    | WS_EX_TRANSPARENT,
+                w!("STATIC"),
+                None,
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                Some(owner),
+                None,
+                None,
+                None,
+            )
+        } {
+            Ok(v) => v,
+            Err(err) => {
+                unsafe { GdiplusShutdown(token) };
+                return Err(anyhow!("Failed to create titles window, {err}"));
+            }
+        };
+
+        set_window_capture_exclusion(hwnd, config.overlay_hide_from_capture);
+
+        Ok(Self {
+            token,
+            hwnd,
+            hdc_screen: unsafe { GetDC(Some(hwnd)) },
+            system_theme: SystemTheme::current(),
+            theme: config.theme,
+            layout: Layout::new(config),
+            font: None,
+            show: false,
+        })
+    }
+
+    pub fn update_config(&mut self, config: &Config) {
+        self.theme = config.theme;
+        self.layout = Layout::new(config);
+        set_window_capture_exclusion(self.hwnd, config.overlay_hide_from_capture);
+    }
+
+    /// Re-reads the system theme, the next paint uses its colors.
+    pub fn update_theme(&mut self) {
+        self.system_theme = SystemTheme::current();
+    }
+
+    pub fn paint(&mut self, state: &SwitchWindowsUiState) {
+        let hmonitor = get_overlay_monitor(self.layout.monitor);
+        let monitor_rect = get_moinitor_rect(hmonitor);
+        let monitor_width = monitor_rect.right - monitor_rect.left;
+        let monitor_height = monitor_rect.bottom - monitor_rect.top;
+        let dpi = get_moinitor_dpi(hmonitor) as i32;
+        let scale = |v: i32| v * dpi / USER_DEFAULT_SCREEN_DPI as i32;
+        let border_size = scale(BORDER_SIZE);
+        let row_height = scale(ROW_HEIGHT);
+        let font = Font::of_size(
+            &mut self.font,
+            FONT_SIZE * dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+        );
+
+        // Rows that do not fit the monitor scroll with the selection
+        let max_rows = ((monitor_height - border_size * 2) / row_height).max(1) as usize;
+        let rows = state.titles.len().min(max_rows);
+        let first = (state.index + 1).saturating_sub(rows);
+        let width = scale(LIST_WIDTH).min(monitor_width);
+        let height = row_height * rows as i32 + border_size * 2;
+        let x = monitor_rect.left + (monitor_width - width) / 2;
+        let y = monitor_rect.top + (monitor_height - height) / 2;
+        let corner_radius = match self.layout.corner_radius {
+            Some(v) => scale(v),
+            None => row_height / 4,
+        } as f32;
+
+        let colors = Colors::new(&self.theme, &self.layout, self.system_theme);
+
+        unsafe {
+            let hdc_mem = CreateCompatibleDC(Some(self.hdc_screen));
+            let bitmap_mem = CreateCompatibleBitmap(self.hdc_screen, width, height);
+            SelectObject(hdc_mem, bitmap_mem.into());
+
+            let mut graphics_ptr: *mut GpGraphics = std::ptr::null_mut();
+            GdipCreateFromHDC(hdc_mem, &mut graphics_ptr as _);
+            GdipSetSmoothingMode(graphics_ptr, SmoothingModeAntiAlias);
+
+            let mut bg_brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+            GdipCreateSolidFill(ALPHA_MASK | colors.background, &mut bg_brush_ptr as _);
+            let mut fg_brush_ptr: *mut GpSolidFill = std::ptr::null_mut();
+            GdipCreateSolidFill(ALPHA_MASK | colors.highlight, &mut fg_brush_ptr as _);
+
+            fill_rect(
+                graphics_ptr,
+                bg_brush_ptr as *mut GpBrush,
+                0.0,
+                0.0,
+                width as f32,
+                height as f32,
+                corner_radius,
+            );
+            if let Some(border_color) = colors.border {
+                let border_width = scale(self.layout.border_width.unwrap_or(1));
+                if border_width > 0 {
+                    draw_border(
+                        graphics_ptr,
+                        border_color,
+                        border_width as f32,
+                        width as f32,
+                        height as f32,
+                        corner_radius,
+                    );
+                }
+            }
+
+            for (row, title) in state.titles.iter().enumerate().skip(first).take(rows) {
+                let top = (border_size + row_height * (row - first) as i32) as f32;
+                let left = border_size as f32;
+                let right = (width - border_size) as f32;
+                if row == state.index {
+                    fill_rect(
+                        graphics_ptr,
+                        fg_brush_ptr as *mut GpBrush,
+                        left,
+                        top,
+                        right,
+                        top + row_height as f32,
+                        corner_radius,
+                    );
+                }
+                let rect = RectF {
+                    X: left + border_size as f32,
+                    Y: top,
+                    Width: right - left - border_size as f32 * 2.0,
+                    Height: row_height as f32,
+                };
+                draw_text(
+                    graphics_ptr,
+                    title,
+                    &rect,
+                    font,
+                    colors.text,
+                    StringTrimmingEllipsisCharacter,
+                );
+            }
+
+            let blend = BLENDFUNCTION {
+                BlendOp: AC_SRC_OVER as _,
+                SourceConstantAlpha: self.theme.opacity,
+                AlphaFormat: AC_SRC_ALPHA as _,
+                ..Default::default()
+            };
+            let _ = UpdateLayeredWindow(
+                self.hwnd,
+                Some(self.hdc_screen),
+                Some(&POINT { x, y }),
+                Some(&SIZE {
+                    cx: width,
+                    cy: height,
+                }),
+                Some(hdc_mem),
+                Some(&POINT::default()),
+                COLORREF(0),
+                Some(&blend),
+                ULW_ALPHA,
+            );
+
+            GdipDeleteBrush(bg_brush_ptr as *mut GpBrush);
+            GdipDeleteBrush(fg_brush_ptr as *mut GpBrush);
+            GdipDeleteGraphics(graphics_ptr);
+            let _ = DeleteObject(bitmap_mem.into());
+            let _ = DeleteDC(hdc_mem);
+
+            if !self.show {
+                let _ = ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+                self.show = true;
+            }
+        }
+    }
+
+    pub fn unpaint(&mut self) {
+        if !self.show {
+            return;
+        }
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        }
+        self.show = false;
+    }
+}
+
+/// Fills the rect, rounded unless the corner radius is 0.
+pub(super) unsafe fn fill_rect(
+    graphics_ptr: *mut GpGraphics,
+    brush_ptr: *mut GpBrush,
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    corner_radius: f32,
+) {
+    unsafe {
+        if corner_radius > 0.0 {
+            draw_round_rect(
+                graphics_ptr,
+                brush_ptr,
+                left,
+                top,
+                right,
+                bottom,
+                corner_radius,
+            );
+        } else {
+            GdipFillRectangle(
+                graphics_ptr,
+                brush_ptr,
+                left,
+                top,
+                right - left,
+                bottom - top,
+            );
+        }
+    }
+}
+
+impl Drop for TitlesPainter {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseDC(Some(self.hwnd), self.hdc_screen);
+            let _ = DestroyWindow(self.hwnd);
+            // The font is deleted while GDI+ is still running
+            self.font = None;
+            GdiplusShutdown(self.token);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ini::Ini;
+
+const APPS_SECTION: &str = "statistics-apps";
+const HOTKEYS_SECTION: &str = "statistics-hotkeys";
+const LATENCY_SECTION: &str = "statistics-latency";
+const TOP_APPS: usize = 10;
+/// Age after which a switch counts half for the frecency sort.
+const FRECENCY_HALF_LIFE: f64 = 7.0 * 24.0 * 3600.0;
+
+/// Usage counted locally, stored with the other state and never sent anywhere.
+#[derive(Debug, Default)]
+pub struct Statistics {
+    /// Switches to each app through the switch apps overlay, by module path.
+    pub app_switches: HashMap<String, u64>,
+    /// Time of the last switch to each app in seconds since the epoch, by module path.
+    pub app_last_switches: HashMap<String, u64>,
+    /// Presses of each hotkey, by hotkey name.
+    pub hotkey_presses: HashMap<String, u64>,
+    /// Time from the hotkey press to the overlay shown or the window switched.
+    latency_total: Duration,
+    latency_count: u64,
+    /// Something was recorded since the last `take_changed`.
+    changed: bool,
+}
+
+impl Statistics {
+    pub fn load(ini: &Ini) -> Self {
+        let mut statistics = Self::default();
+        if let Some(section) = ini.section(Some(APPS_SECTION)) {
+            // Keys are indexes since module paths contain `:`, values are
+            // `count|last|module_path`, older files lack the last switch time
+            for (_, v) in section.iter() {
+                if let Some((count, rest)) = v.split_once('|')
+                    && let Ok(count) = count.parse()
+                {
+                    let mut module_path = rest;
+                    if let Some((last, v)) = rest.split_once('|')
+                        && let Ok(last) = last.parse()
+                    {
+                        statistics.app_last_switches.insert(v.to_string(), last);
+                        module_path = v;
+                    }
+                    statistics
+                        .app_switches
+                        .insert(module_path.to_string(), count);
+                }
+            }
+        }
+        if let Some(section) = ini.section(Some(HOTKEYS_SECTION)) {
+            for (k, v) in section.iter() {
+                if let Ok(count) = v.parse() {
+                    statistics.hotkey_presses.insert(k.to_string(), count);
+                }
+            }
+        }
+        if let Some(section) = ini.section(Some(LATENCY_SECTION)) {
+            let get = |key| section.get(key).and_then(|v| v.parse::<u64>().ok());
+            if let (Some(total), Some(count)) = (get("total_us"), get("count")) {
+                statistics.latency_total = Duration::from_micros(total);
+                statistics.latency_count = count;
+            }
+        }
+        statistics
+    }
+
+    pub fn save(&self, ini: &mut Ini) {
+        for (i, (module_path, count)) in self.app_switches.iter().enumerate() {
+            let last = self.app_last_switches.get(module_path).unwrap_or(&0);
+            ini.with_section(Some(APPS_SECTION))
+                .set(i.to_string(), format!("{count}|{last}|{module_path}"));
+        }
+        for (name, count) in &self.hotkey_presses {
+            ini.with_section(Some(HOTKEYS_SECTION))
+                .set(name, count.to_string());
+        }
+        if self.latency_count > 0 {
+            ini.with_section(Some(LATENCY_SECTION))
+                .set("total_us", self.latency_total.as_micros().to_string())
+                .set("count", self.latency_count.to_string());
+        }
+    }
+
+    pub fn record_app_switch(&mut self, module_path: &str) {
+        *self
+            .app_switches
+            .entry(module_path.to_string())
+            .or_default() += 1;
+        self.app_last_switches
+            .insert(module_path.to_string(), epoch_secs(SystemTime::now()));
+        self.changed = true;
+    }
+
+    pub fn record_hotkey(&mut self, name: &str) {
+        *self.hotkey_presses.entry(name.to_string()).or_default() += 1;
+        self.changed = true;
+    }
+
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.latency_total += latency;
+        self.latency_count += 1;
+        self.changed = true;
+    }
+
+    /// Whether something was recorded since the last call, to skip saving unchanged state.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Switches to the app with each counting half as much every week since the last one,
+    /// apps used often lately score highest.
+    pub fn frecency(&self, module_path: &str, now: SystemTime) -> f64 {
+        let Some(count) = self.app_switches.get(module_path) else {
+            return 0.0;
+        };
+        let last = self.app_last_switches.get(module_path).unwrap_or(&0);
+        let age = epoch_secs(now).saturating_sub(*last) as f64;
+        *count as f64 * 0.5f64.powf(age / FRECENCY_HALF_LIFE)
+    }
+
+    /// Text for the statistics window.
+    pub fn summary(&self) -> String {
+        let mut lines = vec!["Most switched-to apps:".to_string()];
+        let mut apps: Vec<_> = self.app_switches.iter().collect();
+        apps.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (module_path, count) in apps.iter().take(TOP_APPS) {
+            let name = Path::new(module_path)
+                .file_name()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_else(|| module_path.to_string());
+            lines.push(format!("    {count}  {name}"));
+        }
+        if apps.is_empty() {
+            lines.push("    none yet".to_string());
+        }
+
+        lines.push(String::new());
+        lines.push("Hotkeys:".to_string());
+        let mut hotkeys: Vec<_> = self.hotkey_presses.iter().collect();
+        hotkeys.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (name, count) in &hotkeys {
+            lines.push(format!("    {count}  {name}"));
+        }
+        if hotkeys.is_empty() {
+            lines.push("    none yet".to_string());
+        }
+
+        lines.push(String::new());
+        if self.latency_count > 0 {
+            let average = self.latency_total.as_secs_f64() * 1000.0 / self.latency_count as f64;
+            lines.push(format!("Average switch latency: {average:.1} ms"));
+        } else {
+            lines.push("Average switch latency: unknown".to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+fn epoch_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load() {
+        let mut statistics = Statistics::default();
+        statistics.record_app_switch(r"C:\Program Files\App\app.exe");
+        statistics.record_app_switch(r"C:\Program Files\App\app.exe");
+        statistics.record_hotkey("switch apps");
+        statistics.record_latency(Duration::from_millis(3));
+        let mut ini = Ini::new();
+        statistics.save(&mut ini);
+
+        let loaded = Statistics::load(&ini);
+        assert_eq!(
+            loaded.app_switches.get(r"C:\Program Files\App\app.exe"),
+            Some(&2)
+        );
+        assert_eq!(loaded.hotkey_presses.get("switch apps"), Some(&1));
+        assert_eq!(loaded.latency_total, Duration::from_millis(3));
+        assert_eq!(loaded.latency_count, 1);
+        assert_eq!(
+            loaded
+                .app_last_switches
+                .get(r"C:\Program Files\App\app.exe"),
+            statistics
+                .app_last_switches
+                .get(r"C:\Program Files\App\app.exe")
+        );
+
+        // Files written before the last switch times were stored
+        let mut ini = Ini::new();
+        ini.with_section(Some(APPS_SECTION))
+            .set("0", r"3|C:\Windows\notepad.exe");
+        let loaded = Statistics::load(&ini);
+        assert_eq!(loaded.app_switches.get(r"C:\Windows\notepad.exe"), Some(&3));
+    }
+
+    #[test]
+    fn test_frecency() {
+        let now = UNIX_EPOCH + Duration::from_secs(100 * 24 * 3600);
+        let week_ago = epoch_secs(now) - 7 * 24 * 3600;
+        let mut statistics = Statistics::default();
+        statistics.app_switches.insert("recent.exe".into(), 4);
+        statistics
+            .app_last_switches
+            .insert("recent.exe".into(), epoch_secs(now));
+        statistics.app_switches.insert("old.exe".into(), 6);
+        statistics
+            .app_last_switches
+            .insert("old.exe".into(), week_ago);
+        assert_eq!(statistics.frecency("recent.exe", now), 4.0);
+        assert_eq!(statistics.frecency("old.exe", now), 3.0);
+        assert_eq!(statistics.frecency("unknown.exe", now), 0.0);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Result, anyhow};
+use ini::{EscapePolicy, Ini, ParseOption};
+
+use crate::config::get_config_path;
+use crate::statistics::Statistics;
+
+const STORAGE_FILE_NAME: &str = "window-switcher-state";
+const APPS_ORDER_SECTION: &str = "apps-order";
+const LAST_WINDOWS_SECTION: &str = "last-windows";
+
+/// State that survives restarts, stored next to the config file.
+#[derive(Debug, Default)]
+pub struct Storage {
+    path: Option<PathBuf>,
+    /// Module paths in the order arranged by dragging icons in the switch-apps overlay.
+    pub apps_order: Vec<String>,
+    /// Window of each app switch windows returns to, by module path. Windows that no longer
+    /// exist are skipped as they are not listed.
+    pub last_windows: HashMap<String, isize>,
+    pub statistics: Statistics,
+}
+
+impl Storage {
+    /// Loads the state of the instance, named instances sharing a config folder keep their own.
+    pub fn load(instance_name: Option<&str>) -> Self {
+        let path = match get_config_path() {
+            Ok(v) => v.with_file_name(storage_file_name(instance_name)),
+            Err(err) => {
+                error!("Failed to resolve storage path, {err}");
+                return Self::default();
+            }
+        };
+        let mut storage = Self {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        if !path.exists() {
+            return storage;
+        }
+        let opt = ParseOption {
+            enabled_escape: false,
+            ..Default::default()
+        };
+        let ini = match Ini::load_from_file_opt(&path, opt) {
+            Ok(v) => v,
+            Err(err) => {
+                error!("Failed to load storage file '{}', {err}", path.display());
+                return storage;
+            }
+        };
+        if let Some(section) = ini.section(Some(APPS_ORDER_SECTION)) {
+            storage.apps_order = section.iter().map(|(_, v)| v.to_string()).collect();
+        }
+        if let Some(section) = ini.section(Some(LAST_WINDOWS_SECTION)) {
+            // Keys are indexes since module paths contain `:`
+            for (_, v) in section.iter() {
+                if let Some((hwnd, module_path)) = v.split_once('|')
+                    && let Ok(hwnd) = hwnd.parse()
+                {
+                    storage.last_windows.insert(module_path.to_string(), hwnd);
+                }
+            }
+        }
+        storage.statistics = Statistics::load(&ini);
+        storage
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut ini = Ini::new();
+        for (i, module_path) in self.apps_order.iter().enumerate() {
+            ini.with_section(Some(APPS_ORDER_SECTION))
+                .set(i.to_string(), module_path);
+        }
+        for (i, (module_path, hwnd)) in self.last_windows.iter().enumerate() {
+            ini.with_section(Some(LAST_WINDOWS_SECTION))
+                .set(i.to_string(), format!("{hwnd}|{module_path}"));
+        }
+        self.statistics.save(&mut ini);
+        ini.write_to_file_policy(path, EscapePolicy::Nothing)
+            .map_err(|err| anyhow!("Failed to write storage file '{}', {err}", path.display()))
+    }
+}
+
+fn storage_file_name(instance_name: Option<&str>) -> String {
+    match instance_name {
+        Some(name) => {
+            let name: String = name
+                .chars()
+                .map(|c| match c {
+                    '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+                    c => c,
+                })
+                .collect();
+            format!("{STORAGE_FILE_NAME}-{name}.ini")
+        }
+        None => format!("{STORAGE_FILE_NAME}.ini"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_storage_file_name() {
+        assert_eq!(storage_file_name(None), "window-switcher-state.ini");
+        assert_eq!(
+            storage_file_name(Some("work")),
+            "window-switcher-state-work.ini"
+        );
+        assert_eq!(
+            storage_file_name(Some(r"a\b:c")),
+            "window-switcher-state-a_b_c.ini"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::utils::to_wstring;
+
+use anyhow::{Result, anyhow};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, POINT, WPARAM},
+    UI::{
+        Controls::{
+            ICC_BAR_CLASSES, INITCOMMONCONTROLSEX, InitCommonControlsEx, TOOLTIPS_CLASSW,
+            TTF_ABSOLUTE, TTF_TRACK, TTM_ADDTOOLW, TTM_SETMAXTIPWIDTH, TTM_TRACKACTIVATE,
+            TTM_TRACKPOSITION, TTM_UPDATETIPTEXTW, TTS_ALWAYSTIP, TTS_NOPREFIX, TTTOOLINFOW,
+        },
+        WindowsAndMessaging::{
+            CW_USEDEFAULT, CreateWindowExW, DestroyWindow, GetCursorPos, SendMessageW,
+            WINDOW_STYLE, WS_EX_TOPMOST, WS_POPUP,
+        },
+    },
+};
+use windows::core::PWSTR;
+
+/// Offset of the tooltip from the cursor, so that it does not cover the cursor itself.
+const CURSOR_OFFSET: i32 = 16;
+const MAX_WIDTH: isize = 480;
+
+/// Tooltip of the switch apps overlay, shown next to the cursor on request.
+///
+/// A tracking tooltip is used, the overlay is a layered window that cannot host the usual
+/// tooltip subclassing.
+#[derive(Debug)]
+pub struct Tooltip {
+    hwnd: HWND,
+    owner: HWND,
+    visible: bool,
+}
+
+impl Tooltip {
+    pub fn create(owner: HWND) -> Result<Self> {
+        let controls = INITCOMMONCONTROLSEX {
+            dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as u32,
+            dwICC: ICC_BAR_CLASSES,
+        };
+        let _ = unsafe { InitCommonControlsEx(&controls) };
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WS_EX_TOPMOST,
+                TOOLTIPS_CLASSW,
+                None,
+                WS_POPUP | WINDOW_STYLE(TTS_NOPREFIX | TTS_ALWAYSTIP),
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                Some(owner),
+                None,
+                None,
+                None,
+            )
+        }
+        .map_err(|err| anyhow!("Failed to create tooltip, {err}"))?;
+        let mut empty = to_wstring("");
+        let mut info = tool_info(owner, PWSTR(empty.as_mut_ptr()));
+        unsafe {
+            SendMessageW(
+                hwnd,
+                TTM_ADDTOOLW,
+                None,
+                Some(LPARAM(&mut info as *mut _ as _)),
+            );
+            // Lines break at \n only once a maximum width is set
+            SendMessageW(hwnd, TTM_SETMAXTIPWIDTH, None, Some(LPARAM(MAX_WIDTH)));
+        }
+        Ok(Self {
+            hwnd,
+            owner,
+            visible: false,
+        })
+    }
+
+    /// Shows the text below the cursor.
+    pub fn show(&mut self, text: &str) {
+        let mut text = to_wstring(text);
+        let mut info = tool_info(self.owner, PWSTR(text.as_mut_ptr()));
+        let mut cursor_pos = POINT::default();
+        let _ = unsafe { GetCursorPos(&mut cursor_pos) };
+        let x = (cursor_pos.x + CURSOR_OFFSET) as u16 as isize;
+        let y = (cursor_pos.y + CURSOR_OFFSET) as u16 as isize;
+        unsafe {
+            SendMessageW(
+                self.hwnd,
+                TTM_UPDATETIPTEXTW,
+                None,
+                Some(LPARAM(&mut info as *mut _ as _)),
+            );
+            SendMessageW(
+                self.hwnd,
+                TTM_TRACKPOSITION,
+                None,
+                Some(LPARAM(x | (y << 16))),
+            );
+            SendMessageW(
+                self.hwnd,
+                TTM_TRACKACTIVATE,
+                Some(WPARAM(1)),
+                Some(LPARAM(&mut info as *mut _ as _)),
+            );
+        }
+        self.visible = true;
+    }
+
+    pub fn hide(&mut self) {
+        if !self.visible {
+            return;
+        }
+        let mut info = tool_info(self.owner, PWSTR::null());
+        unsafe {
+            SendMessageW(
+                self.hwnd,
+                TTM_TRACKACTIVATE,
+                Some(WPARAM(0)),
+                Some(LPARAM(&mut info as *mut _ as _)),
+            );
+        }
+        self.visible = false;
+    }
+}
+
+impl Drop for Tooltip {
+    fn drop(&mut self) {
+        let _ = unsafe { DestroyWindow(self.hwnd) };
+    }
+}
+
+fn tool_info(owner: HWND, text: PWSTR) -> TTTOOLINFOW {
+    TTTOOLINFOW {
+        cbSize: std::mem::size_of::<TTTOOLINFOW>() as u32,
+        uFlags: TTF_TRACK | TTF_ABSOLUTE,
+        hwnd: owner,
+        uId: 0,
+        lpszText: text,
+        ..Default::default()
+    }
+}
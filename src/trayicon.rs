@@ -1,4 +1,8 @@
-use crate::app::{IDM_CONFIGURE, IDM_EXIT, IDM_STARTUP, NAME, WM_USER_TRAYICON};
+use crate::app::{
+    IDM_CONFIGURE, IDM_EXIT, IDM_PAUSE, IDM_STARTUP, IDM_STATISTICS, NAME, WM_USER_TRAYICON,
+};
+use crate::crash::set_trayicon_window;
+use crate::utils::is_do_not_disturb;
 
 use anyhow::{anyhow, Result};
 use windows::core::{w, PCWSTR};
@@ -7,7 +11,8 @@ use windows::Win32::{
     UI::{
         Shell::{
             Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD,
-            NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW,
+            NIM_DELETE, NIM_MODIFY, NIN_BALLOONHIDE, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK,
+            NOTIFYICONDATAW,
         },
         WindowsAndMessaging::{
             AppendMenuW, CreateIconFromResourceEx, CreatePopupMenu, GetCursorPos,
@@ -20,30 +25,63 @@ use windows::Win32::{
 const ICON_BYTES: &[u8] = include_bytes!("../assets/icon.ico");
 const TEXT_CONFIGURE: PCWSTR = w!("Configure");
 const TEXT_STARTUP: PCWSTR = w!("Startup");
+const TEXT_PAUSE: PCWSTR = w!("Pause");
+const TEXT_STATISTICS: PCWSTR = w!("Statistics");
 const TEXT_EXIT: PCWSTR = w!("Exit");
 
 pub struct TrayIcon {
     data: NOTIFYICONDATAW,
+    /// Hold back balloons while the user doesn't want to be disturbed.
+    respect_focus_assist: bool,
+    /// Action of the balloon currently shown.
+    balloon_action: Option<BalloonAction>,
+}
+
+/// What clicking a balloon does. Balloons have no buttons, the whole balloon acts as one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalloonAction {
+    OpenConfig,
+    Pause,
+}
+
+impl BalloonAction {
+    /// Line appended to the message, telling what clicking does.
+    fn hint(&self) -> &'static str {
+        match self {
+            BalloonAction::OpenConfig => "Click to open it.",
+            BalloonAction::Pause => "Click to pause.",
+        }
+    }
 }
 
 impl TrayIcon {
-    pub fn create() -> Self {
+    pub fn create(respect_focus_assist: bool) -> Self {
         let data = Self::create_nid();
-        Self { data }
+        Self {
+            data,
+            respect_focus_assist,
+            balloon_action: None,
+        }
+    }
+
+    pub fn set_respect_focus_assist(&mut self, value: bool) {
+        self.respect_focus_assist = value;
     }
 
     pub fn register(&mut self, hwnd: HWND) -> Result<()> {
         self.data.hWnd = hwnd;
         unsafe { Shell_NotifyIconW(NIM_ADD, &self.data) }
             .ok()
-            .map_err(|e| anyhow!("Fail to add trayicon, {}", e))
+            .map_err(|e| anyhow!("Fail to add trayicon, {}", e))?;
+        set_trayicon_window(hwnd);
+        Ok(())
     }
 
     pub fn exist(&mut self) -> bool {
         unsafe { Shell_NotifyIconW(NIM_MODIFY, &self.data) }.as_bool()
     }
 
-    pub fn show(&mut self, startup: bool) -> Result<()> {
+    pub fn show(&mut self, startup: bool, paused: bool) -> Result<()> {
         let hwnd = self.data.hWnd;
         let mut cursor = POINT::default();
         unsafe {
@@ -52,7 +90,7 @@ impl TrayIcon {
                 .map_err(|e| anyhow!("Fail to set foreground window, {}", e))?;
             GetCursorPos(&mut cursor).map_err(|e| anyhow!("Fail to get cursor pos, {}", e))?;
             let hmenu = self
-                .create_menu(startup)
+                .create_menu(startup, paused)
                 .map_err(|e| anyhow!("Fail to create menu, {}", e))?;
             TrackPopupMenu(
                 hmenu,
@@ -97,25 +135,45 @@ impl TrayIcon {
         }
     }
 
-    fn create_menu(&mut self, startup: bool) -> Result<HMENU> {
+    fn create_menu(&mut self, startup: bool, paused: bool) -> Result<HMENU> {
         let startup_flags = if startup { MF_CHECKED } else { MF_UNCHECKED };
+        let pause_flags = if paused { MF_CHECKED } else { MF_UNCHECKED };
         unsafe {
             let hmenu = CreatePopupMenu().map_err(|err| anyhow!("Failed to create menu, {err}"))?;
             AppendMenuW(hmenu, MF_STRING, IDM_CONFIGURE as usize, TEXT_CONFIGURE)?;
             AppendMenuW(hmenu, startup_flags, IDM_STARTUP as usize, TEXT_STARTUP)?;
+            AppendMenuW(hmenu, pause_flags, IDM_PAUSE as usize, TEXT_PAUSE)?;
+            AppendMenuW(hmenu, MF_STRING, IDM_STATISTICS as usize, TEXT_STATISTICS)?;
             AppendMenuW(hmenu, MF_STRING, IDM_EXIT as usize, TEXT_EXIT)?;
             Ok(hmenu)
         }
     }
 
     pub fn show_balloon(&mut self, title: &str, message: &str) -> Result<()> {
+        self.show_balloon_with_action(title, message, None)
+            .map(|_| ())
+    }
+
+    /// Shows a balloon which runs the action when clicked, the action's hint is appended to the
+    /// message. Whether it was shown, Focus Assist may suppress it.
+    pub fn show_balloon_with_action(
+        &mut self,
+        title: &str,
+        message: &str,
+        action: Option<BalloonAction>,
+    ) -> Result<bool> {
+        self.balloon_action = None;
+        if self.respect_focus_assist && is_do_not_disturb() {
+            debug!("balloon suppressed: {message}");
+            return Ok(false);
+        }
         let mut info_title: Vec<u16> = title.encode_utf16().collect();
         info_title.resize(63, 0);
         info_title.push(0);
         // This conversion is infallible because we've resized to exactly 64 elements
         let info_title: [u16; 64] = info_title.try_into().expect("info_title buffer size mismatch");
 
-        let mut info: Vec<u16> = message.encode_utf16().collect();
+        let mut info = balloon_text(message, action);
         info.resize(255, 0);
         info.push(0);
         // This conversion is infallible because we've resized to exactly 256 elements
@@ -133,7 +191,20 @@ impl TrayIcon {
 
         // Reset flags
         self.data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
-        Ok(())
+        self.balloon_action = action;
+        Ok(true)
+    }
+
+    /// Returns the action to run for a balloon notification of the tray icon message.
+    pub fn on_balloon_event(&mut self, event: u32) -> Option<BalloonAction> {
+        match event {
+            NIN_BALLOONUSERCLICK => self.balloon_action.take(),
+            NIN_BALLOONTIMEOUT | NIN_BALLOONHIDE => {
+                self.balloon_action = None;
+                None
+            }
+            _ => None,
+        }
     }
 }
 
@@ -143,5 +214,43 @@ impl Drop for TrayIcon {
         unsafe {
             let _ = Shell_NotifyIconW(NIM_DELETE, &self.data);
         }
+        set_trayicon_window(HWND::default());
+    }
+}
+
+/// Text of a balloon as UTF-16 within its 255 units, the message is cut short rather than
+/// the hint of the action.
+fn balloon_text(message: &str, action: Option<BalloonAction>) -> Vec<u16> {
+    let hint: Vec<u16> = match action {
+        Some(action) => format!("\n{}", action.hint()).encode_utf16().collect(),
+        None => vec![],
+    };
+    let mut text = vec![];
+    for c in message.chars() {
+        if text.len() + c.len_utf16() + hint.len() > 255 {
+            break;
+        }
+        let mut buf = [0; 2];
+        text.extend_from_slice(c.encode_utf16(&mut buf));
+    }
+    text.extend(hint);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balloon_text() {
+        let text = String::from_utf16(&balloon_text("Paused", None)).unwrap();
+        assert_eq!(text, "Paused");
+        let text = balloon_text(&"x".repeat(300), Some(BalloonAction::OpenConfig));
+        assert_eq!(text.len(), 255);
+        assert!(
+            String::from_utf16(&text)
+                .unwrap()
+                .ends_with("x\nClick to open it.")
+        );
     }
 }
@@ -1,6 +1,7 @@
 use super::to_wstring;
 
 use std::{
+    collections::HashMap,
     fs::File,
     io::{BufReader, Read},
     mem,
@@ -12,19 +13,33 @@ use indexmap::IndexMap;
 use windows::{
     core::PCWSTR,
     Win32::{
-        Foundation::{COLORREF, HWND, WPARAM},
+        Foundation::{COLORREF, GENERIC_READ, HWND, SIZE, WPARAM},
         Graphics::Gdi::{
-            CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetObjectW, GetPixel, ReleaseDC,
-            SelectObject, BITMAP, HGDIOBJ,
+            CreateBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, GetDIBits,
+            GetObjectW, GetPixel, ReleaseDC, SelectObject, BITMAP, BITMAPINFO, BITMAPINFOHEADER,
+            BI_RGB, DIB_RGB_COLORS, HBITMAP, HGDIOBJ,
         },
+        Graphics::Imaging::{
+            CLSID_WICImagingFactory, GUID_WICPixelFormat32bppBGRA, IWICBitmapSource,
+            IWICImagingFactory, IWICPalette, WICBitmapDitherTypeNone,
+            WICBitmapInterpolationModeFant, WICBitmapPaletteTypeCustom,
+            WICDecodeMetadataCacheOnDemand,
+        },
+        System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
         Storage::FileSystem::FILE_ATTRIBUTE_NORMAL,
         UI::{
             Controls::IImageList,
-            Shell::{SHGetFileInfoW, SHGetImageList, SHFILEINFOW, SHGFI_SYSICONINDEX},
+            Shell::{
+                IShellItemImageFactory, SHCreateItemFromParsingName, SHGetFileInfoW,
+                SHGetImageList, SHGetStockIconInfo, SHFILEINFOW, SHGFI_SYSICONINDEX, SHGSI_ICON,
+                SHGSI_LARGEICON, SHIL_JUMBO, SHSTOCKICONINFO, SIIGBF_BIGGERSIZEOK,
+                SIIGBF_ICONONLY, SIID_SHIELD,
+            },
             WindowsAndMessaging::{
-                CopyIcon, CreateIconFromResourceEx, GetIconInfo, LoadIconW, LoadImageW,
-                SendMessageW, GCL_HICON, HICON, ICONINFO, ICON_BIG, IDI_APPLICATION, IMAGE_ICON,
-                LR_DEFAULTCOLOR, LR_DEFAULTSIZE, LR_LOADFROMFILE, WM_GETICON,
+                CopyIcon, CreateIconFromResourceEx, CreateIconIndirect, DestroyIcon, GetIconInfo,
+                LoadIconW, LoadImageW, SendMessageW, GCL_HICON, HICON, ICONINFO, ICON_BIG,
+                IDI_APPLICATION, IMAGE_ICON, LR_DEFAULTCOLOR, LR_DEFAULTSIZE, LR_LOADFROMFILE,
+                WM_GETICON,
             },
         },
     },
@@ -32,10 +47,16 @@ use windows::{
 use xml::reader::XmlEvent;
 use xml::EventReader;
 
+/// Size the icons are extracted at, the largest the overlay draws them at.
+const ICON_SIZE: i32 = 256;
+
+/// Icon of the app for drawing at `size` pixels, scaled down from a larger one rather than
+/// scaled up from a smaller one where the file has it.
 pub fn get_app_icon(
     override_icons: &IndexMap<String, String>,
     module_path: &str,
     hwnd: HWND,
+    size: i32,
 ) -> HICON {
     let module_path_lc = module_path.to_lowercase();
     if let Some((_, v)) = override_icons
@@ -43,25 +64,25 @@ pub fn get_app_icon(
         .find(|(k, _)| module_path_lc.contains(*k))
     {
         let mut override_path = PathBuf::from(v);
-        if !override_path.is_absolute() {
-            if let Some(module_dir) = Path::new(module_path).parent() {
-                override_path = module_dir.join(override_path);
-            }
+        if !override_path.is_absolute()
+            && let Some(module_dir) = Path::new(module_path).parent()
+        {
+            override_path = module_dir.join(override_path);
         }
         if let Some(icon) = load_image_as_hicon(override_path) {
             return icon;
         }
     }
 
-    if module_path.starts_with("C:\\Program Files\\WindowsApps") {
-        if let Some(icon) =
+    if module_path.starts_with("C:\\Program Files\\WindowsApps")
+        && let Some(icon) =
             get_appx_logo_path(module_path).and_then(|image_path| load_image_as_hicon(&image_path))
-        {
-            return icon;
-        }
+    {
+        return icon;
     }
 
     get_exe_icon(module_path)
+        .or_else(|| get_shell_item_icon(module_path, size))
         .or_else(|| get_window_icon(hwnd))
         .unwrap_or_else(fallback_icon)
 }
@@ -91,18 +112,18 @@ fn get_appx_logo_path(module_path: &str) -> Option<PathBuf> {
                     matched = attributes
                         .iter()
                         .any(|v| v.name.local_name == "Executable" && v.value == executable);
-                } else if xpath == "Package/Applications/Application/VisualElements" && matched {
-                    if let Some(value) = attributes
+                } else if xpath == "Package/Applications/Application/VisualElements"
+                    && matched
+                    && let Some(value) = attributes
                         .iter()
                         .find(|v| {
                             ["Square44x44Logo", "Square30x30Logo", "SmallLogo"]
                                 .contains(&v.name.local_name.as_str())
                         })
                         .map(|v| v.value.clone())
-                    {
-                        logo_value = Some(value);
-                        break;
-                    }
+                {
+                    logo_value = Some(value);
+                    break;
                 }
                 depth += 1;
             }
@@ -136,26 +157,190 @@ pub fn load_image_as_hicon<T: AsRef<Path>>(image_path: T) -> Option<HICON> {
     if !image_path.exists() {
         return None;
     }
-    if let Some("ico") = image_path.extension().and_then(|v| v.to_str()) {
+    let extension = image_path
+        .extension()
+        .map(|v| v.to_string_lossy().to_lowercase());
+    if let Some("ico") = extension.as_deref() {
         let icon_path = to_wstring(image_path.to_string_lossy().as_ref());
         unsafe {
             LoadImageW(
                 None,
                 PCWSTR(icon_path.as_ptr()),
                 IMAGE_ICON,
-                256,
-                256,
+                ICON_SIZE,
+                ICON_SIZE,
                 LR_LOADFROMFILE | LR_DEFAULTSIZE,
             )
         }
         .ok()
         .map(|v| HICON(v.0))
     } else {
-        let mut logo_file = File::open(image_path).ok()?;
-        let mut buffer = vec![];
-        logo_file.read_to_end(&mut buffer).ok()?;
-        unsafe { CreateIconFromResourceEx(&buffer, true, 0x30000, 100, 100, LR_DEFAULTCOLOR) }.ok()
+        load_image_with_wic(image_path).or_else(|| {
+            let mut logo_file = File::open(image_path).ok()?;
+            let mut buffer = vec![];
+            logo_file.read_to_end(&mut buffer).ok()?;
+            unsafe { CreateIconFromResourceEx(&buffer, true, 0x30000, 100, 100, LR_DEFAULTCOLOR) }
+                .ok()
+        })
+    }
+}
+
+/// Decodes any image WIC has a codec for, e.g. png, jpg, bmp or gif, shrunk to the icon size
+/// if larger.
+fn load_image_with_wic(image_path: &Path) -> Option<HICON> {
+    let path = to_wstring(image_path.to_string_lossy().as_ref());
+    unsafe {
+        let factory: IWICImagingFactory =
+            CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER).ok()?;
+        let decoder = factory
+            .CreateDecoderFromFilename(
+                PCWSTR(path.as_ptr()),
+                None,
+                GENERIC_READ,
+                WICDecodeMetadataCacheOnDemand,
+            )
+            .ok()?;
+        let frame = decoder.GetFrame(0).ok()?;
+        let converter = factory.CreateFormatConverter().ok()?;
+        converter
+            .Initialize(
+                &frame,
+                &GUID_WICPixelFormat32bppBGRA,
+                WICBitmapDitherTypeNone,
+                None::<&IWICPalette>,
+                0.0,
+                WICBitmapPaletteTypeCustom,
+            )
+            .ok()?;
+
+        let (mut width, mut height) = (0, 0);
+        converter.GetSize(&mut width, &mut height).ok()?;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let mut source: IWICBitmapSource = converter.into();
+        let max_size = ICON_SIZE as u32;
+        if width > max_size || height > max_size {
+            let scale = max_size as f32 / width.max(height) as f32;
+            width = ((width as f32 * scale) as u32).max(1);
+            height = ((height as f32 * scale) as u32).max(1);
+            let scaler = factory.CreateBitmapScaler().ok()?;
+            scaler
+                .Initialize(&source, width, height, WICBitmapInterpolationModeFant)
+                .ok()?;
+            source = scaler.into();
+        }
+
+        let stride = width * 4;
+        let mut pixels = vec![0u8; (stride * height) as usize];
+        source
+            .CopyPixels(std::ptr::null(), stride, &mut pixels)
+            .ok()?;
+        let hbitmap = CreateBitmap(
+            width as i32,
+            height as i32,
+            1,
+            32,
+            Some(pixels.as_ptr() as _),
+        );
+        if hbitmap.is_invalid() {
+            return None;
+        }
+        let hicon = bitmap_to_icon(hbitmap);
+        let _ = DeleteObject(hbitmap.into());
+        hicon
+    }
+}
+
+/// The UAC shield, the caller destroys the icon.
+pub fn get_shield_icon() -> Option<HICON> {
+    let mut info = SHSTOCKICONINFO {
+        cbSize: mem::size_of::<SHSTOCKICONINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { SHGetStockIconInfo(SIID_SHIELD, SHGSI_ICON | SHGSI_LARGEICON, &mut info) }.ok()?;
+    Some(info.hIcon)
+}
+
+/// Main color of the icon as `0xRRGGBB`, `None` for gray or unreadable icons.
+pub fn get_icon_accent_color(hicon: HICON) -> Option<u32> {
+    dominant_color(&get_icon_pixels(hicon)?)
+}
+
+/// Pixels of the icon as BGRA, row by row from the top.
+fn get_icon_pixels(hicon: HICON) -> Option<Vec<[u8; 4]>> {
+    unsafe {
+        let mut icon_info = ICONINFO::default();
+        GetIconInfo(hicon, &mut icon_info).ok()?;
+        let mut bmp = BITMAP::default();
+        let pixels = if icon_info.hbmColor.is_invalid()
+            || 0 == GetObjectW(
+                icon_info.hbmColor.into(),
+                std::mem::size_of::<BITMAP>() as i32,
+                Some(&mut bmp as *mut _ as *mut _),
+            ) {
+            None
+        } else {
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: bmp.bmWidth,
+                    // Negative for rows from the top
+                    biHeight: -bmp.bmHeight,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut pixels = vec![[0u8; 4]; (bmp.bmWidth * bmp.bmHeight) as usize];
+            let hdc = GetDC(None);
+            let lines = GetDIBits(
+                hdc,
+                icon_info.hbmColor,
+                0,
+                bmp.bmHeight as u32,
+                Some(pixels.as_mut_ptr() as _),
+                &mut info,
+                DIB_RGB_COLORS,
+            );
+            ReleaseDC(None, hdc);
+            (lines > 0).then_some(pixels)
+        };
+        let _ = DeleteObject(icon_info.hbmColor.into());
+        let _ = DeleteObject(icon_info.hbmMask.into());
+        pixels
+    }
+}
+
+/// Most common color among the colorful pixels, quantized to 4 bits per channel and averaged
+/// within that bucket. Transparent, gray, very dark and very light pixels are left out.
+fn dominant_color(pixels: &[[u8; 4]]) -> Option<u32> {
+    // Icons without an alpha channel have it all 0
+    let has_alpha = pixels.iter().any(|v| v[3] != 0);
+    let mut buckets: HashMap<u16, (u32, [u32; 3])> = HashMap::new();
+    for &[b, g, r, a] in pixels {
+        if has_alpha && a < 128 {
+            continue;
+        }
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        if max - min < 48 || max < 48 || min > 224 {
+            continue;
+        }
+        let key = ((r as u16 >> 4) << 8) | ((g as u16 >> 4) << 4) | (b as u16 >> 4);
+        let (count, sums) = buckets.entry(key).or_default();
+        *count += 1;
+        sums[0] += r as u32;
+        sums[1] += g as u32;
+        sums[2] += b as u32;
     }
+    // Ties go to the same bucket every time
+    let (_, (count, [r, g, b])) = buckets
+        .into_iter()
+        .max_by_key(|(key, (count, _))| (*count, *key))?;
+    Some(((r / count) << 16) | ((g / count) << 8) | (b / count))
 }
 
 fn fallback_icon() -> HICON {
@@ -179,15 +364,15 @@ pub fn get_window_icon(hwnd: HWND) -> Option<HICON> {
 
 fn get_exe_icon(module_path: &str) -> Option<HICON> {
     unsafe {
-        let r: ::windows::core::Result<IImageList> = SHGetImageList(0x04);
+        let r: ::windows::core::Result<IImageList> = SHGetImageList(SHIL_JUMBO as i32);
         match r {
             ::windows::core::Result::Ok(list) => {
                 if let Some(info) = get_shfileinfo(module_path) {
                     let r = list.GetIcon(info.iIcon, 1u32);
                     match r {
                         Ok(hicon) => {
-                            let (x, y) = get_icon_size(hicon)?;
-                            if x < 64 && y < 64 {
+                            if is_padded_icon(hicon) {
+                                let _ = DestroyIcon(hicon);
                                 return None;
                             }
                             Some(hicon)
@@ -203,6 +388,54 @@ fn get_exe_icon(module_path: &str) -> Option<HICON> {
     }
 }
 
+/// Whether the jumbo icon is a small icon padded to 256px, as the system image list holds
+/// for files without a large icon. Drawn at 256px it would be a speck in the corner.
+fn is_padded_icon(hicon: HICON) -> bool {
+    get_icon_size(hicon).is_none_or(|(x, y)| x < 64 && y < 64)
+}
+
+/// Icon of the file as Explorer shows it at `size` pixels, scaled up from the largest size the
+/// file has. Used for files whose jumbo icon is padded.
+fn get_shell_item_icon(module_path: &str, size: i32) -> Option<HICON> {
+    let path = to_wstring(module_path);
+    unsafe {
+        let factory: IShellItemImageFactory =
+            SHCreateItemFromParsingName(PCWSTR(path.as_ptr()), None).ok()?;
+        let size = SIZE { cx: size, cy: size };
+        let hbitmap = factory
+            .GetImage(size, SIIGBF_ICONONLY | SIIGBF_BIGGERSIZEOK)
+            .ok()?;
+        let hicon = bitmap_to_icon(hbitmap);
+        let _ = DeleteObject(hbitmap.into());
+        hicon
+    }
+}
+
+/// Wraps a 32-bit bitmap into an icon, its alpha channel is the transparency.
+fn bitmap_to_icon(hbitmap: HBITMAP) -> Option<HICON> {
+    unsafe {
+        let mut bmp = BITMAP::default();
+        if 0 == GetObjectW(
+            hbitmap.into(),
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut bmp as *mut _ as *mut _),
+        ) {
+            return None;
+        }
+        // Ignored for 32-bit color bitmaps, but required all the same
+        let hbm_mask = CreateBitmap(bmp.bmWidth, bmp.bmHeight, 1, 1, None);
+        let icon_info = ICONINFO {
+            fIcon: true.into(),
+            hbmColor: hbitmap,
+            hbmMask: hbm_mask,
+            ..Default::default()
+        };
+        let hicon = CreateIconIndirect(&icon_info).ok();
+        let _ = DeleteObject(hbm_mask.into());
+        hicon
+    }
+}
+
 fn get_shfileinfo(module_path: &str) -> Option<SHFILEINFOW> {
     unsafe {
         let mut p_path: Vec<u16> = module_path
@@ -284,3 +517,39 @@ fn get_icon_size(hicon: HICON) -> Option<(i32, i32)> {
         Some((max_x - min_x + 1, max_y - min_y + 1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dominant_color() {
+        let red = [0x10, 0x10, 0xe0, 0xff];
+        let blue = [0xe0, 0x10, 0x10, 0xff];
+        let gray = [0x80, 0x80, 0x80, 0xff];
+        let transparent_blue = [0xe0, 0x10, 0x10, 0x00];
+        assert_eq!(
+            dominant_color(&[
+                red,
+                red,
+                blue,
+                gray,
+                gray,
+                gray,
+                transparent_blue,
+                transparent_blue
+            ]),
+            Some(0xe01010)
+        );
+        // Without an alpha channel every pixel counts
+        assert_eq!(
+            dominant_color(&[
+                [0xe0, 0x10, 0x10, 0],
+                [0xe0, 0x10, 0x10, 0],
+                [0x10, 0x10, 0xe0, 0]
+            ]),
+            Some(0x1010e0)
+        );
+        assert_eq!(dominant_color(&[gray, gray]), None);
+    }
+}
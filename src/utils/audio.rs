@@ -0,0 +1,56 @@
+use std::collections::HashSet;
+
+use windows::Win32::{
+    Media::Audio::{
+        AudioSessionStateActive, DEVICE_STATE_ACTIVE, IAudioSessionControl2, IAudioSessionManager2,
+        IMMDeviceEnumerator, MMDeviceEnumerator, eRender,
+    },
+    System::Com::{CLSCTX_ALL, CoCreateInstance},
+};
+use windows::core::{Interface, Result};
+
+use super::get_module_path;
+
+/// Module paths of the processes playing audio on any output device right now.
+pub fn get_audio_module_paths() -> HashSet<String> {
+    match get_audio_pids() {
+        Ok(pids) => pids.into_iter().filter_map(get_module_path).collect(),
+        Err(err) => {
+            debug!("Failed to list audio sessions, {err}");
+            HashSet::new()
+        }
+    }
+}
+
+fn get_audio_pids() -> Result<HashSet<u32>> {
+    let mut pids = HashSet::new();
+    unsafe {
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let devices = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+        for i in 0..devices.GetCount()? {
+            let manager: IAudioSessionManager2 = devices.Item(i)?.Activate(CLSCTX_ALL, None)?;
+            let sessions = manager.GetSessionEnumerator()?;
+            for j in 0..sessions.GetCount()? {
+                let Ok(session) = sessions.GetSession(j) else {
+                    continue;
+                };
+                // Inactive sessions belong to apps that played audio before, but are quiet now
+                if session.GetState().ok() != Some(AudioSessionStateActive) {
+                    continue;
+                }
+                // Sessions of exited processes fail, the other apps are still listed
+                let Ok(session) = session.cast::<IAudioSessionControl2>() else {
+                    continue;
+                };
+                // The system sounds session has PID 0
+                if let Ok(pid) = session.GetProcessId()
+                    && pid != 0
+                {
+                    pids.insert(pid);
+                }
+            }
+        }
+    }
+    Ok(pids)
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use windows::Win32::{
+    Foundation::CloseHandle,
+    System::Threading::{
+        CREATE_NO_WINDOW, CreateProcessW, PROCESS_CREATION_FLAGS, PROCESS_INFORMATION, STARTUPINFOW,
+    },
+    UI::{Shell::ShellExecuteW, WindowsAndMessaging::SW_SHOWNORMAL},
+};
+use windows::core::{PCWSTR, PWSTR, w};
+
+use super::to_wstring;
+
+/// Starts a new process of the executable, in the folder of the executable.
+pub fn launch_app(module_path: &str) -> Result<()> {
+    shell_execute(w!("open"), module_path)
+}
+
+/// Starts a new process of the executable as administrator, which prompts for consent.
+pub fn launch_app_elevated(module_path: &str) -> Result<()> {
+    shell_execute(w!("runas"), module_path)
+}
+
+/// Runs a command line without waiting for it to finish. Console programs get no window.
+pub fn run_command(command_line: &str) -> Result<()> {
+    create_process(command_line, CREATE_NO_WINDOW)
+}
+
+/// Starts a command line the user asked for, console programs get a window as usual.
+pub fn launch_command(command_line: &str) -> Result<()> {
+    create_process(command_line, PROCESS_CREATION_FLAGS(0))
+}
+
+fn create_process(command_line: &str, flags: PROCESS_CREATION_FLAGS) -> Result<()> {
+    let mut command_line_w = to_wstring(command_line);
+    let startup_info = STARTUPINFOW {
+        cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+    unsafe {
+        CreateProcessW(
+            PCWSTR::null(),
+            Some(PWSTR(command_line_w.as_mut_ptr())),
+            None,
+            None,
+            false,
+            flags,
+            None,
+            PCWSTR::null(),
+            &startup_info,
+            &mut process_info,
+        )
+        .map_err(|err| anyhow!("Failed to run '{command_line}', {err}"))?;
+        let _ = CloseHandle(process_info.hProcess);
+        let _ = CloseHandle(process_info.hThread);
+    }
+    Ok(())
+}
+
+fn shell_execute(operation: PCWSTR, module_path: &str) -> Result<()> {
+    let file = to_wstring(module_path);
+    let directory = Path::new(module_path)
+        .parent()
+        .map(|v| to_wstring(&v.to_string_lossy()));
+    let directory = directory
+        .as_ref()
+        .map(|v| PCWSTR(v.as_ptr()))
+        .unwrap_or(PCWSTR::null());
+    let ret = unsafe {
+        ShellExecuteW(
+            None,
+            operation,
+            PCWSTR(file.as_ptr()),
+            PCWSTR::null(),
+            directory,
+            SW_SHOWNORMAL,
+        )
+    };
+    // Values greater than 32 indicate success
+    if ret.0 as isize <= 32 {
+        bail!(
+            "Failed to launch '{module_path}', error code {}",
+            ret.0 as isize
+        );
+    }
+    Ok(())
+}
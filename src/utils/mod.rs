@@ -1,25 +1,44 @@
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VIRTUAL_KEY};
+
 mod admin;
 mod app_icon;
+mod audio;
 mod check_error;
 mod handle_wrapper;
+mod launch;
 mod regedit;
 mod scheduled_task;
+mod session;
 mod single_instance;
+mod virtual_desktop;
+mod win_api;
 mod window;
+mod window_composition;
 mod windows_theme;
 mod windows_version;
 
 pub use admin::*;
 pub use app_icon::*;
+pub use audio::*;
 pub use check_error::*;
 pub use handle_wrapper::*;
+pub use launch::*;
 pub use regedit::*;
 pub use scheduled_task::*;
+pub use session::*;
 pub use single_instance::*;
+pub use virtual_desktop::*;
+pub use win_api::*;
 pub use window::*;
+pub use window_composition::*;
 pub use windows_theme::*;
 pub use windows_version::*;
 
 pub fn to_wstring(value: &str) -> Vec<u16> {
     value.encode_utf16().chain(Some(0)).collect::<Vec<u16>>()
 }
+
+/// Whether the key was held down when the current message was generated.
+pub fn is_key_down(key: VIRTUAL_KEY) -> bool {
+    (unsafe { GetKeyState(key.0 as i32) }) < 0
+}
@@ -3,8 +3,8 @@ use windows::Win32::{
     Foundation::ERROR_FILE_NOT_FOUND,
     System::Registry::{
         HKEY, HKEY_CURRENT_USER, KEY_ALL_ACCESS, REG_DWORD_BIG_ENDIAN, REG_SZ, REG_VALUE_TYPE,
-        RRF_RT_REG_DWORD, RRF_RT_REG_SZ, RegCloseKey, RegDeleteValueW, RegGetValueW, RegOpenKeyExW,
-        RegSetValueExW,
+        RRF_RT_REG_BINARY, RRF_RT_REG_DWORD, RRF_RT_REG_SZ, RegCloseKey, RegDeleteValueW,
+        RegGetValueW, RegOpenKeyExW, RegSetValueExW,
     },
 };
 use windows::core::PCWSTR;
@@ -89,6 +89,47 @@ impl RegKey {
         Ok(value)
     }
 
+    pub fn get_binary(&self) -> Result<Vec<u8>> {
+        let mut size: u32 = 0;
+        let ret = unsafe {
+            RegGetValueW(
+                self.hkey,
+                None,
+                self.name,
+                RRF_RT_REG_BINARY,
+                None,
+                None,
+                Some(&mut size),
+            )
+        };
+        if ret.is_err() {
+            bail!(
+                "Fail to get reg value, {:?}",
+                windows::core::Error::from(ret)
+            );
+        }
+        let mut value = vec![0u8; size as usize];
+        let ret = unsafe {
+            RegGetValueW(
+                self.hkey,
+                None,
+                self.name,
+                RRF_RT_REG_BINARY,
+                None,
+                Some(value.as_mut_ptr() as *mut _),
+                Some(&mut size),
+            )
+        };
+        if ret.is_err() {
+            bail!(
+                "Fail to get reg value, {:?}",
+                windows::core::Error::from(ret)
+            );
+        }
+        value.truncate(size as usize);
+        Ok(value)
+    }
+
     pub fn set_value(&self, value: &[u8]) -> Result<()> {
         unsafe { RegSetValueExW(self.hkey, self.name, None, REG_SZ, Some(value)) }
             .ok()
@@ -146,10 +146,9 @@ fn get_author_and_userid() -> WindowsResult<(String, String)> {
             0,
             &mut token_info_length,
         )
-    } {
-        if err != ERROR_INSUFFICIENT_BUFFER.into() {
-            return Err(err);
-        }
+    } && err != ERROR_INSUFFICIENT_BUFFER.into()
+    {
+        return Err(err);
     }
 
     let mut token_user = Vec::<u8>::with_capacity(token_info_length as usize);
@@ -0,0 +1,35 @@
+use windows::Win32::UI::{
+    Shell::{
+        QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME, QUNS_RUNNING_D3D_FULL_SCREEN,
+        SHQueryUserNotificationState,
+    },
+    WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION},
+};
+
+/// Whether the current session is a Remote Desktop session rather than the console.
+pub fn is_remote_session() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// Whether notifications should be held back, e.g. with Focus Assist on, while presenting
+/// or while an app runs full screen.
+pub fn is_do_not_disturb() -> bool {
+    match unsafe { SHQueryUserNotificationState() } {
+        Ok(state) => [
+            QUNS_BUSY,
+            QUNS_RUNNING_D3D_FULL_SCREEN,
+            QUNS_PRESENTATION_MODE,
+            QUNS_QUIET_TIME,
+        ]
+        .contains(&state),
+        Err(err) => {
+            debug!("Failed to query notification state, {err}");
+            false
+        }
+    }
+}
+
+/// Whether an app runs in exclusive full screen, as games do with Direct3D.
+pub fn is_exclusive_full_screen() -> bool {
+    unsafe { SHQueryUserNotificationState() }.is_ok_and(|v| v == QUNS_RUNNING_D3D_FULL_SCREEN)
+}
@@ -7,7 +7,26 @@ use windows::Win32::{
 };
 use windows::core::PCWSTR;
 
-pub const RELOAD_CONFIG_EVENT_NAME: &str = "WindowSwitcherReloadConfigEvent";
+const MUTEX_NAME: &str = "WindowSwitcherMutex";
+const RELOAD_CONFIG_EVENT_NAME: &str = "WindowSwitcherReloadConfigEvent";
+const SECOND_INSTANCE_EVENT_NAME: &str = "WindowSwitcherSecondInstanceEvent";
+
+/// Returns the name of the event used to signal the instance to reload its configuration.
+pub fn reload_config_event_name(instance_id: Option<&str>) -> String {
+    instance_object_name(RELOAD_CONFIG_EVENT_NAME, instance_id)
+}
+
+/// Returns the name of the event used to tell the instance that it was launched again.
+pub fn second_instance_event_name(instance_id: Option<&str>) -> String {
+    instance_object_name(SECOND_INSTANCE_EVENT_NAME, instance_id)
+}
+
+fn instance_object_name(name: &str, instance_id: Option<&str>) -> String {
+    match instance_id {
+        Some(id) => format!("{name}-{id}"),
+        None => name.to_string(),
+    }
+}
 
 /// A struct representing one running instance.
 pub struct SingleInstance {
@@ -23,8 +42,10 @@ unsafe impl Sync for SingleInstance {}
 
 impl SingleInstance {
     /// Returns a new SingleInstance object.
-    pub fn create(name: &str) -> Result<Self> {
-        let name = to_wstring(name);
+    ///
+    /// Instances with different ids don't block each other.
+    pub fn create(instance_id: Option<&str>) -> Result<Self> {
+        let name = to_wstring(&instance_object_name(MUTEX_NAME, instance_id));
         let handle = unsafe { CreateMutexW(None, true, PCWSTR(name.as_ptr())) }
             .map_err(|err| anyhow!("Fail to setup single instance, {err}"))?;
         let handle =
@@ -42,16 +63,15 @@ impl SingleInstance {
     }
 
     /// Signals the running instance to reload its configuration.
-    pub fn signal_reload_config() -> Result<()> {
-        let event_name = to_wstring(RELOAD_CONFIG_EVENT_NAME);
-        let event = unsafe { CreateEventW(None, false, false, PCWSTR(event_name.as_ptr())) }
-            .map_err(|err| anyhow!("Failed to open reload config event, {err}"))?;
-        unsafe { SetEvent(event) }
-            .map_err(|err| anyhow!("Failed to signal reload config, {err}"))?;
-        unsafe {
-            let _ = CloseHandle(event);
-        }
-        Ok(())
+    pub fn signal_reload_config(instance_id: Option<&str>) -> Result<()> {
+        signal_event(&reload_config_event_name(instance_id))
+            .map_err(|err| anyhow!("Failed to signal reload config, {err}"))
+    }
+
+    /// Signals the running instance that the program was launched again.
+    pub fn signal_second_instance(instance_id: Option<&str>) -> Result<()> {
+        signal_event(&second_instance_event_name(instance_id))
+            .map_err(|err| anyhow!("Failed to signal the running instance, {err}"))
     }
 }
 
@@ -65,3 +85,14 @@ impl Drop for SingleInstance {
         }
     }
 }
+
+fn signal_event(name: &str) -> Result<()> {
+    let event_name = to_wstring(name);
+    let event = unsafe { CreateEventW(None, false, false, PCWSTR(event_name.as_ptr())) }
+        .map_err(|err| anyhow!("Failed to open event, {err}"))?;
+    let ret = unsafe { SetEvent(event) };
+    unsafe {
+        let _ = CloseHandle(event);
+    }
+    ret.map_err(|err| anyhow!("Failed to set event, {err}"))
+}
@@ -0,0 +1,173 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    System::{
+        Com::{CLSCTX_ALL, CoCreateInstance},
+        Registry::{
+            HKEY, HKEY_CURRENT_USER, KEY_NOTIFY, REG_NOTIFY_CHANGE_LAST_SET,
+            REG_NOTIFY_CHANGE_NAME, RegNotifyChangeKeyValue, RegOpenKeyExW,
+        },
+    },
+    UI::{
+        Shell::{IVirtualDesktopManager, VirtualDesktopManager},
+        WindowsAndMessaging::PostMessageW,
+    },
+};
+use windows::core::{GUID, PCWSTR, w};
+
+use super::{RegKey, to_wstring};
+
+const VIRTUAL_DESKTOPS_KEY: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops";
+
+thread_local! {
+    /// Created on first use. It belongs to the shell, so it is dropped when Explorer restarts.
+    static MANAGER: RefCell<Option<IVirtualDesktopManager>> = const { RefCell::new(None) };
+}
+
+/// Returns the ID of the virtual desktop the window is on.
+pub fn get_window_desktop_id(hwnd: HWND) -> Option<GUID> {
+    MANAGER.with_borrow_mut(|manager| {
+        if manager.is_none() {
+            match unsafe { CoCreateInstance(&VirtualDesktopManager, None, CLSCTX_ALL) } {
+                Ok(v) => *manager = Some(v),
+                Err(err) => {
+                    error!("Failed to create virtual desktop manager, {err}");
+                    return None;
+                }
+            }
+        }
+        let id = unsafe { manager.as_ref()?.GetWindowDesktopId(hwnd) }.ok()?;
+        (id != GUID::zeroed()).then_some(id)
+    })
+}
+
+/// Returns the number of the virtual desktop the window is on, counting from 1 in the order
+/// shown by Task View, `None` when the window is on the current desktop. `desktops` are the
+/// IDs of `list_virtual_desktop_ids`, read once for all windows.
+pub fn get_window_desktop_number(hwnd: HWND, desktops: &[GUID]) -> Option<usize> {
+    // Creates the manager when needed
+    let id = get_window_desktop_id(hwnd)?;
+    // Windows pinned to all desktops are on the current one too
+    let on_current = MANAGER.with_borrow(|manager| {
+        unsafe { manager.as_ref()?.IsWindowOnCurrentVirtualDesktop(hwnd) }
+            .ok()
+            .map(|v| v.as_bool())
+    });
+    if on_current.unwrap_or(true) {
+        return None;
+    }
+    desktops.iter().position(|v| *v == id).map(|i| i + 1)
+}
+
+/// IDs of the virtual desktops in the order shown by Task View.
+pub fn list_virtual_desktop_ids() -> Vec<GUID> {
+    list_virtual_desktops()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect()
+}
+
+pub fn reset_virtual_desktop_manager() {
+    MANAGER.with_borrow_mut(|manager| *manager = None);
+}
+
+/// Finds the virtual desktops matching the values, which are desktop names, e.g. `Personal`
+/// or `Desktop 2` for unnamed ones, or desktop IDs.
+pub fn resolve_virtual_desktops(values: &[String]) -> HashSet<GUID> {
+    let desktops = list_virtual_desktops();
+    values
+        .iter()
+        .filter_map(|value| {
+            let value = value.trim();
+            if let Ok(id) = GUID::try_from(value.trim_start_matches('{').trim_end_matches('}')) {
+                return Some(id);
+            }
+            desktops
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(value))
+                .map(|(id, _)| *id)
+        })
+        .collect()
+}
+
+/// Posts `msg` to the window whenever the virtual desktops change, e.g. one is added or renamed.
+pub fn watch_virtual_desktops(hwnd: HWND, msg: u32) -> Result<()> {
+    let key = to_wstring(VIRTUAL_DESKTOPS_KEY);
+    let mut hkey = HKEY::default();
+    unsafe {
+        RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            PCWSTR(key.as_ptr()),
+            None,
+            KEY_NOTIFY,
+            &mut hkey,
+        )
+    }
+    .ok()
+    .map_err(|err| anyhow!("Failed to open virtual desktops key, {err}"))?;
+
+    let hwnd_ptr = hwnd.0 as isize;
+    let hkey_ptr = hkey.0 as isize;
+    std::thread::spawn(move || {
+        let hkey = HKEY(hkey_ptr as _);
+        // Blocks until something below the key changed, switching the desktop does as well
+        while unsafe {
+            RegNotifyChangeKeyValue(
+                hkey,
+                true,
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                None,
+                false,
+            )
+        }
+        .is_ok()
+        {
+            let _ = unsafe { PostMessageW(Some(HWND(hwnd_ptr as _)), msg, WPARAM(0), LPARAM(0)) };
+        }
+    });
+    Ok(())
+}
+
+/// Lists the IDs and names of the virtual desktops, in the order shown by Task View.
+fn list_virtual_desktops() -> Vec<(GUID, String)> {
+    let key = to_wstring(VIRTUAL_DESKTOPS_KEY);
+    let ids = match RegKey::new_hkcu(PCWSTR(key.as_ptr()), w!("VirtualDesktopIDs"))
+        .and_then(|k| k.get_binary())
+    {
+        Ok(v) => v,
+        Err(err) => {
+            debug!("Failed to read virtual desktops, {err}");
+            return vec![];
+        }
+    };
+    ids.chunks_exact(16)
+        .enumerate()
+        .map(|(i, bytes)| {
+            let id = GUID::from_values(
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                u16::from_le_bytes([bytes[4], bytes[5]]),
+                u16::from_le_bytes([bytes[6], bytes[7]]),
+                [
+                    bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14],
+                    bytes[15],
+                ],
+            );
+            let name = get_virtual_desktop_name(id).unwrap_or_else(|| format!("Desktop {}", i + 1));
+            (id, name)
+        })
+        .collect()
+}
+
+fn get_virtual_desktop_name(id: GUID) -> Option<String> {
+    let key = to_wstring(&format!(r"{VIRTUAL_DESKTOPS_KEY}\Desktops\{{{id:?}}}"));
+    let value = RegKey::new_hkcu(PCWSTR(key.as_ptr()), w!("Name"))
+        .and_then(|k| k.get_value())
+        .ok()??;
+    let name = String::from_utf16_lossy(&value);
+    let name = name.trim_end_matches('\0');
+    (!name.is_empty()).then(|| name.to_string())
+}
@@ -0,0 +1,171 @@
+use anyhow::Result;
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use windows::Win32::{Foundation::HWND, UI::WindowsAndMessaging::HICON};
+use windows::core::GUID;
+
+use super::{
+    ListWindowsOptions, get_app_icon, get_audio_module_paths, get_foreground_window,
+    get_window_desktop_number, get_window_pid, get_z_order, is_elevated_window, is_iconic_window,
+    is_process_elevated, list_virtual_desktop_ids, list_windows,
+};
+
+/// Win32 calls the switching logic depends on, so it can be covered by tests with a mock.
+pub trait WinApi {
+    fn list_windows(
+        &self,
+        options: &ListWindowsOptions,
+    ) -> Result<IndexMap<String, Vec<(HWND, String)>>>;
+    fn get_z_order(&self) -> Result<Vec<HWND>>;
+    fn is_iconic_window(&self, hwnd: HWND) -> bool;
+    fn is_elevated_window(&self, hwnd: HWND) -> bool;
+    /// PID of the window and whether its process is elevated, `None` if that is unknown.
+    fn get_window_process(&self, hwnd: HWND) -> (u32, Option<bool>);
+    fn list_virtual_desktop_ids(&self) -> Vec<GUID>;
+    fn get_window_desktop_number(&self, hwnd: HWND, desktops: &[GUID]) -> Option<usize>;
+    fn get_audio_module_paths(&self) -> HashSet<String>;
+    fn get_foreground_window(&self) -> HWND;
+    fn get_app_icon(
+        &self,
+        override_icons: &IndexMap<String, String>,
+        module_path: &str,
+        hwnd: HWND,
+        size: i32,
+    ) -> HICON;
+}
+
+#[derive(Debug, Default)]
+pub struct Win32Api;
+
+impl WinApi for Win32Api {
+    fn list_windows(
+        &self,
+        options: &ListWindowsOptions,
+    ) -> Result<IndexMap<String, Vec<(HWND, String)>>> {
+        list_windows(options)
+    }
+
+    fn get_z_order(&self) -> Result<Vec<HWND>> {
+        get_z_order()
+    }
+
+    fn is_iconic_window(&self, hwnd: HWND) -> bool {
+        is_iconic_window(hwnd)
+    }
+
+    fn is_elevated_window(&self, hwnd: HWND) -> bool {
+        is_elevated_window(hwnd)
+    }
+
+    fn get_window_process(&self, hwnd: HWND) -> (u32, Option<bool>) {
+        let pid = get_window_pid(hwnd);
+        (pid, is_process_elevated(pid))
+    }
+
+    fn list_virtual_desktop_ids(&self) -> Vec<GUID> {
+        list_virtual_desktop_ids()
+    }
+
+    fn get_window_desktop_number(&self, hwnd: HWND, desktops: &[GUID]) -> Option<usize> {
+        get_window_desktop_number(hwnd, desktops)
+    }
+
+    fn get_audio_module_paths(&self) -> HashSet<String> {
+        get_audio_module_paths()
+    }
+
+    fn get_foreground_window(&self) -> HWND {
+        get_foreground_window()
+    }
+
+    fn get_app_icon(
+        &self,
+        override_icons: &IndexMap<String, String>,
+        module_path: &str,
+        hwnd: HWND,
+        size: i32,
+    ) -> HICON {
+        get_app_icon(override_icons, module_path, hwnd, size)
+    }
+}
+
+/// Windows described up front, in z-order grouped by module path like `list_windows`.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockWinApi {
+    pub windows: IndexMap<String, Vec<(HWND, String)>>,
+    pub iconic_windows: std::collections::HashSet<isize>,
+    pub foreground_window: HWND,
+    /// All windows from the top, in the order they were added.
+    pub z_order: Vec<isize>,
+}
+
+#[cfg(test)]
+impl MockWinApi {
+    /// Adds a window, windows added first are on top.
+    pub fn with_window(mut self, module_path: &str, hwnd: isize) -> Self {
+        self.windows
+            .entry(module_path.to_string())
+            .or_default()
+            .push((HWND(hwnd as _), format!("window {hwnd}")));
+        self.z_order.push(hwnd);
+        self
+    }
+
+    pub fn with_iconic_window(mut self, module_path: &str, hwnd: isize) -> Self {
+        self.iconic_windows.insert(hwnd);
+        self.with_window(module_path, hwnd)
+    }
+}
+
+#[cfg(test)]
+impl WinApi for MockWinApi {
+    fn list_windows(
+        &self,
+        _options: &ListWindowsOptions,
+    ) -> Result<IndexMap<String, Vec<(HWND, String)>>> {
+        Ok(self.windows.clone())
+    }
+
+    fn get_z_order(&self) -> Result<Vec<HWND>> {
+        Ok(self.z_order.iter().map(|v| HWND(*v as _)).collect())
+    }
+
+    fn is_iconic_window(&self, hwnd: HWND) -> bool {
+        self.iconic_windows.contains(&(hwnd.0 as isize))
+    }
+
+    fn is_elevated_window(&self, _hwnd: HWND) -> bool {
+        false
+    }
+
+    fn get_window_process(&self, _hwnd: HWND) -> (u32, Option<bool>) {
+        (0, None)
+    }
+
+    fn list_virtual_desktop_ids(&self) -> Vec<GUID> {
+        vec![]
+    }
+
+    fn get_window_desktop_number(&self, _hwnd: HWND, _desktops: &[GUID]) -> Option<usize> {
+        None
+    }
+
+    fn get_audio_module_paths(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+
+    fn get_foreground_window(&self) -> HWND {
+        self.foreground_window
+    }
+
+    fn get_app_icon(
+        &self,
+        _override_icons: &IndexMap<String, String>,
+        _module_path: &str,
+        _hwnd: HWND,
+        _size: i32,
+    ) -> HICON {
+        HICON::default()
+    }
+}
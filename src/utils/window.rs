@@ -1,14 +1,16 @@
-use crate::utils::is_process_elevated;
+use crate::utils::{get_window_desktop_id, is_process_elevated, HandleWrapper};
 
 use anyhow::{anyhow, Result};
 use indexmap::IndexMap;
-use std::{ffi::c_void, mem::size_of, path::PathBuf};
-use windows::core::{BOOL, PWSTR};
+use std::{collections::HashSet, ffi::c_void, mem::size_of, path::PathBuf, rc::Rc};
+use windows::core::{BOOL, GUID, PWSTR};
 use windows::Win32::{
-    Foundation::{HWND, LPARAM, MAX_PATH, POINT, RECT},
+    Foundation::{GetLastError, ERROR_INSUFFICIENT_BUFFER, HWND, LPARAM, MAX_PATH, RECT},
     Graphics::{
         Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED, DWM_CLOAKED_SHELL},
-        Gdi::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+        Gdi::{
+            GetMonitorInfoW, MonitorFromWindow, HMONITOR, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+        },
     },
     System::{
         LibraryLoader::GetModuleFileNameW,
@@ -18,12 +20,16 @@ use windows::Win32::{
         },
     },
     UI::{
+        HiDpi::{GetDpiForMonitor, GetDpiForSystem, MDT_EFFECTIVE_DPI},
         Input::KeyboardAndMouse::{SendInput, INPUT, INPUT_MOUSE},
         WindowsAndMessaging::{
-            EnumWindows, GetCursorPos, GetForegroundWindow, GetWindow, GetWindowLongPtrW,
-            GetWindowPlacement, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
-            SetForegroundWindow, ShowWindow, GWL_EXSTYLE, GWL_STYLE, GWL_USERDATA, GW_OWNER,
-            SW_RESTORE, WINDOWPLACEMENT, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_ICONIC, WS_VISIBLE,
+            EnumWindows, FlashWindowEx, GetClassNameW, GetForegroundWindow, GetWindow,
+            GetWindowLongPtrW, GetWindowPlacement, GetWindowRect, GetWindowTextW,
+            GetWindowThreadProcessId, IsIconic, IsWindow, SetForegroundWindow,
+            SetWindowDisplayAffinity, ShowWindow, FLASHWINFO, FLASHW_TIMERNOFG, FLASHW_TRAY,
+            GWL_EXSTYLE, GWL_STYLE, GWL_USERDATA, GW_OWNER, SW_RESTORE, USER_DEFAULT_SCREEN_DPI,
+            WDA_EXCLUDEFROMCAPTURE, WDA_NONE, WINDOWPLACEMENT, WS_CAPTION, WS_EX_TOOLWINDOW,
+            WS_EX_TOPMOST, WS_ICONIC, WS_VISIBLE,
         },
     },
 };
@@ -75,21 +81,78 @@ pub fn is_small_window(hwnd: HWND) -> bool {
     width < 120 || height < 90
 }
 
-pub fn get_moinitor_rect() -> RECT {
+/// Whether the window covers its whole monitor without a caption, like games running full
+/// screen or borderless. The desktop covers its monitor too and is left out.
+pub fn is_full_screen_window(hwnd: HWND) -> bool {
+    let style = unsafe { GetWindowLongPtrW(hwnd, GWL_STYLE) } as u32;
+    if style & WS_CAPTION.0 == WS_CAPTION.0 || is_shell_window(hwnd) {
+        return false;
+    }
+    let mut rect = RECT::default();
+    if unsafe { GetWindowRect(hwnd, &mut rect) }.is_err() {
+        return false;
+    }
+    let monitor = get_moinitor_rect(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) });
+    rect.left <= monitor.left
+        && rect.top <= monitor.top
+        && rect.right >= monitor.right
+        && rect.bottom >= monitor.bottom
+}
+
+/// Whether the window is the desktop or a taskbar, which belong to the shell rather than an app.
+pub fn is_shell_window(hwnd: HWND) -> bool {
+    let mut buf = [0u16; 32];
+    let len = unsafe { GetClassNameW(hwnd, &mut buf) };
+    let class = String::from_utf16_lossy(&buf[..len.max(0) as usize]);
+    matches!(
+        class.as_str(),
+        "Progman" | "WorkerW" | "Shell_TrayWnd" | "Shell_SecondaryTrayWnd"
+    )
+}
+
+pub fn get_moinitor_rect(hmonitor: HMONITOR) -> RECT {
     unsafe {
         let mut mi = MONITORINFO {
             cbSize: std::mem::size_of::<MONITORINFO>() as u32,
             ..MONITORINFO::default()
         };
-        let mut cursor = POINT::default();
-        let _ = GetCursorPos(&mut cursor);
-
-        let hmonitor = MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST);
         let _ = GetMonitorInfoW(hmonitor, &mut mi);
         mi.rcMonitor
     }
 }
 
+/// Effective DPI of the monitor, 96 when it is unknown.
+pub fn get_moinitor_dpi(hmonitor: HMONITOR) -> u32 {
+    unsafe {
+        let (mut dpi_x, mut dpi_y) = (0, 0);
+        match GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) {
+            Ok(()) if dpi_x > 0 => dpi_x,
+            _ => USER_DEFAULT_SCREEN_DPI,
+        }
+    }
+}
+
+/// DPI of the primary monitor at sign-in, which the per-monitor DPIs usually match.
+pub fn get_system_dpi() -> u32 {
+    match unsafe { GetDpiForSystem() } {
+        0 => USER_DEFAULT_SCREEN_DPI,
+        dpi => dpi,
+    }
+}
+
+/// Leaves the window out of screenshots and screen recordings, which show what is behind it.
+/// Needs Windows 10 2004 or later.
+pub fn set_window_capture_exclusion(hwnd: HWND, exclude: bool) {
+    let affinity = if exclude {
+        WDA_EXCLUDEFROMCAPTURE
+    } else {
+        WDA_NONE
+    };
+    if let Err(err) = unsafe { SetWindowDisplayAffinity(hwnd, affinity) } {
+        debug!("Failed to set window display affinity, {err}");
+    }
+}
+
 pub fn get_window_size(hwnd: HWND) -> (i32, i32) {
     let mut placement = WINDOWPLACEMENT::default();
     let _ = unsafe { GetWindowPlacement(hwnd, &mut placement) };
@@ -105,10 +168,25 @@ pub fn get_exe_folder() -> Result<PathBuf> {
         .map(|v| v.to_path_buf())
 }
 
+/// Longest path Windows supports, for paths which don't fit in `MAX_PATH`.
+const MAX_LONG_PATH: u32 = 32768;
+
 pub fn get_exe_path() -> Vec<u16> {
-    let mut path = vec![0u16; MAX_PATH as _];
-    let size = unsafe { GetModuleFileNameW(None, &mut path) } as usize;
-    path[..size].to_vec()
+    let mut len = MAX_PATH;
+    loop {
+        let mut path = vec![0u16; len as _];
+        let size = unsafe { GetModuleFileNameW(None, &mut path) };
+        // The path is truncated when the buffer is too small
+        if size < len || len >= MAX_LONG_PATH {
+            return path[..size as usize].to_vec();
+        }
+        len = MAX_LONG_PATH;
+    }
+}
+
+/// Whether the process of the window runs as administrator.
+pub fn is_elevated_window(hwnd: HWND) -> bool {
+    is_process_elevated(get_window_pid(hwnd)) == Some(true)
 }
 
 pub fn get_window_pid(hwnd: HWND) -> u32 {
@@ -119,27 +197,48 @@ pub fn get_window_pid(hwnd: HWND) -> u32 {
 
 pub fn get_module_path(pid: u32) -> Option<String> {
     let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }.ok()?;
-    let mut len: u32 = MAX_PATH;
-    let mut name = vec![0u16; len as usize];
-    let ret = unsafe {
-        QueryFullProcessImageNameW(
-            handle,
-            PROCESS_NAME_WIN32,
-            PWSTR(name.as_mut_ptr()),
-            &mut len,
-        )
+    let handle = HandleWrapper::new(handle);
+    let mut capacity = MAX_PATH;
+    let name = loop {
+        let mut len = capacity;
+        let mut name = vec![0u16; len as usize];
+        let ret = unsafe {
+            QueryFullProcessImageNameW(
+                handle.get_handle(),
+                PROCESS_NAME_WIN32,
+                PWSTR(name.as_mut_ptr()),
+                &mut len,
+            )
+        };
+        if ret.is_ok() {
+            name.truncate(len as usize);
+            break name;
+        }
+        if unsafe { GetLastError() } != ERROR_INSUFFICIENT_BUFFER || capacity >= MAX_LONG_PATH {
+            return None;
+        }
+        capacity = MAX_LONG_PATH;
     };
-    if ret.is_err() || len == 0 {
-        return None;
-    }
-    unsafe { name.set_len(len as usize) };
-    let module_path = String::from_utf16_lossy(&name);
+    let module_path = normalize_module_path(&String::from_utf16_lossy(&name));
     if module_path.is_empty() {
         return None;
     }
     Some(module_path)
 }
 
+/// Drops the `\\?\` prefix of long paths, so the same exe is grouped, cached and matched
+/// the same way however its path was reported, e.g. `\\?\UNC\server\share\app.exe`
+/// becomes `\\server\share\app.exe`.
+pub fn normalize_module_path(module_path: &str) -> String {
+    if let Some(path) = module_path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{path}")
+    } else if let Some(path) = module_path.strip_prefix(r"\\?\") {
+        path.to_string()
+    } else {
+        module_path.to_string()
+    }
+}
+
 pub fn get_window_exe(hwnd: HWND) -> Option<String> {
     let pid = get_window_pid(hwnd);
     if pid == 0 {
@@ -149,7 +248,8 @@ pub fn get_window_exe(hwnd: HWND) -> Option<String> {
     module_path.split('\\').map(|v| v.to_string()).next_back()
 }
 
-pub fn set_foreground_window(hwnd: HWND) {
+/// Brings the window to the foreground, returns false if Windows refused to.
+pub fn set_foreground_window(hwnd: HWND) -> bool {
     // ref https://github.com/microsoft/PowerToys/blob/4cb72ee126caf1f720c507f6a1dbe658cd515366/src/modules/fancyzones/FancyZonesLib/WindowUtils.cpp#L191
     // SAFETY: All Windows API calls here are safe when hwnd is a valid window handle.
     // The SendInput trick is used to work around Windows' focus stealing prevention.
@@ -165,8 +265,24 @@ pub fn set_foreground_window(hwnd: HWND) {
 
         SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
 
-        let _ = SetForegroundWindow(hwnd);
+        SetForegroundWindow(hwnd).as_bool()
+    }
+}
+
+/// Flashes the taskbar button of the window until it comes to the foreground.
+pub fn flash_window(hwnd: HWND) {
+    let info = FLASHWINFO {
+        cbSize: size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: FLASHW_TRAY | FLASHW_TIMERNOFG,
+        uCount: 0,
+        dwTimeout: 0,
     };
+    let _ = unsafe { FlashWindowEx(&info) };
+}
+
+pub fn is_window(hwnd: HWND) -> bool {
+    unsafe { IsWindow(Some(hwnd)) }.as_bool()
 }
 
 pub fn get_foreground_window() -> HWND {
@@ -206,15 +322,43 @@ pub fn set_window_user_data(hwnd: HWND, ptr: isize) -> isize {
     unsafe { windows::Win32::UI::WindowsAndMessaging::SetWindowLongPtrW(hwnd, GWL_USERDATA, ptr) }
 }
 
+#[derive(Debug, Default, Clone)]
+pub struct ListWindowsOptions {
+    pub ignore_minimal: bool,
+    pub only_current_desktop: bool,
+    pub is_admin: bool,
+    /// Windows listed even if they would be filtered out. Shared, not copied for every switch.
+    pub pinned_windows: Rc<HashSet<isize>>,
+    /// Lowercase exe names of apps whose windows are listed even if they would be filtered out.
+    pub pinned_apps: Rc<HashSet<String>>,
+    /// Virtual desktops whose windows are never listed.
+    pub excluded_desktops: Rc<HashSet<GUID>>,
+    /// List windows of elevated processes even when not running as administrator.
+    pub include_elevated: bool,
+}
+
+impl ListWindowsOptions {
+    fn is_pinned(&self, hwnd: HWND) -> bool {
+        if self.pinned_windows.contains(&(hwnd.0 as isize)) {
+            return true;
+        }
+        !self.pinned_apps.is_empty()
+            && get_window_exe(hwnd).is_some_and(|v| self.pinned_apps.contains(&v.to_lowercase()))
+    }
+}
+
 /// Lists available windows
 ///
 /// Duo to the limitation of `OpenProcess`, this function will not list `Task Manager`
-/// and others which are running as administrator if `Switcher` is not `running as administrator`.
-pub fn list_windows(
-    ignore_minimal: bool,
-    only_current_desktop: bool,
-    is_admin: bool,
-) -> Result<IndexMap<String, Vec<(HWND, String)>>> {
+/// and others which are running as administrator if `Switcher` is not `running as administrator`,
+/// unless `include_elevated` is set.
+pub fn list_windows(options: &ListWindowsOptions) -> Result<IndexMap<String, Vec<(HWND, String)>>> {
+    let ListWindowsOptions {
+        ignore_minimal,
+        only_current_desktop,
+        is_admin,
+        ..
+    } = *options;
     let mut result: IndexMap<String, Vec<(HWND, String)>> = IndexMap::new();
     let mut hwnds: Vec<HWND> = Default::default();
     unsafe { EnumWindows(Some(enum_window), LPARAM(&mut hwnds as *mut _ as isize)) }
@@ -228,8 +372,9 @@ pub fn list_windows(
             && !is_tool
             && !is_topmost
             && !is_cloaked_window(hwnd, only_current_desktop)
-            && !is_small_window(hwnd);
-        if ok {
+            && !is_small_window(hwnd)
+            && !is_on_excluded_desktop(hwnd, &options.excluded_desktops);
+        if ok || (is_visible && options.is_pinned(hwnd)) {
             let title = get_window_title(hwnd);
             if !title.is_empty() && title != "Windows Input Experience" {
                 valid_hwnds.push((hwnd, title));
@@ -240,17 +385,18 @@ pub fn list_windows(
     for (hwnd, title) in valid_hwnds.into_iter() {
         let mut pid = get_window_pid(hwnd);
         let mut module_path = get_module_path(pid).unwrap_or_default();
-        if !is_valid_module_path(&module_path) {
-            if let Some((i, _)) = owner_hwnds.iter().enumerate().find(|(_, v)| **v == hwnd) {
-                pid = get_window_pid(hwnds[i]);
-                module_path = get_module_path(pid).unwrap_or_default();
-            }
+        if !is_valid_module_path(&module_path)
+            && let Some((i, _)) = owner_hwnds.iter().enumerate().find(|(_, v)| **v == hwnd)
+        {
+            pid = get_window_pid(hwnds[i]);
+            module_path = get_module_path(pid).unwrap_or_default();
         }
         if is_valid_module_path(&module_path) {
-            if !is_admin {
-                if let Some(true) = is_process_elevated(pid) {
-                    continue;
-                }
+            if !is_admin
+                && !options.include_elevated
+                && let Some(true) = is_process_elevated(pid)
+            {
+                continue;
             }
             result.entry(module_path).or_default().push((hwnd, title));
         }
@@ -259,6 +405,19 @@ pub fn list_windows(
     Ok(result)
 }
 
+/// Top-level windows from the top of the z-order, which follows the order they were used in.
+pub fn get_z_order() -> Result<Vec<HWND>> {
+    let mut hwnds: Vec<HWND> = Default::default();
+    unsafe { EnumWindows(Some(enum_window), LPARAM(&mut hwnds as *mut _ as isize)) }
+        .map_err(|e| anyhow!("Fail to get windows {}", e))?;
+    Ok(hwnds)
+}
+
+fn is_on_excluded_desktop(hwnd: HWND, excluded_desktops: &HashSet<GUID>) -> bool {
+    !excluded_desktops.is_empty()
+        && get_window_desktop_id(hwnd).is_some_and(|id| excluded_desktops.contains(&id))
+}
+
 fn is_valid_module_path(module_path: &str) -> bool {
     !module_path.is_empty() && module_path != "C:\\Windows\\System32\\ApplicationFrameHost.exe"
 }
@@ -268,3 +427,24 @@ extern "system" fn enum_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
     windows.push(hwnd);
     BOOL(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_module_path() {
+        assert_eq!(
+            normalize_module_path(r"\\?\C:\Program Files\App\app.exe"),
+            r"C:\Program Files\App\app.exe"
+        );
+        assert_eq!(
+            normalize_module_path(r"\\?\UNC\server\share\app.exe"),
+            r"\\server\share\app.exe"
+        );
+        assert_eq!(
+            normalize_module_path(r"\\server\share\app.exe"),
+            r"\\server\share\app.exe"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::ffi::c_void;
+use windows::Win32::{
+    Foundation::HWND,
+    System::LibraryLoader::{GetModuleHandleW, GetProcAddress},
+};
+use windows::core::{BOOL, s, w};
+
+const WCA_ACCENT_POLICY: u32 = 19;
+const ACCENT_DISABLED: u32 = 0;
+const ACCENT_ENABLE_ACRYLICBLURBEHIND: u32 = 4;
+
+#[repr(C)]
+struct AccentPolicy {
+    accent_state: u32,
+    accent_flags: u32,
+    /// Tint of the blur as `0xAABBGGRR`.
+    gradient_color: u32,
+    animation_id: u32,
+}
+
+#[repr(C)]
+struct WindowCompositionAttribData {
+    attrib: u32,
+    data: *mut c_void,
+    size: usize,
+}
+
+type SetWindowCompositionAttribute =
+    unsafe extern "system" fn(HWND, *mut WindowCompositionAttribData) -> BOOL;
+
+/// Blurs what is behind the transparent parts of the window, like the acrylic of Windows 11.
+///
+/// Uses the undocumented `SetWindowCompositionAttribute`, returns false when it is missing
+/// or fails, e.g. before Windows 10 1803.
+pub fn set_window_blur(hwnd: HWND, enable: bool) -> bool {
+    let Some(set_attribute) = get_set_window_composition_attribute() else {
+        return false;
+    };
+    let mut policy = AccentPolicy {
+        accent_state: if enable {
+            ACCENT_ENABLE_ACRYLICBLURBEHIND
+        } else {
+            ACCENT_DISABLED
+        },
+        accent_flags: 0,
+        // Acrylic misbehaves with a fully transparent tint, the painter draws the real tint
+        gradient_color: 0x01000000,
+        animation_id: 0,
+    };
+    let mut data = WindowCompositionAttribData {
+        attrib: WCA_ACCENT_POLICY,
+        data: &mut policy as *mut _ as _,
+        size: std::mem::size_of::<AccentPolicy>(),
+    };
+    unsafe { set_attribute(hwnd, &mut data) }.as_bool()
+}
+
+fn get_set_window_composition_attribute() -> Option<SetWindowCompositionAttribute> {
+    unsafe {
+        let module = GetModuleHandleW(w!("user32.dll")).ok()?;
+        let proc = GetProcAddress(module, s!("SetWindowCompositionAttribute"))?;
+        Some(std::mem::transmute::<
+            unsafe extern "system" fn() -> isize,
+            SetWindowCompositionAttribute,
+        >(proc))
+    }
+}
@@ -1,3 +1,12 @@
+use windows::Win32::{
+    Graphics::Gdi::{GetSysColor, SYS_COLOR_INDEX},
+    UI::{
+        Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW},
+        WindowsAndMessaging::{
+            SPI_GETHIGHCONTRAST, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, SystemParametersInfoW,
+        },
+    },
+};
 use windows::core::w;
 
 use super::RegKey;
@@ -11,3 +20,26 @@ pub fn is_light_theme() -> bool {
     };
     reg_key.get_int().map(|v| v == 1).unwrap_or(false)
 }
+
+/// Whether a contrast theme is turned on in the accessibility settings.
+pub fn is_high_contrast() -> bool {
+    let mut info = HIGHCONTRASTW {
+        cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+        ..Default::default()
+    };
+    let ret = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            info.cbSize,
+            Some(&mut info as *mut _ as _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    ret.is_ok() && info.dwFlags.contains(HCF_HIGHCONTRASTON)
+}
+
+/// System color as `0xRRGGBB`, contrast themes define them.
+pub fn get_sys_color(index: SYS_COLOR_INDEX) -> u32 {
+    let color = unsafe { GetSysColor(index) };
+    ((color & 0xff) << 16) | (color & 0xff00) | ((color >> 16) & 0xff)
+}
@@ -0,0 +1,87 @@
+//! Context menu of an app in the switch apps overlay, acting on its window.
+
+use anyhow::{Result, anyhow};
+use windows::Win32::{
+    Foundation::{HWND, POINT},
+    UI::WindowsAndMessaging::{
+        AppendMenuW, CreatePopupMenu, DestroyMenu, GetCursorPos, HMENU, MF_SEPARATOR, MF_STRING,
+        SetForegroundWindow, TPM_LEFTALIGN, TPM_NONOTIFY, TPM_RETURNCMD, TPM_TOPALIGN,
+        TrackPopupMenu,
+    },
+};
+use windows::core::{PCWSTR, w};
+
+const IDM_MINIMIZE: u32 = 1;
+const IDM_MAXIMIZE: u32 = 2;
+const IDM_MOVE_TO_NEXT_MONITOR: u32 = 3;
+const IDM_CLOSE: u32 = 4;
+
+const TEXT_MINIMIZE: PCWSTR = w!("Minimize");
+const TEXT_MAXIMIZE: PCWSTR = w!("Maximize");
+const TEXT_MOVE_TO_NEXT_MONITOR: PCWSTR = w!("Move to next monitor");
+const TEXT_CLOSE: PCWSTR = w!("Close");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAction {
+    Minimize,
+    Maximize,
+    MoveToNextMonitor,
+    Close,
+}
+
+/// Shows the menu at the cursor and waits for the user, `None` when it was dismissed.
+pub fn show_window_menu(owner: HWND, several_monitors: bool) -> Result<Option<WindowAction>> {
+    let hmenu = create_menu(several_monitors)?;
+    let mut cursor = POINT::default();
+    unsafe {
+        // Otherwise the menu stays open when clicking elsewhere
+        let _ = SetForegroundWindow(owner);
+        let _ = GetCursorPos(&mut cursor);
+    }
+    // The command is returned instead of posted, the clicked app is still known here
+    let id = unsafe {
+        TrackPopupMenu(
+            hmenu,
+            TPM_LEFTALIGN | TPM_TOPALIGN | TPM_RETURNCMD | TPM_NONOTIFY,
+            cursor.x,
+            cursor.y,
+            None,
+            owner,
+            None,
+        )
+    }
+    .0 as u32;
+    let _ = unsafe { DestroyMenu(hmenu) };
+    Ok(match id {
+        IDM_MINIMIZE => Some(WindowAction::Minimize),
+        IDM_MAXIMIZE => Some(WindowAction::Maximize),
+        IDM_MOVE_TO_NEXT_MONITOR => Some(WindowAction::MoveToNextMonitor),
+        IDM_CLOSE => Some(WindowAction::Close),
+        _ => None,
+    })
+}
+
+fn create_menu(several_monitors: bool) -> Result<HMENU> {
+    unsafe {
+        let hmenu = CreatePopupMenu().map_err(|err| anyhow!("Failed to create menu, {err}"))?;
+        let result = (|| {
+            AppendMenuW(hmenu, MF_STRING, IDM_MINIMIZE as usize, TEXT_MINIMIZE)?;
+            AppendMenuW(hmenu, MF_STRING, IDM_MAXIMIZE as usize, TEXT_MAXIMIZE)?;
+            if several_monitors {
+                AppendMenuW(
+                    hmenu,
+                    MF_STRING,
+                    IDM_MOVE_TO_NEXT_MONITOR as usize,
+                    TEXT_MOVE_TO_NEXT_MONITOR,
+                )?;
+            }
+            AppendMenuW(hmenu, MF_SEPARATOR, 0, PCWSTR::null())?;
+            AppendMenuW(hmenu, MF_STRING, IDM_CLOSE as usize, TEXT_CLOSE)
+        })();
+        if let Err(err) = result {
+            let _ = DestroyMenu(hmenu);
+            return Err(anyhow!("Failed to create menu, {err}"));
+        }
+        Ok(hmenu)
+    }
+}
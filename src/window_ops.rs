@@ -0,0 +1,304 @@
+//! Actions on top-level windows, bound to their own hotkeys.
+
+use std::{ffi::c_void, mem::size_of};
+
+use anyhow::{Result, anyhow, bail};
+use windows::Win32::{
+    Foundation::{COLORREF, HWND, LPARAM, RECT, WPARAM},
+    Graphics::{
+        Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute},
+        Gdi::{
+            EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST,
+            MONITORINFO, MonitorFromWindow,
+        },
+    },
+    UI::WindowsAndMessaging::{
+        GWL_EXSTYLE, GetLayeredWindowAttributes, GetWindowLongPtrW, GetWindowRect, HWND_NOTOPMOST,
+        HWND_TOPMOST, IsZoomed, LAYERED_WINDOW_ATTRIBUTES_FLAGS, LWA_ALPHA, PostMessageW,
+        SW_MAXIMIZE, SW_RESTORE, SW_SHOWMINNOACTIVE, SW_SHOWNOACTIVATE, SWP_NOACTIVATE, SWP_NOMOVE,
+        SWP_NOSIZE, SWP_NOZORDER, SetLayeredWindowAttributes, SetWindowLongPtrW, SetWindowPos,
+        ShowWindow, WM_CLOSE, WS_EX_LAYERED, WS_EX_TOPMOST,
+    },
+};
+use windows::core::BOOL;
+
+use crate::utils::{ListWindowsOptions, is_iconic_window, is_shell_window, list_windows};
+
+/// Minimizes every window of the current desktop except `keep`, without activating anything.
+///
+/// Returns the minimized windows, so they can be restored later.
+pub fn minimize_others(keep: HWND, is_admin: bool) -> Result<Vec<HWND>> {
+    let windows = list_windows(&ListWindowsOptions {
+        ignore_minimal: true,
+        only_current_desktop: true,
+        is_admin,
+        ..Default::default()
+    })?;
+    let hwnds: Vec<HWND> = windows
+        .values()
+        .flatten()
+        .map(|(hwnd, _)| *hwnd)
+        .filter(|hwnd| *hwnd != keep)
+        .collect();
+    for hwnd in &hwnds {
+        let _ = unsafe { ShowWindow(*hwnd, SW_SHOWMINNOACTIVE) };
+    }
+    debug!("minimized others {hwnds:?}");
+    Ok(hwnds)
+}
+
+/// Restores the windows minimized by [`minimize_others`] without taking the focus.
+pub fn restore_windows(hwnds: &[HWND]) {
+    for hwnd in hwnds.iter().rev() {
+        if is_iconic_window(*hwnd) {
+            let _ = unsafe { ShowWindow(*hwnd, SW_SHOWNOACTIVATE) };
+        }
+    }
+    debug!("restored {hwnds:?}");
+}
+
+/// Where to place a window, relative to the work area of its monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snap {
+    Left,
+    Right,
+    Maximize,
+}
+
+pub fn snap_window(hwnd: HWND, snap: Snap) -> Result<()> {
+    debug!("snap window {hwnd:?} {snap:?}");
+    if snap == Snap::Maximize {
+        let _ = unsafe { ShowWindow(hwnd, SW_MAXIMIZE) };
+        return Ok(());
+    }
+    if is_iconic_window(hwnd) || unsafe { IsZoomed(hwnd) }.as_bool() {
+        let _ = unsafe { ShowWindow(hwnd, SW_RESTORE) };
+    }
+
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    let mut mi = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !unsafe { GetMonitorInfoW(monitor, &mut mi) }.as_bool() {
+        bail!("Failed to get monitor info");
+    }
+    let work = mi.rcWork;
+    let half_width = (work.right - work.left) / 2;
+    let left = match snap {
+        Snap::Left => work.left,
+        _ => work.left + half_width,
+    };
+
+    // Windows 10+ have invisible resize borders, grow the window by them so the
+    // visible frame lines up with the work area
+    let (border_left, border_top, border_right, border_bottom) = invisible_borders(hwnd);
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            None,
+            left - border_left,
+            work.top - border_top,
+            half_width + border_left + border_right,
+            work.bottom - work.top + border_top + border_bottom,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        )
+    }
+    .map_err(|err| anyhow!("Failed to snap window, {err}"))
+}
+
+/// Asks the window to close, the app may still ask e.g. to save changes first.
+pub fn close_window(hwnd: HWND) -> Result<()> {
+    debug!("close window {hwnd:?}");
+    unsafe { PostMessageW(Some(hwnd), WM_CLOSE, WPARAM(0), LPARAM(0)) }
+        .map_err(|err| anyhow!("Failed to close window, {err}"))
+}
+
+/// Minimizes the window without activating another one.
+pub fn minimize_window(hwnd: HWND) {
+    debug!("minimize window {hwnd:?}");
+    let _ = unsafe { ShowWindow(hwnd, SW_SHOWMINNOACTIVE) };
+}
+
+/// Whether windows can be moved to another monitor.
+pub fn has_several_monitors() -> bool {
+    get_monitors().len() > 1
+}
+
+/// Moves the window to the same place on the next monitor, a maximized window stays
+/// maximized there. The desktop and the taskbars stay where they are.
+pub fn move_to_next_monitor(hwnd: HWND) -> Result<()> {
+    if is_shell_window(hwnd) {
+        debug!("not moving shell window {hwnd:?}");
+        return Ok(());
+    }
+    let monitors = get_monitors();
+    let current = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    let Some(index) = monitors.iter().position(|v| *v == current) else {
+        bail!("Failed to find the monitor of the window");
+    };
+    let next = monitors[(index + 1) % monitors.len()];
+    if next == current {
+        return Ok(());
+    }
+    debug!("move window {hwnd:?} to monitor {next:?}");
+    let from = get_work_area(current)?;
+    let to = get_work_area(next)?;
+
+    let maximized = unsafe { IsZoomed(hwnd) }.as_bool();
+    if maximized || is_iconic_window(hwnd) {
+        let _ = unsafe { ShowWindow(hwnd, SW_RESTORE) };
+    }
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect) }
+        .map_err(|err| anyhow!("Failed to get window rect, {err}"))?;
+    // Keep the distance to the top left of the work area, as long as the window fits
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+    let left = to.left + (rect.left - from.left).clamp(0, (to.right - to.left - width).max(0));
+    let top = to.top + (rect.top - from.top).clamp(0, (to.bottom - to.top - height).max(0));
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            None,
+            left,
+            top,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        )
+    }
+    .map_err(|err| anyhow!("Failed to move window, {err}"))?;
+    if maximized {
+        let _ = unsafe { ShowWindow(hwnd, SW_MAXIMIZE) };
+    }
+    Ok(())
+}
+
+fn get_work_area(monitor: HMONITOR) -> Result<RECT> {
+    let mut mi = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !unsafe { GetMonitorInfoW(monitor, &mut mi) }.as_bool() {
+        bail!("Failed to get monitor info");
+    }
+    Ok(mi.rcWork)
+}
+
+/// Lists the connected monitors.
+pub fn get_monitors() -> Vec<HMONITOR> {
+    let mut monitors: Vec<HMONITOR> = vec![];
+    let _ = unsafe {
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(enum_monitor),
+            LPARAM(&mut monitors as *mut _ as isize),
+        )
+    };
+    monitors
+}
+
+extern "system" fn enum_monitor(
+    hmonitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    // SAFETY: lparam is the Vec passed by get_monitors, alive for the enumeration
+    let monitors = unsafe { &mut *(lparam.0 as *mut Vec<HMONITOR>) };
+    monitors.push(hmonitor);
+    BOOL(1)
+}
+
+fn invisible_borders(hwnd: HWND) -> (i32, i32, i32, i32) {
+    let mut rect = RECT::default();
+    let mut frame = RECT::default();
+    let ok = unsafe { GetWindowRect(hwnd, &mut rect) }.is_ok()
+        && unsafe {
+            DwmGetWindowAttribute(
+                hwnd,
+                DWMWA_EXTENDED_FRAME_BOUNDS,
+                &mut frame as *mut RECT as *mut c_void,
+                size_of::<RECT>() as u32,
+            )
+        }
+        .is_ok();
+    if !ok {
+        return (0, 0, 0, 0);
+    }
+    (
+        frame.left - rect.left,
+        frame.top - rect.top,
+        rect.right - frame.right,
+        rect.bottom - frame.bottom,
+    )
+}
+
+/// Keeps the window above all others or lets it go back among them, returns whether it is
+/// always on top now.
+pub fn toggle_topmost(hwnd: HWND) -> Result<bool> {
+    let exstyle = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32;
+    let topmost = exstyle & WS_EX_TOPMOST.0 == 0;
+    let insert_after = if topmost {
+        HWND_TOPMOST
+    } else {
+        HWND_NOTOPMOST
+    };
+    debug!("window {hwnd:?} topmost {topmost}");
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            Some(insert_after),
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+    }
+    .map_err(|err| anyhow!("Failed to set window always on top, {err}"))?;
+    Ok(topmost)
+}
+
+/// Opacity levels the transparency hotkey cycles through.
+const OPACITY_LEVELS: [u8; 3] = [255, 204, 153];
+
+/// Sets the window to the next opacity level, 100% → 80% → 60% → 100%.
+///
+/// `owns_layered` tells whether the window was made layered by a previous call, the style is
+/// removed again once it is back to fully opaque. Returns the new value of `owns_layered`.
+pub fn cycle_opacity(hwnd: HWND, owns_layered: bool) -> Result<bool> {
+    let exstyle = unsafe { GetWindowLongPtrW(hwnd, GWL_EXSTYLE) } as u32;
+    let is_layered = exstyle & WS_EX_LAYERED.0 != 0;
+    let mut key = COLORREF(0);
+    let mut alpha = 255u8;
+    let mut flags = LAYERED_WINDOW_ATTRIBUTES_FLAGS(0);
+    if is_layered {
+        let _ = unsafe {
+            GetLayeredWindowAttributes(hwnd, Some(&mut key), Some(&mut alpha), Some(&mut flags))
+        };
+        if flags.0 & LWA_ALPHA.0 == 0 {
+            alpha = 255;
+        }
+    }
+    let alpha = OPACITY_LEVELS
+        .iter()
+        .find(|v| **v < alpha)
+        .copied()
+        .unwrap_or(255);
+    debug!("window {hwnd:?} opacity {alpha}");
+
+    if alpha == 255 && owns_layered {
+        unsafe { SetWindowLongPtrW(hwnd, GWL_EXSTYLE, (exstyle & !WS_EX_LAYERED.0) as _) };
+        return Ok(false);
+    }
+    if !is_layered {
+        unsafe { SetWindowLongPtrW(hwnd, GWL_EXSTYLE, (exstyle | WS_EX_LAYERED.0) as _) };
+    }
+    // Keep the color key of windows which are layered on their own
+    unsafe { SetLayeredWindowAttributes(hwnd, key, alpha, flags | LWA_ALPHA) }
+        .map_err(|err| anyhow!("Failed to set window opacity, {err}"))?;
+    Ok(owns_layered || !is_layered)
+}